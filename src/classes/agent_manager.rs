@@ -1,34 +1,68 @@
 use crate::classes::mint::mint;
-use crate::classes::{lead::lead_generation, publish::publish, remix::remix};
+use crate::classes::{
+    feed::feed, lead::lead_generation, publish::publish, remix::remix, respond::respond,
+};
 use crate::utils::helpers::fetch_metadata;
-use crate::utils::types::{Balance, Price};
+use crate::utils::types::{AgentError, Balance, Price};
 use crate::utils::{
-    constants::{ACCESS_CONTROLS, AGENTS, ARTISTS, LENS_CHAIN_ID},
+    analytics::{allot_slots, analytics_weights, track_posts, weigh_tasks},
+    constants::ARTISTS,
     contracts::{initialize_api, initialize_contracts},
+    control::{generation, is_cancelled, is_paused, wait_while_paused},
+    dry_run::{dry_run, report},
     lens::{handle_lens_account, handle_tokens},
-    types::{AgentActivity, AgentManager, Collection, SavedTokens, TripleAAgent, TripleAWorker},
+    image::register_image_settings,
+    llm::register_llm_settings,
+    metrics::{activity_attempted, activity_succeeded, gho_balance},
+    network::network,
+    respond::respond_worker,
+    memory::prune_memory,
+    persona::register_persona,
+    schedule::next_fire,
+    subgraph::agent_workers,
+    transactions::{register_gas_settings, send_transaction, succeeded},
+    types::{
+        AgentActivity, AgentManager, AgentStore, Collection, PlannedRun, RentPlan, RentPlanEntry,
+        RentQuote, SavedTokens, SubgraphCollection, SubgraphCollectionMetadata, TripleAAgent,
+        TripleAWorker,
+    },
 };
 use crate::ActivityType;
 use chrono::{Timelike, Utc};
 use dotenv::{from_filename, var};
 use ethers::{
-    contract::{self, ContractInstance, FunctionCall},
-    core::k256::ecdsa::SigningKey,
-    middleware::{Middleware, SignerMiddleware},
-    providers::{Http, Provider},
-    signers::{LocalWallet, Wallet},
-    types::{Address, Eip1559TransactionRequest, NameOrAddress, H160, H256, U256},
+    contract::FunctionCall,
+    types::{Address, H160, U256},
+};
+use std::{
+    str::FromStr,
+    time::{Duration, Instant},
 };
-use reqwest::Client;
-use serde_json::{json, Value};
-use std::{error::Error, io, str::FromStr, sync::Arc, time::Duration};
-use tokio::time;
+use tracing::{debug, error, info, info_span, warn, Instrument};
 
 impl AgentManager {
     pub fn new(agent: &TripleAAgent) -> Option<Self> {
         let contracts = initialize_contracts(agent.id);
         initialize_api();
 
+        let store = match AgentStore::open(agent.id, &agent.schedule.timezone) {
+            Ok(store) => store,
+            Err(err) => {
+                error!("Failed to open state store for agent_{}: {:?}", agent.id, err);
+                return None;
+            }
+        };
+        let state = store.load().unwrap_or_default();
+        register_gas_settings(agent.id, agent.gas.clone());
+        register_llm_settings(agent.id, agent.llm.clone());
+        register_image_settings(agent.id, agent.image.clone());
+        register_persona(agent);
+        prune_memory(agent.id);
+        let mut agent = agent.clone();
+        if let Some(last_active_time) = state.last_active_time {
+            agent.last_active_time = last_active_time;
+        }
+
         match contracts {
             Some((
                 access_controls_contract,
@@ -36,16 +70,17 @@ impl AgentManager {
                 collection_manager_contract,
                 market_contract,
             )) => Some(AgentManager {
-                agent: agent.clone(),
+                agent,
                 current_queue: Vec::new(),
                 agents_contract,
                 access_controls_contract,
                 market_contract,
-                tokens: None,
+                tokens: state.tokens,
                 collection_manager_contract,
+                store,
             }),
             None => {
-                eprintln!(
+                error!(
                     "Failed to initialize contracts for agent with ID: {}",
                     agent.id
                 );
@@ -54,253 +89,241 @@ impl AgentManager {
         }
     }
 
-    pub async fn resolve_activity(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
+    pub async fn resolve_activity(&mut self) -> Result<(), AgentError> {
         self.agent.last_active_time = Utc::now().num_seconds_from_midnight();
+        self.store.save_active(self.agent.last_active_time);
         if self.current_queue.len() > 0 {
             return Ok(());
         }
 
-        let collections_info = self.get_collections_info().await;
+        let info = self.get_collections_info().await.map_err(|err| {
+            error!("Error obtaining collection information: {}", err);
+            err
+        })?;
+        self.current_queue = info.clone();
 
-        match collections_info {
-            Ok(info) => {
-                self.current_queue = info.clone();
+        if info.len() < 1 {
+            info!(
+                "No collections for agent this round for agent_{}",
+                self.agent.id
+            );
+            return Ok(());
+        }
+
+        if let Err(err) = self.pay_rent().await {
+            error!("Error paying rent: {}", err);
+            self.current_queue.clear();
+            return Err(err);
+        }
 
-                if info.len() < 1 {
-                    println!(
-                        "No collections for agent this round for agent_{}",
-                        self.agent.id
+        self.queue_lens_activity().await
+    }
+
+    async fn check_gas_balance(&mut self) -> Result<(), AgentError> {
+        let result = self
+            .access_controls_contract
+            .get_native_grass_balance(
+                H160::from_str(&self.agent.wallet).map_err(AgentError::parse)?,
+            )
+            .call()
+            .await;
+
+        match result {
+            Ok(balance) => {
+                gho_balance(self.agent.id, balance);
+                info!(
+                    agent_id = self.agent.id,
+                    balance = %balance,
+                    "Agent GHO balance: {} GHO",
+                    balance.as_u128() as f64 / 1e18
+                );
+                let threshold = U256::from(10_000_000_000_000_000u128);
+
+                if balance < threshold {
+                    error!(
+                        agent_id = self.agent.id,
+                        wallet = %self.agent.wallet,
+                        "Insufficient GHO balance: {} GHO, minimum 0.01 GHO. Send GHO (the native gas token on Lens) to the agent wallet",
+                        balance.as_u128() as f64 / 1e18
                     );
-                    return Ok(());
+                    return Err(AgentError::config(
+                        "Insufficient GHO balance. Please top up your agent's wallet.",
+                    ));
                 }
 
-                let _ = self.queue_lens_activity().await;
-                // match self.pay_rent().await {
-                //     Ok(_) => {
-                //         let _ = self.queue_lens_activity().await;
-                //     }
-                //     Err(err) => {
-                //         eprintln!("Error paying rent: {:?}", err);
-                //     }
-                // }
+                info!("GHO balance sufficient for transactions");
+                Ok(())
             }
             Err(err) => {
-                eprintln!("Error obtaining collection information: {:?}", err);
+                error!("Error checking agent GHO balance: {}", err);
+                Err(err.into())
             }
         }
-
-        Ok(())
     }
 
-    async fn check_gas_balance(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let method = self.access_controls_contract.method::<_, U256>(
-            "getNativeGrassBalance",
-            H160::from_str(&self.agent.wallet.clone()).unwrap(),
-        );
+    async fn plan_rent(&self) -> Result<RentPlan, AgentError> {
+        let mut plan = RentPlan::default();
+        let preference = rent_preference()?;
 
-        match method {
-            Ok(call) => {
-                let result: Result<
-                    U256,
-                    contract::ContractError<
-                        SignerMiddleware<Arc<Provider<Http>>, LocalWallet>,
-                    >,
-                > = call.call().await;
-
-                match result {
-                    Ok(balance) => {
-                        println!("Agent GHO Balance: {} ({}  GHO)\n", balance, balance.as_u128() as f64 / 1e18);
-                        let threshold = U256::from(10_000_000_000_000_000u128);
-
-                        if balance < threshold {
-                            eprintln!("\n❌ ERROR: Insufficient GHO balance!");
-                            eprintln!("Current balance: {} GHO", balance.as_u128() as f64 / 1e18);
-                            eprintln!("Minimum required: 0.01 GHO");
-                            eprintln!("\nPlease send GHO to your agent's wallet: {}", self.agent.wallet);
-                            eprintln!("GHO is the native gas token on Lens mainnet.\n");
-                            return Err("Insufficient GHO balance. Please top up your agent's wallet.".into());
-                        }
+        for collection in &self.current_queue {
+            let mut quotes = vec![];
+
+            for price in &collection.collection.prices {
+                let token = match H160::from_str(&price.token) {
+                    Ok(token) => token,
+                    Err(err) => {
+                        error!("Invalid rent token {}: {}", price.token, err);
+                        continue;
+                    }
+                };
 
-                        println!("✓ GHO balance sufficient for transactions\n");
-                        Ok(())
+                let rent = match self.calculate_rent(collection, &price.token).await {
+                    Ok(rent) => rent,
+                    Err(err) => {
+                        error!("Error calculating rent: {}", err);
+                        continue;
                     }
+                };
+
+                match self.rent_balance(token, collection.collection_id).await {
+                    Ok(balance) => quotes.push(RentQuote {
+                        token,
+                        rent,
+                        balance,
+                    }),
                     Err(err) => {
-                        eprintln!("Error checking agent GHO balance: {}", err);
-                        Err(Box::new(err))
+                        error!("Error calling token balance: {}", err);
                     }
                 }
             }
-            Err(err) => {
-                eprintln!("Error creating balance check method: {}", err);
-                Err(Box::new(err))
+
+            if let Some(quote) = pick_quote(&quotes, &preference) {
+                plan.funded.push(RentPlanEntry {
+                    collection_id: collection.collection_id,
+                    token: quote.token,
+                    rent: quote.rent,
+                    balance: quote.balance,
+                });
+            } else if ARTISTS
+                .iter()
+                .any(|a| a.eq_ignore_ascii_case(&collection.collection.artist))
+            {
+                plan.exempt.push(collection.collection_id);
+            } else {
+                plan.unfunded.push(collection.collection_id);
             }
-        }
-    }
 
-    async fn pay_rent(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let mut rent_tokens: Vec<H160> = vec![];
-        let mut rent_collection_ids: Vec<U256> = vec![];
+            plan.quotes.push((collection.collection_id, quotes));
+        }
 
-        let _ = self.check_gas_balance().await;
+        Ok(plan)
+    }
 
-        println!("Queue {:?}", &self.current_queue);
+    fn report_rent_plan(&self, plan: &RentPlan) {
+        info!("Rent plan for agent_{}:", self.agent.id);
+        for entry in &plan.funded {
+            info!(
+                "  collection {} -> {:?} rent {} of balance {}",
+                entry.collection_id, entry.token, entry.rent, entry.balance
+            );
+        }
+        for collection_id in &plan.exempt {
+            info!("  collection {} -> artist collection, no rent", collection_id);
+        }
+        for collection_id in &plan.unfunded {
+            info!("  collection {} -> unfunded, dropping worker", collection_id);
+        }
 
-        for collection in &self.current_queue {
-            println!("Collection {:?}", &collection.collection_id);
+        if dry_run() {
+            report(
+                self.agent.id,
+                "rent_plan",
+                serde_json::to_value(plan).unwrap_or_default(),
+            );
+        }
+    }
 
-            for price in &collection.collection.prices {
-                println!("Price {:?}", &price);
+    async fn rent_balance(
+        &self,
+        token: H160,
+        collection_id: U256,
+    ) -> Result<U256, AgentError> {
+        Ok(self
+            .agents_contract
+            .get_agent_rent_balance(token, U256::from(self.agent.id), collection_id)
+            .call()
+            .await?)
+    }
 
-                let method = self.agents_contract.method::<_, U256>(
-                    "getAgentRentBalance",
-                    (
-                        H160::from_str(&price.token).unwrap(),
-                        self.agent.id,
-                        collection.collection.collection_id,
-                    ),
-                );
+    async fn pay_rent(&mut self) -> Result<(), AgentError> {
+        self.check_gas_balance().await?;
 
-                match method {
-                    Ok(call) => {
-                        let result: Result<
-                            U256,
-                            contract::ContractError<
-                                SignerMiddleware<Arc<Provider<Http>>, LocalWallet>,
-                            >,
-                        > = call.call().await;
-
-                        match result {
-                            Ok(balance) => {
-                                println!("Balance: {}\n", balance);
-
-                                match self.calculate_rent(collection, &price.token).await {
-                                    Ok(cycle_rent) => {
-                                        if balance >= cycle_rent {
-                                            rent_tokens.push(H160::from_str(&price.token).unwrap());
-                                            rent_collection_ids.push(collection.collection_id);
-                                            break;
-                                        }
-                                    }
-                                    Err(err) => {
-                                        eprintln!("Error calculating rent: {}", err);
-                                    }
-                                }
-                            }
-                            Err(err) => {
-                                eprintln!("Error calling token balance: {}", err);
-                            }
-                        }
-                    }
-                    Err(err) => {
-                        eprintln!("Error getting active token balance: {}", err);
-                    }
-                }
-            }
-        }
+        let plan = self.plan_rent().await?;
+        self.report_rent_plan(&plan);
 
-        println!(
-            "Rent: {:?} {:?}\n",
-            rent_collection_ids.clone(),
-            rent_tokens
-        );
+        let rent_tokens: Vec<H160> = plan.funded.iter().map(|entry| entry.token).collect();
+        let rent_collection_ids: Vec<U256> =
+            plan.funded.iter().map(|entry| entry.collection_id).collect();
 
         if rent_collection_ids.len() > 0 {
-            println!(
-                "Method data {:?} {:?} {}",
-                rent_tokens,
-                rent_collection_ids.clone(),
+            debug!(
+                tokens = ?rent_tokens,
+                collection_ids = ?rent_collection_ids,
+                "payRent call data for agent_{}",
                 self.agent.id
             );
-            let method = self
-                .agents_contract
-                .method::<(Vec<Address>, Vec<U256>, U256), H256>(
-                    "payRent",
-                    (
-                        rent_tokens.clone(),
-                        rent_collection_ids.clone(),
-                        U256::from(self.agent.id as u64),
-                    ),
-                );
-
-            match method {
-                Ok(call) => {
-                    let FunctionCall { tx, .. } = call;
-
-                    if let Some(tx_request) = tx.as_eip1559_ref() {
-                        let gas_price = U256::from(500_000_000_000u64);
-                        let max_priority_fee = U256::from(25_000_000_000u64);
-                        let gas_limit = U256::from(300_000);
-
-                        let client = self.agents_contract.client().clone();
-                        let chain_id = *LENS_CHAIN_ID;
-                        let req = Eip1559TransactionRequest {
-                            from: Some(self.agent.wallet.parse::<Address>().unwrap()),
-                            to: Some(NameOrAddress::Address(AGENTS.parse::<Address>().unwrap())),
-                            gas: Some(gas_limit),
-                            value: tx_request.value,
-                            data: tx_request.data.clone(),
-                            max_priority_fee_per_gas: Some(max_priority_fee),
-                            max_fee_per_gas: Some(gas_price + max_priority_fee),
-                            chain_id: Some(chain_id.into()),
-                            ..Default::default()
-                        };
-
-                        let pending_tx = match client.send_transaction(req, None).await {
-                            Ok(tx) => tx,
-                            Err(e) => {
-                                eprintln!("Error sending the transaction for payRent: {:?}", e);
-                                Err(Box::new(e))?
-                            }
-                        };
-
-                        let tx_hash = match pending_tx.confirmations(1).await {
-                            Ok(hash) => hash,
-                            Err(e) => {
-                                eprintln!("Error with transaction confirmation: {:?}", e);
-                                Err(Box::new(e))?
-                            }
-                        };
+            let FunctionCall { tx, .. } = self.agents_contract.pay_rent(
+                rent_tokens.clone(),
+                rent_collection_ids.clone(),
+                U256::from(self.agent.id as u64),
+            );
+            let client = self.agents_contract.client();
 
-                        println!("Agent {} TX Hash: {:?}", self.agent.id, tx_hash);
+            match send_transaction(
+                &client,
+                self.agent.id,
+                "payRent",
+                network().agents.parse::<Address>().map_err(AgentError::parse)?,
+                tx.data().cloned(),
+                tx.value().cloned(),
+            )
+            .await
+            {
+                Ok(sent) if sent.receipt.as_ref().map(succeeded).unwrap_or(true) => {
+                    info!(tx_hash = ?sent.hash, "payRent sent for agent_{}", self.agent.id);
 
-                        self.current_queue
-                            .retain(|item| rent_collection_ids.contains(&item.collection_id));
+                    self.current_queue
+                        .retain(|item| !plan.unfunded.contains(&item.collection_id));
 
-                        println!(
-                            "Final queue for agent{}: {:?}",
-                            self.agent.id, self.current_queue
-                        );
+                    info!(
+                        "Final queue for agent_{}: {} collection(s)",
+                        self.agent.id,
+                        self.current_queue.len()
+                    );
 
-                        Ok(())
-                    } else {
-                        self.current_queue = Vec::new();
-                        eprintln!("Error in sending Transaction");
-                        Err(Box::new(io::Error::new(
-                            io::ErrorKind::Other,
-                            "Error in sending Transaction",
-                        )))
-                    }
+                    Ok(())
+                }
+                Ok(sent) => {
+                    self.current_queue = Vec::new();
+                    warn!("payRent reverted: {}", sent.hash);
+                    Err(AgentError::reverted(format!("payRent reverted: {:?}", sent.hash)))
                 }
-
                 Err(err) => {
                     self.current_queue = Vec::new();
-                    eprintln!("Error in create method for payRent: {:?}", err);
-                    Err(Box::new(err))
+                    error!("Error sending the transaction for payRent: {:?}", err);
+                    Err(err)
                 }
             }
         } else {
-            println!(
+            info!(
                 "No collection Ids with sufficient tokens for agent_{}",
                 self.agent.id
             );
 
-            self.current_queue.retain(|item| {
-                ARTISTS
-                    .iter()
-                    .any(|a| a.eq_ignore_ascii_case(&item.collection.artist))
-            });
+            self.current_queue
+                .retain(|item| plan.exempt.contains(&item.collection_id));
 
-            println!("Queue retained for artists {:?}", self.current_queue);
+            info!("Queue retained for artists: {} collection(s)", self.current_queue.len());
 
             Ok(())
         }
@@ -308,242 +331,136 @@ impl AgentManager {
 
     async fn get_collections_info(
         &self,
-    ) -> Result<Vec<AgentActivity>, Box<dyn Error + Send + Sync>> {
-        let client = Client::new();
-        let query = json!({
-            "query": r#"
-            query ($SkyhuntersAgentManager_id: Int!) {
-                agentCreateds(where: { SkyhuntersAgentManager_id: $SkyhuntersAgentManager_id }, first: 1) {
-                    balances {
-                     rentBalance
-                     bonusBalance
-                     collectionId
-                    }
-                    workers {
-                        publish
-                        remix
-                        lead
-                        mint
-                        leadFrequency
-                        publishFrequency
-                        mintFrequency
-                        remixFrequency
-                        instructions
-                        collectionId
-                        collection {
-                            artist
-                            collectionId
-                            uri
-                            metadata {
-                                description
-                                title
-                                image
-                            }
-                            prices {
-                                price
-                                token
-                            }  
-                        }
-                    }
-                }
-            }
-            "#,
-            "variables": {
-                "SkyhuntersAgentManager_id": self.agent.id
-            }
-        });
-        let graph_url = "https://triplea.digitalax.xyz";
-        let response = time::timeout(Duration::from_secs(60), async {
-            let res = client
-                .post(graph_url)
-                .json(&query)
-                .send()
-                .await?;
-
-            res.json::<Value>().await
-        })
-        .await;
-
-        match response {
-            Ok(result) => match result {
-                Ok(result) => {
-                    let empty_vec = vec![];
-                    let agent_createds = result["data"]["agentCreateds"]
-                        .as_array()
-                        .unwrap_or(&empty_vec);
-                    let mut activities = Vec::new();
-
-                    for agent_created in agent_createds {
-                        let balances = agent_created["balances"]
-                            .as_array()
-                            .unwrap_or(&vec![])
-                            .iter()
-                            .map(|balance| {
-                                let collection_id = U256::from_dec_str(
-                                    balance["collectionId"].as_str().unwrap_or("0"),
-                                )
-                                .unwrap_or_default();
-
-                                (
-                                    collection_id,
-                                    Balance {
-                                        rent_balance: U256::from_dec_str(
-                                            balance["rentBalance"].as_str().unwrap_or("0"),
-                                        )
-                                        .unwrap_or_default(),
-                                        bonus_balance: U256::from_dec_str(
-                                            balance["bonusBalance"].as_str().unwrap_or("0"),
-                                        )
-                                        .unwrap_or_default(),
-                                    },
-                                )
-                            })
-                            .collect::<std::collections::HashMap<_, _>>();
-
-                        for worker in agent_created["workers"].as_array().unwrap_or(&vec![]) {
-                            let collection_id =
-                                U256::from_dec_str(worker["collectionId"].as_str().unwrap_or("0"))
-                                    .unwrap_or_default();
-
-                            let artist = worker["collection"]["artist"]
-                                .as_str()
-                                .unwrap_or_default()
-                                .to_string();
-
-                            let username =
-                                handle_lens_account(&artist, true).await.unwrap_or_default();
-
-                            let default_balance = Balance {
-                                rent_balance: U256::zero(),
-                                bonus_balance: U256::zero(),
-                            };
-                            let balance: &Balance =
-                                balances.get(&collection_id).unwrap_or(&default_balance);
-
-                            let metadata = worker["collection"]["metadata"].clone();
-                            let is_metadata_empty = metadata.is_null()
-                                || metadata.as_object().map(|o| o.is_empty()).unwrap_or(false);
-
-                            let metadata_filled = if is_metadata_empty {
-                                if let Some(uri) = worker["collection"]["uri"].as_str() {
-                                    fetch_metadata(uri).await.unwrap_or(json!({}))
-                                } else {
-                                    json!({})
-                                }
-                            } else {
-                                metadata
-                            };
-                            activities.push(AgentActivity {
-                                collection: Collection {
-                                    collection_id,
-                                    artist,
-                                    username,
-                                    image: metadata_filled["image"]
-                                        .as_str()
-                                        .unwrap_or_default()
-                                        .to_string(),
-                                    title: metadata_filled["title"]
-                                        .as_str()
-                                        .unwrap_or_default()
-                                        .to_string(),
-                                    description: metadata_filled["description"]
-                                        .as_str()
-                                        .unwrap_or_default()
-                                        .to_string(),
-                                    prices: worker["collection"]["prices"]
-                                        .as_array()
-                                        .unwrap_or(&vec![])
-                                        .iter()
-                                        .filter_map(|v| {
-                                            Some(Price {
-                                                price: v["price"]
-                                                    .as_str()
-                                                    .and_then(|s| U256::from_dec_str(s).ok())
-                                                    .unwrap_or_default(),
-                                                token: v["token"]
-                                                    .as_str()
-                                                    .unwrap_or_default()
-                                                    .to_string(),
-                                            })
-                                        })
-                                        .collect(),
-                                },
-                                token: worker["token"].as_str().unwrap_or_default().to_string(),
-                                balance: balance.clone(),
-                                collection_id,
-                                worker: TripleAWorker {
-                                    instructions: worker["instructions"]
-                                        .as_str()
-                                        .unwrap_or_default()
-                                        .to_string(),
-                                    lead: worker["lead"].as_bool().unwrap_or_default(),
-                                    publish: worker["publish"].as_bool().unwrap_or_default(),
-                                    mint: worker["mint"].as_bool().unwrap_or_default(),
-                                    remix: worker["remix"].as_bool().unwrap_or_default(),
-                                    lead_frequency: U256::from_dec_str(
-                                        worker["leadFrequency"].as_str().unwrap_or("0"),
-                                    )
-                                    .unwrap_or_default(),
-                                    publish_frequency: U256::from_dec_str(
-                                        worker["publishFrequency"].as_str().unwrap_or("0"),
-                                    )
-                                    .unwrap_or_default(),
-                                    remix_frequency: U256::from_dec_str(
-                                        worker["remixFrequency"].as_str().unwrap_or("0"),
-                                    )
-                                    .unwrap_or_default(),
-                                    mint_frequency: U256::from_dec_str(
-                                        worker["mintFrequency"].as_str().unwrap_or("0"),
-                                    )
-                                    .unwrap_or_default(),
-                                },
-                            });
-                        }
-                    }
+    ) -> Result<Vec<AgentActivity>, AgentError> {
+        let agent_workers = match agent_workers(self.agent.id).await? {
+            Some(agent_workers) => agent_workers,
+            None => return Ok(vec![]),
+        };
 
-                    Ok(activities)
-                }
-                Err(err) => {
-                    eprintln!("Error in response: {:?}", err);
-                    Err(Box::new(err))
-                }
-            },
-            Err(err) => {
-                eprintln!("Time out: {:?}", err);
-                Err(Box::new(io::Error::new(
-                    io::ErrorKind::TimedOut,
-                    format!("Timeout: {:?}", err),
-                )))
-            }
+        let balances = agent_workers
+            .balances
+            .iter()
+            .map(|balance| {
+                (
+                    balance.collection_id,
+                    Balance {
+                        rent_balance: balance.rent_balance,
+                        bonus_balance: balance.bonus_balance,
+                    },
+                )
+            })
+            .collect::<std::collections::HashMap<_, _>>();
+
+        let mut activities = Vec::new();
+
+        for worker in agent_workers.workers {
+            let collection_id = worker.collection_id;
+            let respond = respond_worker(&self.agent.respond, collection_id);
+            let collection = worker.collection.unwrap_or(SubgraphCollection {
+                artist: String::new(),
+                uri: None,
+                metadata: None,
+                prices: vec![],
+            });
+
+            let username = handle_lens_account(&collection.artist, true)
+                .await
+                .unwrap_or_default();
+
+            let balance = balances.get(&collection_id).cloned().unwrap_or(Balance {
+                rent_balance: U256::zero(),
+                bonus_balance: U256::zero(),
+            });
+
+            let metadata = match (collection.metadata, &collection.uri) {
+                (Some(metadata), _) => metadata,
+                (None, Some(uri)) => fetch_metadata(uri)
+                    .await
+                    .and_then(|value| {
+                        serde_json::from_value::<SubgraphCollectionMetadata>(value).ok()
+                    })
+                    .unwrap_or_default(),
+                (None, None) => SubgraphCollectionMetadata::default(),
+            };
+
+            activities.push(AgentActivity {
+                collection: Collection {
+                    collection_id,
+                    artist: collection.artist,
+                    username,
+                    image: metadata.image,
+                    title: metadata.title,
+                    description: metadata.description,
+                    prices: collection
+                        .prices
+                        .into_iter()
+                        .map(|price| Price {
+                            price: price.price,
+                            token: price.token,
+                        })
+                        .collect(),
+                },
+                token: String::new(),
+                balance,
+                collection_id,
+                worker: TripleAWorker {
+                    instructions: worker.instructions,
+                    lead: worker.lead,
+                    publish: worker.publish,
+                    mint: worker.mint,
+                    remix: worker.remix,
+                    lead_frequency: worker.lead_frequency,
+                    publish_frequency: worker.publish_frequency,
+                    remix_frequency: worker.remix_frequency,
+                    mint_frequency: worker.mint_frequency,
+                    respond: respond.frequency > 0,
+                    respond_frequency: U256::from(respond.frequency),
+                    feed: !self.agent.feeds.is_empty() && self.agent.feed_frequency > 0,
+                    feed_frequency: U256::from(self.agent.feed_frequency),
+                },
+            });
         }
-    }
 
-    async fn queue_lens_activity(&mut self) -> Result<(), Box<dyn Error + Send + Sync>> {
-        let current_time = Utc::now().num_seconds_from_midnight() as i64;
-        let remaining_time = self.agent.clock as i64 - current_time;
+        Ok(activities)
+    }
 
-        let adjusted_remaining_time = if remaining_time > 0 {
-            remaining_time
-        } else {
-            7200
+    async fn queue_lens_activity(&mut self) -> Result<(), AgentError> {
+        let span = self.agent.schedule.span as i64;
+        let adjusted_remaining_time = match next_fire(&self.agent.schedule, Utc::now()) {
+            Some(next) => (next - Utc::now()).num_seconds().clamp(1, span.max(1)),
+            None => span,
         };
 
         let queue = self.current_queue.clone();
         let queue_size = queue.len() as i64;
 
-        let interval = if queue_size > 0 {
+        let interval = if queue_size > 0 && !dry_run() {
             adjusted_remaining_time / queue_size
         } else {
             0
         };
 
-        println!(
+        info!(
             "Queue Length for Agent_{} before loop: {}",
             self.agent.id,
             self.current_queue.len()
         );
 
+        let run = Utc::now().timestamp();
+        let since = generation(self.agent.id);
+        let planned: Vec<_> = queue
+            .iter()
+            .map(|activity| (activity.collection_id, plan_tasks(activity)))
+            .collect();
+        let slots = allot_slots(&analytics_weights(self.agent.id), &planned);
+
         for activity in queue {
+            if is_cancelled(self.agent.id, since) {
+                info!("Queued activity for agent_{} was cancelled", self.agent.id);
+                self.current_queue.clear();
+                break;
+            }
+
             let tokens = handle_tokens(
                 self.agent.id,
                 &self.agent.account_address,
@@ -553,39 +470,53 @@ impl AgentManager {
 
             match tokens {
                 Ok(new_tokens) => {
+                    remember_tokens(&self.store, self.tokens.as_ref(), &new_tokens);
                     self.tokens = Some(new_tokens);
-                    self.current_queue
-                        .retain(|item| item.collection_id == activity.collection_id);
-
-                    let agent = self.agent.clone();
-                    let tokens = self.tokens.clone();
-                    let collection_contract = self.collection_manager_contract.clone();
-                    let agents_contract = self.agents_contract.clone();
-                    let market_contract = self.market_contract.clone();
-
-                    tokio::spawn(async move {
-                        cycle_activity(
-                            &agent,
-                            tokens,
-                            &activity,
-                            interval,
-                            collection_contract,
-                            agents_contract,
-                            market_contract,
-                        )
-                        .await;
-                    });
+                    dequeue(&mut self.current_queue, activity.collection_id);
+
+                    let tasks = plan_tasks(&activity);
+                    self.store.save_plan(run, &activity, &tasks);
+
+                    let (_, skipped) = weigh_tasks(
+                        &slots,
+                        activity.collection_id,
+                        tasks.iter().cloned().enumerate().collect(),
+                    );
+                    if !skipped.is_empty() {
+                        info!(
+                            collection_id = %activity.collection_id,
+                            "Analytics skipped {} of {} task(s): {:?}",
+                            skipped.len(),
+                            tasks.len(),
+                            skipped
+                        );
+                    }
+                    for (index, task) in &skipped {
+                        self.store.save_skipped(run, activity.collection_id, *index, task);
+                    }
+
+                    self.spawn_cycle(
+                        PlannedRun {
+                            run,
+                            day: self.store.day(),
+                            activity,
+                            tasks,
+                            completed: skipped.into_iter().map(|(index, _)| index).collect(),
+                        },
+                        interval,
+                    );
                 }
 
                 Err(err) => {
-                    eprintln!("Error renewing Lens tokens: {:?}", err);
+                    error!("Error renewing Lens tokens: {:?}", err);
+                    dequeue(&mut self.current_queue, activity.collection_id);
                 }
             }
 
             tokio::time::sleep(std::time::Duration::from_secs(interval as u64)).await;
         }
 
-        println!(
+        info!(
             "Queue Length for Agent_{} after finishing: {}",
             self.agent.id,
             self.current_queue.len()
@@ -594,160 +525,153 @@ impl AgentManager {
         Ok(())
     }
 
+    pub async fn resume_pending(&mut self) {
+        let pending = self.store.pending_plans();
+        if pending.is_empty() {
+            return;
+        }
+
+        info!(
+            "Resuming {} unfinished plan(s) for agent_{}",
+            pending.len(),
+            self.agent.id
+        );
+
+        match handle_tokens(self.agent.id, &self.agent.account_address, self.tokens.clone()).await
+        {
+            Ok(new_tokens) => {
+                remember_tokens(&self.store, self.tokens.as_ref(), &new_tokens);
+                self.tokens = Some(new_tokens);
+            }
+            Err(err) => {
+                error!("Error renewing Lens tokens on resume: {:?}", err);
+                return;
+            }
+        }
+
+        let interval = self.agent.schedule.span as i64 / pending.len() as i64;
+        for plan in pending {
+            self.spawn_cycle(plan, interval);
+        }
+    }
+
+    fn spawn_cycle(&self, plan: PlannedRun, interval: i64) {
+        let manager = self.clone();
+
+        tokio::spawn(async move {
+            cycle_activity(manager, plan, interval).await;
+        });
+    }
+
     async fn calculate_rent(
         &self,
         activity: &AgentActivity,
         token: &String,
-    ) -> Result<U256, Box<dyn Error + Send + Sync>> {
+    ) -> Result<U256, AgentError> {
+        let token = H160::from_str(token).map_err(AgentError::parse)?;
         let mut rent_total = U256::from(0);
 
-        if activity.worker.lead {
-            let rent_method = self
-                .access_controls_contract
-                .method::<_, U256>("getTokenCycleRentLead", H160::from_str(token).unwrap());
-
-            match rent_method {
-                Ok(rent_call) => {
-                    let token_result: Result<
-                        U256,
-                        contract::ContractError<SignerMiddleware<Arc<Provider<Http>>, LocalWallet>>,
-                    > = rent_call.call().await;
-
-                    match token_result {
-                        Ok(rent_threshold) => {
-                            rent_total += rent_threshold * activity.worker.lead_frequency;
-                        }
-                        Err(err) => {
-                            eprintln!("Error in rent method lead: {}", err);
-                        }
-                    }
-                }
-                Err(err) => {
-                    eprintln!("Error in rent method lead: {}", err);
-                }
+        let contract = &self.access_controls_contract;
+
+        for (enabled, method, rent_call, frequency) in [
+            (
+                activity.worker.lead,
+                "getTokenCycleRentLead",
+                contract.get_token_cycle_rent_lead(token),
+                activity.worker.lead_frequency,
+            ),
+            (
+                activity.worker.publish,
+                "getTokenCycleRentPublish",
+                contract.get_token_cycle_rent_publish(token),
+                activity.worker.publish_frequency,
+            ),
+            (
+                activity.worker.remix,
+                "getTokenCycleRentRemix",
+                contract.get_token_cycle_rent_remix(token),
+                activity.worker.remix_frequency,
+            ),
+            (
+                activity.worker.mint,
+                "getTokenCycleRentMint",
+                contract.get_token_cycle_rent_mint(token),
+                activity.worker.mint_frequency,
+            ),
+        ] {
+            if !enabled {
+                continue;
             }
-        }
 
-        if activity.worker.publish {
-            let rent_method = self
-                .access_controls_contract
-                .method::<_, U256>("getTokenCycleRentPublish", H160::from_str(token).unwrap());
-
-            match rent_method {
-                Ok(rent_call) => {
-                    let token_result: Result<
-                        U256,
-                        contract::ContractError<SignerMiddleware<Arc<Provider<Http>>, LocalWallet>>,
-                    > = rent_call.call().await;
-
-                    match token_result {
-                        Ok(rent_threshold) => {
-                            rent_total += rent_threshold * activity.worker.publish_frequency;
-                        }
-                        Err(err) => {
-                            eprintln!("Error in rent method publish: {}", err);
-                        }
-                    }
-                }
-                Err(err) => {
-                    eprintln!("Error in rent method publish: {}", err);
-                }
-            }
-        }
+            let rent_threshold = rent_call
+                .call()
+                .await
+                .map_err(|err| {
+                    error!("Error in rent method {}: {}", method, err);
+                    AgentError::from(err)
+                })?;
 
-        if activity.worker.remix {
-            let rent_method = self
-                .access_controls_contract
-                .method::<_, U256>("getTokenCycleRentRemix", H160::from_str(token).unwrap());
-
-            match rent_method {
-                Ok(rent_call) => {
-                    let token_result: Result<
-                        U256,
-                        contract::ContractError<SignerMiddleware<Arc<Provider<Http>>, LocalWallet>>,
-                    > = rent_call.call().await;
-
-                    match token_result {
-                        Ok(rent_threshold) => {
-                            rent_total += rent_threshold * activity.worker.remix_frequency;
-                        }
-                        Err(err) => {
-                            eprintln!("Error in rent method remix: {}", err);
-                        }
-                    }
-                }
-                Err(err) => {
-                    eprintln!("Error in rent method remix: {}", err);
-                }
-            }
-        }
-
-        if activity.worker.mint {
-            let rent_method = self
-                .access_controls_contract
-                .method::<_, U256>("getTokenCycleRentMint", H160::from_str(token).unwrap());
-
-            match rent_method {
-                Ok(rent_call) => {
-                    let token_result: Result<
-                        U256,
-                        contract::ContractError<SignerMiddleware<Arc<Provider<Http>>, LocalWallet>>,
-                    > = rent_call.call().await;
-
-                    match token_result {
-                        Ok(rent_threshold) => {
-                            rent_total += rent_threshold * activity.worker.mint_frequency;
-                        }
-                        Err(err) => {
-                            eprintln!("Error in rent method mint: {}", err);
-                        }
-                    }
-                }
-                Err(err) => {
-                    eprintln!("Error in rent method mint: {}", err);
-                }
-            }
+            rent_total += rent_threshold * frequency;
         }
 
         Ok(rent_total)
     }
 }
 
-async fn cycle_activity(
-    agent: &TripleAAgent,
-    tokens: Option<SavedTokens>,
-    activity: &AgentActivity,
-    interval: i64,
-    collection_manager_contract: Arc<
-        ContractInstance<
-            Arc<SignerMiddleware<Arc<Provider<Http>>, Wallet<SigningKey>>>,
-            SignerMiddleware<Arc<Provider<Http>>, Wallet<SigningKey>>,
-        >,
-    >,
-    agents_contract: Arc<
-        ContractInstance<
-            Arc<SignerMiddleware<Arc<Provider<Http>>, Wallet<SigningKey>>>,
-            SignerMiddleware<Arc<Provider<Http>>, Wallet<SigningKey>>,
-        >,
-    >,
-    market_contract: Arc<
-        ContractInstance<
-            Arc<SignerMiddleware<Arc<Provider<Http>>, Wallet<SigningKey>>>,
-            SignerMiddleware<Arc<Provider<Http>>, Wallet<SigningKey>>,
-        >,
-    >,
-) {
-    let total_activities = activity.worker.lead_frequency.as_u64()
-        + activity.worker.publish_frequency.as_u64()
-        + activity.worker.remix_frequency.as_u64()
-        + activity.worker.mint_frequency.as_u64();
-
-    if total_activities == 0 {
-        println!("⚠️ No activities found. Skipping cycle.");
-        return;
+// Rent tokens in the order they are spent. Amounts of different tokens can't be compared,
+// so the first preferred token the balance covers wins. Tokens left out of the list come
+// last, in the order the collection lists its prices.
+fn rent_preference() -> Result<Vec<Address>, AgentError> {
+    let network = network();
+    let order = var("AGENT_RENT_TOKENS").unwrap_or_else(|_| "wgho,mona,bonsai".to_string());
+
+    order
+        .split(',')
+        .map(|token| token.trim())
+        .filter(|token| !token.is_empty())
+        .map(|token| {
+            let address = match token.to_lowercase().as_str() {
+                "wgho" => network.wgho.clone(),
+                "bonsai" => network.bonsai.clone(),
+                "mona" => network.mona.clone(),
+                _ => token.to_string(),
+            };
+            address.parse::<Address>().map_err(|_| {
+                AgentError::config(format!("Invalid rent token {} in AGENT_RENT_TOKENS", token))
+            })
+        })
+        .collect()
+}
+
+fn pick_quote<'a>(quotes: &'a [RentQuote], preference: &[Address]) -> Option<&'a RentQuote> {
+    quotes
+        .iter()
+        .enumerate()
+        .filter(|(_, quote)| quote.balance >= quote.rent)
+        .min_by_key(|(index, quote)| {
+            let rank = preference.iter().position(|token| *token == quote.token);
+            (rank.unwrap_or(preference.len()), *index)
+        })
+        .map(|(_, quote)| quote)
+}
+
+// The queue only holds the collections of the round being spawned. Once every collection
+// has been spawned or dropped it is empty, so the next fire plans a new round.
+fn dequeue(queue: &mut Vec<AgentActivity>, collection_id: U256) {
+    queue.retain(|item| item.collection_id != collection_id);
+}
+
+fn remember_tokens(store: &AgentStore, old: Option<&SavedTokens>, new: &SavedTokens) {
+    let changed = old
+        .map(|saved| saved.tokens.refresh_token != new.tokens.refresh_token || saved.expiry != new.expiry)
+        .unwrap_or(true);
+
+    if changed {
+        store.save_tokens(new);
     }
-    let activity_interval = interval / total_activities as i64;
+}
 
+fn plan_tasks(activity: &AgentActivity) -> Vec<ActivityType> {
     let mut tasks = vec![];
     for _ in 0..activity.worker.lead_frequency.as_u64() {
         tasks.push(ActivityType::Lead);
@@ -761,111 +685,201 @@ async fn cycle_activity(
     for _ in 0..activity.worker.mint_frequency.as_u64() {
         tasks.push(ActivityType::Mint);
     }
+    if activity.worker.respond {
+        for _ in 0..activity.worker.respond_frequency.as_u64() {
+            tasks.push(ActivityType::Respond);
+        }
+    }
+    if activity.worker.feed {
+        for _ in 0..activity.worker.feed_frequency.as_u64() {
+            tasks.push(ActivityType::Feed);
+        }
+    }
 
-    tasks = distribute_tasks(tasks);
+    distribute_tasks(tasks)
+}
+
+async fn cycle_activity(manager: AgentManager, plan: PlannedRun, interval: i64) {
+    let AgentManager {
+        agent,
+        tokens,
+        store,
+        collection_manager_contract,
+        agents_contract,
+        market_contract,
+        ..
+    } = manager;
+    let PlannedRun {
+        run,
+        activity,
+        tasks,
+        completed,
+        ..
+    } = plan;
 
     if tasks.is_empty() {
-        println!("⚠️ No tasks available after distribution.");
+        warn!("No tasks available after distribution");
+        return;
+    }
+
+    let pending: Vec<(usize, ActivityType)> = tasks
+        .into_iter()
+        .enumerate()
+        .filter(|(index, _)| !completed.contains(index))
+        .collect();
+
+    if pending.is_empty() {
+        info!("No pending tasks left for this cycle");
         return;
     }
 
-    println!("Tasks to run: {:?}", tasks);
+    let activity_interval = interval / pending.len() as i64;
+    let since = generation(agent.id);
+
+    info!("Tasks to run: {:?}", pending);
 
-    let handles: Vec<_> = tasks
+    let handles: Vec<_> = pending
         .into_iter()
         .enumerate()
-        .map(|(i, task)| {
+        .map(|(slot, (index, task))| {
             let agent = agent.clone();
             let tokens = tokens.clone();
+            let store = store.clone();
             let collection = activity.collection.clone();
+            let collection_id = activity.collection_id;
             let instructions = activity.worker.instructions.clone();
             let collection_contract = collection_manager_contract.clone();
             let agents_contract = agents_contract.clone();
             let market_contract = market_contract.clone();
+            let span = info_span!(
+                "task",
+                agent_id = agent.id,
+                collection_id = %collection_id,
+                activity = ?task,
+                index
+            );
             tokio::spawn(async move {
-                tokio::time::sleep(std::time::Duration::from_secs(
-                    (i as i64 * activity_interval) as u64,
-                ))
+                tokio::time::sleep(Duration::from_secs((slot as i64 * activity_interval) as u64))
                 .await;
 
-                match task {
-                    ActivityType::Mint => {
-                        let tokens =
-                            handle_tokens(agent.id, &agent.account_address, tokens.clone()).await;
-
-                        match tokens {
-                            Ok(new_tokens) => {
-                                let _ = mint(
-                                    &agent,
-                                    Some(new_tokens),
-                                    collection_contract,
-                                    agents_contract,
-                                    market_contract,
-                                    &collection,
-                                )
-                                .await;
-                            }
+                if is_cancelled(agent.id, since) {
+                    info!("Cancelled {:?} for agent_{}", task, agent.id);
+                    store.save_cancelled(run, collection_id, index, &task);
+                    return;
+                }
 
-                            Err(err) => {
-                                eprintln!("Error renewing Lens tokens on Mint: {:?}", err);
-                            }
-                        }
-                    }
-                    ActivityType::Lead => {
-                        let tokens =
-                            handle_tokens(agent.id, &agent.account_address, tokens.clone()).await;
-
-                        match tokens {
-                            Ok(new_tokens) => {
-                                let _ = lead_generation(
-                                    &agent,
-                                    &collection,
-                                    Some(new_tokens),
-                                    &instructions,
-                                )
-                                .await;
-                            }
-                            Err(err) => {
-                                eprintln!("Error renewing Lens tokens on Lead Gen: {:?}", err);
-                            }
-                        }
+                // A paused task stays pending: it runs on resume, or after a restart
+                // through resume_pending. Only a cancel drops it.
+                if is_paused(agent.id) {
+                    info!("Agent_{} is paused, holding {:?} until resumed", agent.id, task);
+                    if !wait_while_paused(agent.id, since).await {
+                        info!("Cancelled {:?} for agent_{}", task, agent.id);
+                        store.save_cancelled(run, collection_id, index, &task);
+                        return;
                     }
-                    ActivityType::Publish => {
-                        let tokens =
-                            handle_tokens(agent.id, &agent.account_address, tokens.clone()).await;
+                }
 
-                        match tokens {
-                            Ok(new_tokens) => {
-                                let _ =
+                let activity_type = format!("{:?}", task);
+                activity_attempted(agent.id, &activity_type);
+
+                // An open circuit means the upstream is known to be down, so wait for it
+                // within this task's slot instead of spending the task on a failure.
+                let deadline =
+                    Instant::now() + Duration::from_secs(activity_interval.max(0) as u64);
+                let result = loop {
+                    let result = match handle_tokens(
+                        agent.id,
+                        &agent.account_address,
+                        tokens.clone(),
+                    )
+                    .await
+                    {
+                        Ok(new_tokens) => {
+                            remember_tokens(&store, tokens.as_ref(), &new_tokens);
+
+                            match task {
+                                ActivityType::Mint => {
+                                    mint(
+                                        &agent,
+                                        Some(new_tokens),
+                                        collection_contract.clone(),
+                                        agents_contract.clone(),
+                                        market_contract.clone(),
+                                        &collection,
+                                    )
+                                    .await
+                                }
+                                ActivityType::Lead => {
+                                    lead_generation(
+                                        &agent,
+                                        &collection,
+                                        Some(new_tokens),
+                                        &instructions,
+                                    )
+                                    .await
+                                }
+                                ActivityType::Publish => {
                                     publish(&agent, Some(new_tokens), &collection, &instructions)
-                                        .await;
-                            }
-                            Err(err) => {
-                                eprintln!("Error renewing Lens tokens on Publish: {:?}", err);
+                                        .await
+                                }
+                                ActivityType::Respond => {
+                                    respond(
+                                        &agent,
+                                        &collection,
+                                        &store,
+                                        Some(new_tokens),
+                                        &instructions,
+                                    )
+                                    .await
+                                }
+                                ActivityType::Feed => {
+                                    feed(&agent, &collection, Some(new_tokens), &instructions)
+                                        .await
+                                }
+                                ActivityType::Remix => {
+                                    remix(
+                                        &agent,
+                                        &collection,
+                                        Some(new_tokens),
+                                        collection_contract.clone(),
+                                    )
+                                        .await
+                                }
                             }
                         }
-                    }
-                    ActivityType::Remix => {
-                        let tokens =
-                            handle_tokens(agent.id, &agent.account_address, tokens.clone()).await;
-
-                        match tokens {
-                            Ok(new_tokens) => {
-                                let _ = remix(
-                                    &agent,
-                                    &collection,
-                                    Some(new_tokens),
-                                    collection_contract,
-                                )
-                                .await;
-                            }
-                            Err(err) => {
-                                eprintln!("Error renewing Lens tokens on Remix: {:?}", err);
-                            }
+                        Err(err) => {
+                            error!("Error renewing Lens tokens on {:?}: {:?}", task, err);
+                            Err(err)
+                        }
+                    };
+
+                    match &result {
+                        Err(AgentError::Unavailable { upstream, retry_in })
+                            if Instant::now() + *retry_in < deadline
+                                && !is_cancelled(agent.id, since) =>
+                        {
+                            warn!(
+                                "{} unavailable, retrying {:?} for agent_{} in {:?}",
+                                upstream, task, agent.id, retry_in
+                            );
+                            tokio::time::sleep(*retry_in).await;
                         }
+                        _ => break result,
                     }
+                };
+
+                match &result {
+                    Ok(hashes) => {
+                        activity_succeeded(agent.id, &activity_type);
+                        track_posts(agent.id, &task, collection_id, hashes);
+                    }
+                    Err(err) => error!(
+                        retryable = err.is_retryable(),
+                        "Error running {:?} for agent_{}: {}", task, agent.id, err
+                    ),
                 }
-            })
+                store.save_completed(run, collection_id, index, &task, &result);
+            }.instrument(span))
         })
         .collect();
 
@@ -873,9 +887,10 @@ async fn cycle_activity(
         let _ = handle.await;
     }
 
-    println!(
-        "Finished cycle_activity for Agent_{} and Activity {:?}",
-        agent.id, activity
+    info!(
+        collection_id = %activity.collection_id,
+        "Finished cycle_activity for Agent_{}",
+        agent.id
     );
 }
 
@@ -891,3 +906,100 @@ fn distribute_tasks(mut tasks: Vec<ActivityType>) -> Vec<ActivityType> {
     }
     distributed
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::utils::types::{Balance, Collection};
+
+    fn activity(collection_id: u64) -> AgentActivity {
+        AgentActivity {
+            collection: Collection {
+                image: String::new(),
+                title: String::new(),
+                description: String::new(),
+                artist: String::new(),
+                username: String::new(),
+                collection_id: U256::from(collection_id),
+                prices: vec![],
+            },
+            token: String::new(),
+            worker: TripleAWorker {
+                lead: true,
+                publish: false,
+                remix: false,
+                mint: false,
+                lead_frequency: U256::one(),
+                publish_frequency: U256::zero(),
+                remix_frequency: U256::zero(),
+                mint_frequency: U256::zero(),
+                instructions: String::new(),
+                respond: false,
+                respond_frequency: U256::zero(),
+                feed: false,
+                feed_frequency: U256::zero(),
+            },
+            balance: Balance {
+                rent_balance: U256::zero(),
+                bonus_balance: U256::zero(),
+            },
+            collection_id: U256::from(collection_id),
+        }
+    }
+
+    #[test]
+    fn second_fire_plans_again() {
+        let mut queue = vec![activity(1), activity(2), activity(3)];
+
+        dequeue(&mut queue, U256::from(1));
+        assert_eq!(
+            queue.iter().map(|item| item.collection_id.as_u64()).collect::<Vec<_>>(),
+            vec![2, 3]
+        );
+
+        dequeue(&mut queue, U256::from(2));
+        dequeue(&mut queue, U256::from(3));
+        assert!(queue.is_empty(), "a spawned round must leave nothing for the next fire");
+
+        queue = vec![activity(4)];
+        dequeue(&mut queue, U256::from(4));
+        assert!(queue.is_empty());
+    }
+
+    fn quote(byte: u8, rent: u64, balance: u64) -> RentQuote {
+        RentQuote {
+            token: Address::repeat_byte(byte),
+            rent: U256::from(rent),
+            balance: U256::from(balance),
+        }
+    }
+
+    #[test]
+    fn rent_follows_token_preference() {
+        let preference = vec![Address::repeat_byte(2), Address::repeat_byte(1)];
+        let quotes = vec![quote(1, 5, 10), quote(2, 900, 1000), quote(3, 1, 1)];
+
+        // A smaller amount in another token isn't cheaper, the preferred token wins.
+        assert_eq!(pick_quote(&quotes, &preference).unwrap().token, Address::repeat_byte(2));
+
+        // Tokens the balance doesn't cover are skipped.
+        let quotes = vec![quote(1, 5, 10), quote(2, 900, 10), quote(3, 1, 1)];
+        assert_eq!(pick_quote(&quotes, &preference).unwrap().token, Address::repeat_byte(1));
+
+        // Tokens outside the preference come last, in price order.
+        let quotes = vec![quote(4, 5, 10), quote(3, 1, 1), quote(2, 9, 1)];
+        assert_eq!(pick_quote(&quotes, &preference).unwrap().token, Address::repeat_byte(4));
+        assert!(pick_quote(&[quote(1, 5, 1)], &preference).is_none());
+    }
+
+    #[test]
+    fn plan_tasks_follows_worker_frequencies() {
+        let mut planned = activity(1);
+        planned.worker.lead_frequency = U256::from(2);
+        planned.worker.publish_frequency = U256::from(1);
+
+        let tasks = plan_tasks(&planned);
+        assert_eq!(tasks.len(), 3);
+        assert_eq!(tasks.iter().filter(|task| **task == ActivityType::Lead).count(), 2);
+    }
+}
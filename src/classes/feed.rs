@@ -0,0 +1,167 @@
+use crate::classes::lead::format_response;
+use crate::utils::{
+    bindings::agent_feed_rule::{AgentFeedRule, CreatePostParams, NotAgent},
+    contracts::initialize_provider,
+    helpers::{format_instructions, hashes_or_error, text_model},
+    lens::{feed_info, make_publication},
+    memory::remember,
+    store::{day_of, today},
+    types::{
+        AgentError, AgentMemory, Claims, Collection, FeedInfo, MemoryEntry, MemoryKind, SavedTokens,
+        TripleAAgent,
+    },
+    venice::call_feed_completion,
+};
+use chrono::Utc;
+use ethers::types::{Address, Bytes, U256};
+use std::{collections::HashSet, str::FromStr};
+use tracing::{debug, error, info, warn};
+
+// Feeds being posted to right now, so two Feed tasks never post to the same feed twice.
+static CLAIMED: Claims = Claims::new();
+
+fn posted_today(agent: &TripleAAgent) -> Result<HashSet<String>, AgentError> {
+    let day = today(&agent.schedule.timezone);
+
+    Ok(AgentMemory::open(agent.id)?
+        .entries()?
+        .into_iter()
+        .filter(|entry| entry.kind == MemoryKind::Feed && day_of(entry.at, &agent.schedule.timezone) == day)
+        .filter_map(|entry| entry.feed.map(|feed| feed.to_lowercase()))
+        .collect())
+}
+
+// Dry-runs processCreatePost on every rule of the feed. Only a NotAgent revert blocks
+// the post; other reverts come from rules that aren't AgentFeedRule or that expect
+// the feed as the caller, and Lens enforces those when the post is made.
+async fn check_feed_rules(
+    agent: &TripleAAgent,
+    feed: &str,
+    info: &FeedInfo,
+) -> Result<(), AgentError> {
+    let author = Address::from_str(&agent.account_address).map_err(|_| {
+        AgentError::config(format!("Invalid account address for agent_{}", agent.id))
+    })?;
+    let provider = initialize_provider();
+
+    for rule in &info.rules {
+        let Ok(address) = Address::from_str(rule) else {
+            warn!("Skipping feed rule with invalid address {}", rule);
+            continue;
+        };
+        let params = CreatePostParams {
+            author,
+            content_uri: String::new(),
+            reposted_post_id: U256::zero(),
+            quoted_post_id: U256::zero(),
+            replied_post_id: U256::zero(),
+            rule_changes: vec![],
+            extra_data: vec![],
+        };
+
+        let call = AgentFeedRule::new(address, provider.clone())
+            .process_create_post(U256::zero(), params, Bytes::default())
+            .from(author);
+        match call.call().await {
+            Ok(_) => debug!("Feed rule {} accepts agent_{}", rule, agent.id),
+            Err(err) if err.decode_revert::<NotAgent>().is_some() => {
+                return Err(AgentError::lens_rejected(format!(
+                    "Feed {} rule {} rejected agent_{}: NotAgent",
+                    feed, rule, agent.id
+                )));
+            }
+            Err(err) => debug!("Feed rule {} check inconclusive: {}", rule, err),
+        }
+    }
+
+    Ok(())
+}
+
+pub async fn feed(
+    agent: &TripleAAgent,
+    collection: &Collection,
+    tokens: Option<SavedTokens>,
+    collection_instructions: &str,
+) -> Result<Vec<String>, AgentError> {
+    let model = text_model(&agent.model);
+    let auth_tokens = tokens
+        .as_ref()
+        .map(|tokens| tokens.tokens.access_token.clone())
+        .ok_or_else(|| AgentError::lens("No Lens tokens to post to feeds with"))?;
+
+    let posted = posted_today(agent)?;
+    let mut hashes = vec![];
+    let mut errors = vec![];
+
+    for feed in &agent.feeds {
+        if posted.contains(&feed.to_lowercase()) || !CLAIMED.claim(feed) {
+            info!("Agent_{} already posted to feed {} today, skipping", agent.id, feed);
+            continue;
+        }
+
+        let result = feed_post(
+            agent,
+            collection,
+            &auth_tokens,
+            collection_instructions,
+            model,
+            feed,
+        )
+        .await;
+        CLAIMED.release(feed);
+
+        match result {
+            Ok(hash) => hashes.push(hash),
+            Err(err) => {
+                error!("Error posting to feed {} for agent_{}: {:?}", feed, agent.id, err);
+                errors.push(err);
+            }
+        }
+    }
+
+    hashes_or_error(hashes, errors)
+}
+
+async fn feed_post(
+    agent: &TripleAAgent,
+    collection: &Collection,
+    auth_tokens: &str,
+    collection_instructions: &str,
+    model: &str,
+    feed: &str,
+) -> Result<String, AgentError> {
+    let info = feed_info(feed).await?;
+    check_feed_rules(agent, feed, &info).await?;
+
+    let llm_response = call_feed_completion(
+        agent.id,
+        collection,
+        &format_instructions(agent),
+        collection_instructions,
+        &info.description,
+        &info.title,
+        model,
+    )
+    .await?;
+
+    let content = format_response(&llm_response, collection, true).await?;
+    let hash = make_publication(&content, agent.id, auth_tokens, Some(feed.to_string())).await?;
+
+    remember(
+        agent.id,
+        MemoryEntry {
+            at: Utc::now().timestamp(),
+            kind: MemoryKind::Feed,
+            collection_id: collection.collection_id,
+            collection_title: collection.title.clone(),
+            author: None,
+            post_id: None,
+            replied_to: None,
+            feed: Some(feed.to_string()),
+            text: llm_response,
+            hash: hash.clone(),
+        },
+    );
+
+    Ok(hash)
+}
@@ -1,13 +1,18 @@
 use crate::utils::{
-    helpers::format_instructions,
+    helpers::{format_instructions, hashes_or_error, text_model},
     ipfs::upload_lens_storage,
-    lens::{feed_info, follow_profiles, make_comment, make_publication, make_quote, search_posts},
-    types::{Collection, Content, Image, Publication, SavedTokens, TripleAAgent},
-    venice::{call_comment_completion, call_feed_completion, receive_query},
+    lens::{follow_profiles, make_comment, make_quote, search_posts},
+    memory::{recall_memory, remember},
+    types::{
+        AgentError, Collection, Content, Image, MemoryEntry, MemoryKind, MemoryQuery, Publication,
+        SavedTokens, TripleAAgent,
+    },
+    venice::{call_comment_completion, receive_query},
 };
+use chrono::Utc;
 use futures::future::join_all;
 use serde_json::{to_string, Value};
-use std::{error::Error, io};
+use tracing::error;
 use uuid::Uuid;
 
 pub async fn lead_generation(
@@ -15,27 +20,27 @@ pub async fn lead_generation(
     collection: &Collection,
     tokens: Option<SavedTokens>,
     collection_instructions: &str,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    let model = if agent.model.contains("dolphin") {
-        println!("DEBUG: Overriding deprecated model {} with llama-3.3-70b", agent.model);
-        "llama-3.3-70b"
-    } else {
-        &agent.model
-    };
-    match receive_query(&collection.description, &collection.title, model).await {
+) -> Result<Vec<String>, AgentError> {
+    let model = text_model(&agent.model);
+    let auth_tokens = tokens
+        .as_ref()
+        .map(|tokens| tokens.tokens.access_token.clone())
+        .ok_or_else(|| AgentError::lens("No Lens tokens to comment and quote with"))?;
+    match receive_query(agent.id, &collection.description, &collection.title, model).await {
         Ok(query) => match search_posts(&agent.wallet, &query).await {
             Ok((posts, profiles)) => {
-                let _ = follow_profiles(
+                let followed = follow_profiles(
                     profiles.clone(),
-                    &tokens.as_ref().unwrap().tokens.access_token,
+                    &auth_tokens,
+                    agent.id,
                 )
                 .await;
 
                 let (comments_posts, quotes_posts) = posts.split_at(posts.len() / 2);
 
-                let _ = make_comments(
+                let (mut hashes, mut errors) = make_comments(
                     comments_posts.to_vec(),
-                    &tokens.as_ref().unwrap().tokens.access_token,
+                    &auth_tokens,
                     agent.id,
                     &agent.model,
                     &format_instructions(&agent),
@@ -44,9 +49,9 @@ pub async fn lead_generation(
                 )
                 .await;
 
-                let _ = make_quotes(
+                let (quote_hashes, quote_errors) = make_quotes(
                     quotes_posts.to_vec(),
-                    &tokens.as_ref().unwrap().tokens.access_token,
+                    &auth_tokens,
                     agent.id,
                     &agent.model,
                     &format_instructions(&agent),
@@ -54,34 +59,22 @@ pub async fn lead_generation(
                     &collection,
                 )
                 .await;
+                hashes.extend(quote_hashes);
+                errors.extend(quote_errors);
+                if let Err(err) = followed {
+                    errors.push(err);
+                }
 
-                // let _ = feed_posts(
-                //     collection,
-                //     &tokens.as_ref().unwrap().tokens.access_token,
-                //     agent.id,
-                //     agent.feeds.clone(),
-                //     &agent.model,
-                //     &format_instructions(&agent),
-                //     &collection_instructions,
-                // )
-                // .await;
-
-                Ok(())
+                hashes_or_error(hashes, errors)
             }
             Err(err) => {
-                println!("Error finding posts {:?}", err);
-                Err(Box::new(io::Error::new(
-                    io::ErrorKind::Other,
-                    "Error finding posts",
-                )))
+                error!("Error finding posts {:?}", err);
+                Err(err)
             }
         },
         Err(err) => {
-            println!("Error receiving query {:?}", err);
-            Err(Box::new(io::Error::new(
-                io::ErrorKind::Other,
-                "Error receiving query",
-            )))
+            error!("Error receiving query {:?}", err);
+            Err(err)
         }
     }
 }
@@ -94,7 +87,7 @@ async fn make_comments(
     custom_instructions: &str,
     collection_instructions: &str,
     collection: &Collection,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
+) -> (Vec<String>, Vec<AgentError>) {
     let comment_futures = posts.into_iter().map(|post| async move {
         let mut content = String::new();
 
@@ -106,47 +99,80 @@ async fn make_comments(
                 .to_string();
         }
 
+        let author = post["author"]["address"].as_str().map(|author| author.to_string());
+        let memory = recall_memory(
+            private_key,
+            &MemoryQuery {
+                author: author.clone(),
+                collection_id: Some(collection.collection_id),
+                text: content.clone(),
+            },
+        );
+
         match call_comment_completion(
+            private_key,
             &content,
             custom_instructions,
             collection_instructions,
             &collection.description,
+            &memory,
             &model,
         )
         .await
         {
             Ok((llm_response, image)) => {
                 match format_response(&llm_response, &collection, image).await {
-                    Ok(content) => {
-                        let _ = make_comment(
-                            &content,
-                            private_key,
-                            auth_tokens,
-                            post["id"].as_str().unwrap_or_default(),
-                        )
-                        .await;
+                    Ok(lens_content) => {
+                        let post_id = post["id"].as_str().unwrap_or_default();
+                        let result =
+                            make_comment(&lens_content, private_key, auth_tokens, post_id).await;
+                        if let Ok(hash) = &result {
+                            remember(
+                                private_key,
+                                MemoryEntry {
+                                    at: Utc::now().timestamp(),
+                                    kind: MemoryKind::Comment,
+                                    collection_id: collection.collection_id,
+                                    collection_title: collection.title.clone(),
+                                    author,
+                                    post_id: Some(post_id.to_string()),
+                                    replied_to: Some(content),
+                                    feed: None,
+                                    text: llm_response,
+                                    hash: hash.clone(),
+                                },
+                            );
+                        }
+                        result
                     }
                     Err(err) => {
-                        println!("Error with Comment format {:?}", err);
+                        error!("Error with Comment format {:?}", err);
+                        Err(err)
                     }
                 }
             }
             Err(err) => {
-                println!("Error with LLM Comment {:?}", err);
+                error!("Error with LLM Comment {:?}", err);
+                Err(err)
             }
         }
-        Ok::<(), Box<dyn Error + Send + Sync>>(())
     });
 
     let results: Vec<_> = join_all(comment_futures).await;
+    let mut hashes = vec![];
+    let mut errors = vec![];
 
     for result in results {
-        if let Err(e) = result {
-            println!("Error with commenting: {:?}", e);
+        match result {
+            Ok(hash) => hashes.push(hash),
+            Err(e) => {
+                error!("Error with commenting: {:?}", e);
+                errors.push(e);
+            }
         }
     }
 
-    Ok(())
+    (hashes, errors)
 }
 
 async fn make_quotes(
@@ -157,7 +183,7 @@ async fn make_quotes(
     custom_instructions: &str,
     collection_instructions: &str,
     collection: &Collection,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
+) -> (Vec<String>, Vec<AgentError>) {
     let quote_futures = posts.into_iter().map(|post| async move {
         let mut content = String::new();
 
@@ -169,54 +195,87 @@ async fn make_quotes(
                 .to_string();
         }
 
+        let author = post["author"]["address"].as_str().map(|author| author.to_string());
+        let memory = recall_memory(
+            private_key,
+            &MemoryQuery {
+                author: author.clone(),
+                collection_id: Some(collection.collection_id),
+                text: content.clone(),
+            },
+        );
+
         match call_comment_completion(
+            private_key,
             &content,
             custom_instructions,
             collection_instructions,
             &collection.description,
+            &memory,
             &model,
         )
         .await
         {
             Ok((llm_response, image)) => {
                 match format_response(&llm_response, &collection, image).await {
-                    Ok(content) => {
-                        let _ = make_quote(
-                            &content,
-                            private_key,
-                            auth_tokens,
-                            post["id"].as_str().unwrap_or_default(),
-                        )
-                        .await;
+                    Ok(lens_content) => {
+                        let post_id = post["id"].as_str().unwrap_or_default();
+                        let result =
+                            make_quote(&lens_content, private_key, auth_tokens, post_id).await;
+                        if let Ok(hash) = &result {
+                            remember(
+                                private_key,
+                                MemoryEntry {
+                                    at: Utc::now().timestamp(),
+                                    kind: MemoryKind::Quote,
+                                    collection_id: collection.collection_id,
+                                    collection_title: collection.title.clone(),
+                                    author,
+                                    post_id: Some(post_id.to_string()),
+                                    replied_to: Some(content),
+                                    feed: None,
+                                    text: llm_response,
+                                    hash: hash.clone(),
+                                },
+                            );
+                        }
+                        result
                     }
                     Err(err) => {
-                        println!("Error with Quote format {:?}", err);
+                        error!("Error with Quote format {:?}", err);
+                        Err(err)
                     }
                 }
             }
             Err(err) => {
-                println!("Error with LLM Quote {:?}", err);
+                error!("Error with LLM Quote {:?}", err);
+                Err(err)
             }
         }
-        Ok::<(), Box<dyn Error + Send + Sync>>(())
     });
 
     let results: Vec<_> = join_all(quote_futures).await;
+    let mut hashes = vec![];
+    let mut errors = vec![];
 
     for result in results {
-        if let Err(e) = result {
-            println!("Error with quoting: {:?}", e);
+        match result {
+            Ok(hash) => hashes.push(hash),
+            Err(e) => {
+                error!("Error with quoting: {:?}", e);
+                errors.push(e);
+            }
         }
     }
 
-    Ok(())
+    (hashes, errors)
 }
 
-async fn format_response(
+pub async fn format_response(
     llm_message: &str,
     collection: &Collection,
     use_image: bool,
-) -> Result<String, Box<dyn Error + Send + Sync>> {
+) -> Result<String, AgentError> {
     let mut focus = String::from("TEXT_ONLY");
     let mut schema = "https://json-schemas.lens.dev/posts/text-only/3.0.0.json".to_string();
     let mut image = None;
@@ -252,75 +311,10 @@ async fn format_response(
     let content = match upload_lens_storage(publication_json).await {
         Ok(con) => con,
         Err(e) => {
-            eprintln!("Error uploading content to Lens Storage: {}", e);
-            return Err(Box::new(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Error uploading content to Lens Storage: {}", e),
-            )));
+            error!("Error uploading content to Lens Storage: {}", e);
+            return Err(e);
         }
     };
 
     Ok(content)
 }
-
-async fn feed_posts(
-    collection: &Collection,
-    auth_tokens: &str,
-    private_key: u32,
-    feeds: Vec<String>,
-    model: &str,
-    custom_instructions: &str,
-    collection_instructions: &str,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    let feed_futures = feeds.into_iter().map(|feed| async move {
-        match feed_info(&feed).await {
-            Ok((title, description)) => {
-                match call_feed_completion(
-                    &collection,
-                    custom_instructions,
-                    collection_instructions,
-                    &description,
-                    &title,
-                    &model,
-                )
-                .await
-                {
-                    Ok(llm_response) => {
-                        match format_response(&llm_response, &collection, true).await {
-                            Ok(content) => {
-                                let _ = make_publication(
-                                    &content,
-                                    private_key,
-                                    auth_tokens,
-                                    Some(feed),
-                                )
-                                .await;
-                            }
-                            Err(err) => {
-                                println!("Error with Feed format {:?}", err);
-                            }
-                        }
-                    }
-                    Err(err) => {
-                        println!("Error with LLM Feed {:?}", err);
-                    }
-                }
-            }
-            Err(err) => {
-                println!("Error with LLM Feed {:?}", err);
-            }
-        }
-
-        Ok::<(), Box<dyn Error + Send + Sync>>(())
-    });
-
-    let results: Vec<_> = join_all(feed_futures).await;
-
-    for result in results {
-        if let Err(e) = result {
-            println!("Error with feed: {:?}", e);
-        }
-    }
-
-    Ok(())
-}
@@ -1,53 +1,39 @@
 use crate::utils::{
+    bindings::{SignerClient, TripleAAgents, TripleACollectionManager, TripleAMarket},
     constants::{
-        INFURA_GATEWAY, INPUT_IRL_FASHION, LENS_CHAIN_ID, MARKET, NEGATIVE_PROMPT_IMAGE, VENICE_API,
+        INPUT_IRL_FASHION, NEGATIVE_PROMPT_IMAGE,
     },
-    helpers::{find_collection, mint_collection},
-    ipfs::{upload_image_to_ipfs, upload_lens_storage},
+    helpers::{find_collection, hashes_or_error, mint_collection, upload_image_post},
+    image::{configured_image_model, generate_image, source_image},
+    ipfs::upload_image_to_ipfs,
     lens::make_publication,
-    types::{Collection, Content, Image, Price, Publication, SavedTokens, TripleAAgent},
+    network::network,
+    transactions::{send_transaction, succeeded},
+    types::{
+        AgentError, Collection, ImageRequest, Price, SavedTokens, TripleAAgent,
+    },
     venice::call_image_details,
 };
-use base64::{engine::general_purpose::STANDARD, Engine};
 use ethers::{
-    contract::{self, ContractInstance, FunctionCall},
-    core::k256::ecdsa::SigningKey,
-    middleware::{Middleware, SignerMiddleware},
-    providers::{Http, Provider},
-    signers::{LocalWallet, Wallet},
-    types::{Address, Eip1559TransactionRequest, NameOrAddress, H160, H256, U256, U64},
+    contract::FunctionCall,
+    types::{Address, H160, U256},
 };
 use rand::{thread_rng, Rng};
-use reqwest::Client;
-use serde_json::{to_string, Value};
-use std::{env, error::Error, io, str::FromStr, sync::Arc};
-use uuid::Uuid;
+use std::{str::FromStr, sync::Arc};
+use tracing::{error, info};
 
 pub async fn mint(
     agent: &TripleAAgent,
     tokens: Option<SavedTokens>,
-    collection_manager_contract: Arc<
-        ContractInstance<
-            Arc<SignerMiddleware<Arc<Provider<Http>>, Wallet<SigningKey>>>,
-            SignerMiddleware<Arc<Provider<Http>>, Wallet<SigningKey>>,
-        >,
-    >,
-    agents_contract: Arc<
-        ContractInstance<
-            Arc<SignerMiddleware<Arc<Provider<Http>>, Wallet<SigningKey>>>,
-            SignerMiddleware<Arc<Provider<Http>>, Wallet<SigningKey>>,
-        >,
-    >,
-    market_contract: Arc<
-        ContractInstance<
-            Arc<SignerMiddleware<Arc<Provider<Http>>, Wallet<SigningKey>>>,
-            SignerMiddleware<Arc<Provider<Http>>, Wallet<SigningKey>>,
-        >,
-    >,
+    collection_manager_contract: Arc<TripleACollectionManager<SignerClient>>,
+    agents_contract: Arc<TripleAAgents<SignerClient>>,
+    market_contract: Arc<TripleAMarket<SignerClient>>,
     collection: &Collection,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    let client = Client::new();
-    let venice_key = env::var("VENICE_KEY").expect("VENICE_KEY no está configurada en .env");
+) -> Result<Vec<String>, AgentError> {
+    let auth_tokens = tokens
+        .as_ref()
+        .map(|tokens| tokens.tokens.access_token.clone())
+        .ok_or_else(|| AgentError::lens("No Lens tokens to announce mints with"))?;
     let format = vec!["Hoodie", "Long Sleeve", "Tee"][thread_rng().gen_range(0..3)];
     let location = vec![
         "Havannah",
@@ -152,334 +138,212 @@ pub async fn mint(
             "fauxhawk",
         ][thread_rng().gen_range(0..7)]
     };
-    let preset = ["Legend of Zelda", "Surrealist"][thread_rng().gen_range(0..2)];
 
     let prompt= format!("An abstract drawing deconstucivist Fashion of a 24 year old {} with {} skin and {} colored eyes and {} hair. The skin pores and texture are clearly visible and in focus. Wearing a {} {} with {} with large text \"{}\" typography on the streetwear, standing in the colorful graffiti filled pop art alley ways of {} in the {}, {}, pop art urban background, highly detailed, in the background subway stations and graffiti murals, abstract cuts, rule of thirds, in the background Disjointed wooden planks forming a pathway, in the style of H. R. Giger, in the style of Enki Bilal.", gender, color_skin, color_eyes, style_hair, color_fashion, format, graphics, typography, location, time, standing_position );
 
-    let image_response = client
-        .get(&format!(
-            "{}ipfs/{}",
-            INFURA_GATEWAY,
-            INPUT_IRL_FASHION[thread_rng().gen_range(0..INPUT_IRL_FASHION.len())]
-        ))
-        .send()
-        .await?;
-
-    if image_response.status() == 200 {
-        let bytes = image_response.bytes().await?;
+    let reference = INPUT_IRL_FASHION[thread_rng().gen_range(0..INPUT_IRL_FASHION.len())];
+    let source = source_image(agent.id, reference).await?;
+    let image_model = configured_image_model(agent.id)?;
 
-        let payload_inicial = serde_json::json!({
-            "model": "qwen-image",
-            "prompt": prompt,
-            "width": 768,
-            "height": 768,
-            "negative_prompt": NEGATIVE_PROMPT_IMAGE,
-        });
+    let image = generate_image(
+        agent.id,
+        ImageRequest {
+            task: "mint".to_string(),
+            model: image_model.clone(),
+            prompt: prompt.clone(),
+            negative_prompt: Some(NEGATIVE_PROMPT_IMAGE.to_string()),
+            width: 768,
+            height: 768,
+            seed: None,
+            style_preset: None,
+            source,
+            strength: None,
+        },
+    )
+    .await?;
 
-        let response = client
-            .post(format!("{}image/generate", VENICE_API))
-            .header("Content-Type", "application/json")
-            .header("Authorization", format!("Bearer {}", venice_key))
-            .json(&payload_inicial)
-            .send()
-            .await?;
-
-        if response.status() == 200 {
-            let json: Value = response.json().await?;
-            let images = json
-                .get("images")
-                .and_then(|v| v.as_array())
-                .cloned()
-                .unwrap_or_else(Vec::new);
-            let image = images
-                .first()
-                .and_then(|v| v.as_str())
-                .unwrap_or("")
-                .to_string();
-            match call_image_details(&agent.model, true).await {
-                Ok((title, description, amount, prices)) => {
-                    match upload_image_to_ipfs(&image).await {
-                        Ok(ipfs) => {
-                            match mint_collection(
-                                &description,
-                                &format!("ipfs://{}", ipfs.Hash),
-                                &title,
-                                amount,
-                                collection_manager_contract,
-                                prices,
-                                &agent,
-                                U256::from(0),
-                                &agent.model,
-                                &prompt,
-                                "qwen-image",
-                                1u8,
-                                Some(format.to_string()),
-                                false,
-                                &collection.artist,
+    match call_image_details(agent.id, &agent.model, true).await {
+        Ok((title, description, amount, prices)) => {
+            match upload_image_to_ipfs(&image).await {
+                Ok(ipfs) => {
+                    match mint_collection(
+                        &description,
+                        &format!("ipfs://{}", ipfs.Hash),
+                        &title,
+                        amount,
+                        collection_manager_contract,
+                        prices,
+                        agent,
+                        U256::from(0),
+                        &agent.model,
+                        &prompt,
+                        &image_model,
+                        1u8,
+                        Some(format.to_string()),
+                        false,
+                        &collection.artist,
+                    )
+                    .await
+                    {
+                        Ok(tx_hash) => {
+                            // The collection is minted, so later failures must not send
+                            // the task back to mint it again.
+                            let mut errors = vec![];
+                            let mut hashes = vec![tx_hash];
+                            match announce_mint(
+                                agent,
+                                &auth_tokens,
+                                title,
+                                description,
+                                &image.mime_type,
+                                &ipfs.Hash,
                             )
                             .await
                             {
-                                Ok(_) => {
-                                    let focus = String::from("IMAGE");
-                                    let schema =
-                                        "https://json-schemas.lens.dev/posts/image/3.0.0.json"
-                                            .to_string();
-                                    let tags = vec![
-                                        "tripleA".to_string(),
-                                        title.replace(" ", "").to_lowercase(),
-                                    ];
-
-                                    let publication = Publication {
-                                        schema,
-                                        lens: Content {
-                                            mainContentFocus: focus,
-                                            title,
-                                            content: description,
-                                            id: Uuid::new_v4().to_string(),
-                                            locale: "en".to_string(),
-                                            tags,
-                                            image: Some(Image {
-                                                tipo: "image/png".to_string(),
-                                                item: format!("ipfs://{}", ipfs.Hash),
-                                            }),
-                                        },
-                                    };
-
-                                    let publication_json = to_string(&publication)?;
-
-                                    let content = match upload_lens_storage(publication_json).await
-                                    {
-                                        Ok(con) => con,
-                                        Err(e) => {
-                                            eprintln!(
-                                                "Error uploading content to Lens Storage: {}",
-                                                e
-                                            );
-                                            return Err(Box::new(io::Error::new(
-                                                io::ErrorKind::Other,
-                                                format!(
-                                                    "Error uploading content to Lens Storage: {}",
-                                                    e
-                                                ),
-                                            )));
-                                        }
-                                    };
-
-                                    let _ = make_publication(
-                                        &content,
-                                        agent.id,
-                                        &tokens.as_ref().unwrap().tokens.access_token,
-                                        None,
-                                    )
-                                    .await;
-
-                                    let _ = collect_artists(
-                                        agents_contract,
-                                        market_contract,
-                                        &collection.artist,
-                                        collection.prices.clone(),
-                                        &agent,
-                                    )
-                                    .await;
-
-                                    Ok(())
-                                }
+                                Ok(hash) => hashes.push(hash),
                                 Err(err) => {
-                                    return Err(Box::new(std::io::Error::new(
-                                        std::io::ErrorKind::Other,
-                                        format!("Error with minting collection {:?}", err),
-                                    )));
+                                    error!(
+                                        "Error announcing mint for agent_{}: {:?}",
+                                        agent.id, err
+                                    );
+                                    errors.push(err);
                                 }
                             }
+
+                            if let Err(err) = collect_artists(
+                                agents_contract,
+                                market_contract,
+                                &collection.artist,
+                                collection.prices.clone(),
+                                agent,
+                            )
+                            .await
+                            {
+                                errors.push(err);
+                            }
+
+                            hashes_or_error(hashes, errors)
                         }
-                        Err(err) => {
-                            return Err(Box::new(std::io::Error::new(
-                                std::io::ErrorKind::Other,
-                                format!("Error in uploading image to IPFS {:?}", err),
-                            )));
-                        }
+                        Err(err) => Err(err),
                     }
                 }
-                Err(err) => {
-                    return Err(Box::new(std::io::Error::new(
-                        std::io::ErrorKind::Other,
-                        format!("Error with minting collection {:?}", err),
-                    )));
-                }
+                Err(err) => Err(err),
             }
-        } else {
-            return Err(Box::new(std::io::Error::new(
-                std::io::ErrorKind::Other,
-                format!("Error in sending to Venice {:?}", response.status()),
-            )));
         }
-    } else {
-        return Err(Box::new(std::io::Error::new(
-            std::io::ErrorKind::Other,
-            "Error in with source image base64 in mint",
-        )));
+        Err(err) => Err(err),
     }
 }
 
+async fn announce_mint(
+    agent: &TripleAAgent,
+    auth_tokens: &str,
+    title: String,
+    description: String,
+    mime_type: &str,
+    ipfs_hash: &str,
+) -> Result<String, AgentError> {
+    let content = upload_image_post(title, description, mime_type, ipfs_hash).await?;
+    make_publication(&content, agent.id, auth_tokens, None).await
+}
+
 async fn collect_artists(
-    agents_contract: Arc<
-        ContractInstance<
-            Arc<SignerMiddleware<Arc<Provider<Http>>, Wallet<SigningKey>>>,
-            SignerMiddleware<Arc<Provider<Http>>, Wallet<SigningKey>>,
-        >,
-    >,
-    market_contract: Arc<
-        ContractInstance<
-            Arc<SignerMiddleware<Arc<Provider<Http>>, Wallet<SigningKey>>>,
-            SignerMiddleware<Arc<Provider<Http>>, Wallet<SigningKey>>,
-        >,
-    >,
+    agents_contract: Arc<TripleAAgents<SignerClient>>,
+    market_contract: Arc<TripleAMarket<SignerClient>>,
     artist: &str,
     prices: Vec<Price>,
     agent: &TripleAAgent,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
+) -> Result<(), AgentError> {
+    let mut first_error = None;
+
     for price in &prices {
-        let balance_method = agents_contract.method::<_, U256>(
-            "getArtistCollectBalanceByToken",
-            (
-                H160::from_str(artist).unwrap(),
-                H160::from_str(&price.token).unwrap(),
+        let balance_result = agents_contract
+            .get_artist_collect_balance_by_token(
+                H160::from_str(artist).map_err(AgentError::parse)?,
+                H160::from_str(&price.token).map_err(AgentError::parse)?,
                 U256::from(agent.id),
-            ),
-        );
-
-        match balance_method {
-            Ok(balance_call) => {
-                let balance_result: Result<
-                    U256,
-                    contract::ContractError<SignerMiddleware<Arc<Provider<Http>>, LocalWallet>>,
-                > = balance_call.call().await;
+            )
+            .call()
+            .await;
 
-                match balance_result {
-                    Ok(balance) => {
-                        if balance > U256::from(0) {
-                            let _ = find_and_buy_collection(
-                                market_contract.clone(),
-                                artist,
-                                &price.token,
-                                balance,
-                                agent,
-                            )
-                            .await;
-                        } else {
-                            println!(
-                                "No artist balance for {} and agent {} and token {}",
-                                &artist, agent.id, &price.token
-                            );
-                        }
-                    }
-                    Err(err) => {
-                        eprintln!("Error in artist balance method: {}", err);
+        match balance_result {
+            Ok(balance) => {
+                if balance > U256::from(0) {
+                    if let Err(err) = find_and_buy_collection(
+                        market_contract.clone(),
+                        artist,
+                        &price.token,
+                        balance,
+                        agent,
+                    )
+                    .await
+                    {
+                        error!("Error collecting from artist {}: {}", artist, err);
+                        first_error.get_or_insert(err);
                     }
+                } else {
+                    info!(
+                        "No artist balance for {} and agent {} and token {}",
+                        &artist, agent.id, &price.token
+                    );
                 }
             }
             Err(err) => {
-                eprintln!("Error in artist balance method: {}", err);
+                error!("Error in artist balance method: {}", err);
+                first_error.get_or_insert(err.into());
             }
         }
     }
 
-    Ok(())
+    match first_error {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
 }
 
 async fn find_and_buy_collection(
-    market_contract: Arc<
-        ContractInstance<
-            Arc<SignerMiddleware<Arc<Provider<Http>>, Wallet<SigningKey>>>,
-            SignerMiddleware<Arc<Provider<Http>>, Wallet<SigningKey>>,
-        >,
-    >,
+    market_contract: Arc<TripleAMarket<SignerClient>>,
     artist: &str,
     token: &str,
     balance: U256,
     agent: &TripleAAgent,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    match find_collection(balance, token, artist).await {
-        Ok(collections) => {
-            let chosen_collection = &collections[thread_rng().gen_range(0..collections.len())];
-
-            let method = market_contract.method::<(Address, U256, U256, U256), H256>(
-                "agentBuy",
-                (
-                    H160::from_str(token).unwrap(),
-                    chosen_collection.collectionId,
-                    U256::from(1),
-                    U256::from(agent.id),
-                ),
-            );
-
-            match method {
-                Ok(call) => {
-                    let FunctionCall { tx, .. } = call;
-
-                    if let Some(tx_request) = tx.as_eip1559_ref() {
-                        let gas_price = U256::from(500_000_000_000u64);
-                        let max_priority_fee = U256::from(25_000_000_000u64);
-                        let gas_limit = U256::from(300_000);
-
-                        let client = market_contract.client().clone();
-                        let chain_id = *LENS_CHAIN_ID;
-                        let req = Eip1559TransactionRequest {
-                            from: Some(agent.wallet.parse::<Address>().unwrap()),
-                            to: Some(NameOrAddress::Address(MARKET.parse::<Address>().unwrap())),
-                            gas: Some(gas_limit),
-                            value: tx_request.value,
-                            data: tx_request.data.clone(),
-                            max_priority_fee_per_gas: Some(max_priority_fee),
-                            max_fee_per_gas: Some(gas_price + max_priority_fee),
-                            chain_id: Some(chain_id.into()),
-                            ..Default::default()
-                        };
-
-                        let pending_tx = match client.send_transaction(req, None).await {
-                            Ok(tx) => tx,
-                            Err(e) => {
-                                eprintln!("Error sending the transaction for agentBuy: {:?}", e);
-                                Err(Box::new(e))?
-                            }
-                        };
-
-                        let tx_hash = match pending_tx.confirmations(1).await {
-                            Ok(hash) => hash,
-                            Err(e) => {
-                                eprintln!("Error with transaction confirmation: {:?}", e);
-                                Err(Box::new(e))?
-                            }
-                        };
+) -> Result<(), AgentError> {
+    let collections = find_collection(balance, token, artist).await?;
+    if collections.is_empty() {
+        info!(
+            "No collections by {} within balance for agent {} and token {}",
+            artist, agent.id, token
+        );
+        return Ok(());
+    }
+    let chosen_collection = &collections[thread_rng().gen_range(0..collections.len())];
 
-                        println!("Agent Buy Hash: {:?}", tx_hash);
+    let FunctionCall { tx, .. } = market_contract.agent_buy(
+        H160::from_str(token).map_err(AgentError::parse)?,
+        chosen_collection.collectionId,
+        U256::from(1),
+        U256::from(agent.id),
+    );
+    let client = market_contract.client();
 
-                        match tx_hash {
-                            Some(tx) => {
-                                if tx.status == Some(U64::from(1)) {
-                                    eprintln!("Success")
-                                } else {
-                                    eprintln!("Error in sending Transaction");
-                                }
-                            }
-                            None => {
-                                eprintln!("Error in sending Transaction");
-                            }
-                        }
-                    } else {
-                        eprintln!("Error in sending Transaction");
-                    }
-                }
+    let sent = send_transaction(
+        &client,
+        agent.id,
+        "agentBuy",
+        network().market.parse::<Address>().map_err(AgentError::parse)?,
+        tx.data().cloned(),
+        tx.value().cloned(),
+    )
+    .await?;
+    info!(tx_hash = ?sent.hash, "agentBuy sent for agent_{}", agent.id);
 
-                Err(err) => {
-                    eprintln!("Error in buy method for agent buy: {:?}", err);
-                }
-            }
-        }
-        Err(err) => {
-            eprintln!("Error in finding collection within balance range: {}", err);
+    match sent.receipt {
+        Some(tx) if !succeeded(&tx) => Err(AgentError::reverted(format!(
+            "agentBuy reverted: {:?}",
+            sent.hash
+        ))),
+        _ => {
+            info!(tx_hash = ?sent.hash, "agentBuy confirmed");
+            Ok(())
         }
     }
-
-    Ok(())
 }
@@ -2,4 +2,6 @@ pub mod agent_manager;
 pub mod publish;
 pub mod lead;
 pub mod remix;
-pub mod mint;
\ No newline at end of file
+pub mod mint;
+pub mod respond;
+pub mod feed;
\ No newline at end of file
@@ -1,12 +1,17 @@
+use chrono::Utc;
 use serde_json::to_string;
-use std::{error::Error, io};
+use tracing::{error, info};
 use uuid::Uuid;
 
 use crate::utils::{
-    helpers::format_instructions,
+    helpers::{format_instructions, text_model},
     ipfs::upload_lens_storage,
     lens::make_publication,
-    types::{Collection, Content, Image, Publication, SavedTokens, TripleAAgent},
+    memory::{recall_memory, remember},
+    types::{
+        AgentError, Collection, Content, Image, MemoryEntry, MemoryKind, MemoryQuery, Publication,
+        SavedTokens, TripleAAgent,
+    },
     venice::call_chat_completion,
 };
 
@@ -15,46 +20,71 @@ pub async fn publish(
     tokens: Option<SavedTokens>,
     collection: &Collection,
     collection_instructions: &str,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    let model = if agent.model.contains("dolphin") {
-        println!("DEBUG: Overriding deprecated model {} with llama-3.3-70b", agent.model);
-        "llama-3.3-70b"
-    } else {
-        &agent.model
-    };
+) -> Result<Vec<String>, AgentError> {
+    let model = text_model(&agent.model);
+    let auth_tokens = tokens
+        .as_ref()
+        .map(|tokens| tokens.tokens.access_token.clone())
+        .ok_or_else(|| AgentError::lens("No Lens tokens to publish with"))?;
+    let memory = recall_memory(
+        agent.id,
+        &MemoryQuery {
+            author: None,
+            collection_id: Some(collection.collection_id),
+            text: collection.description.clone(),
+        },
+    );
     match call_chat_completion(
         collection,
         &format_instructions(&agent),
         collection_instructions,
+        &memory,
         &agent.id,
         model,
     )
     .await
     {
-        Ok(llm_message) => match format_publication(agent, tokens, &llm_message, &collection).await
+        Ok(llm_message) => match format_publication(agent, &auth_tokens, &llm_message, &collection).await
         {
-            Ok(_) => Ok(()),
+            Ok(hash) => {
+                remember(
+                    agent.id,
+                    MemoryEntry {
+                        at: Utc::now().timestamp(),
+                        kind: MemoryKind::Post,
+                        collection_id: collection.collection_id,
+                        collection_title: collection.title.clone(),
+                        author: None,
+                        post_id: None,
+                        replied_to: None,
+                        feed: None,
+                        text: llm_message,
+                        hash: hash.clone(),
+                    },
+                );
+                Ok(vec![hash])
+            }
             Err(err) => {
-                eprintln!(
+                error!(
                     "Error in making lens post for agent_{}: {:?}",
                     agent.id, err
                 );
-                Ok(())
+                Err(err)
             }
         },
         Err(err) => {
-            eprintln!("Error with Venice completion: {:?}", err);
-            Ok(())
+            error!("Error with Venice completion: {:?}", err);
+            Err(err)
         }
     }
 }
 
 async fn format_publication(
     agent: &TripleAAgent,
-    tokens: Option<SavedTokens>,
+    auth_tokens: &str,
     llm_message: &str,
     collection: &Collection,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
+) -> Result<String, AgentError> {
     let focus = String::from("IMAGE");
     let schema = "https://json-schemas.lens.dev/posts/image/3.0.0.json".to_string();
     let tags = vec![
@@ -88,36 +118,27 @@ async fn format_publication(
     let content = match upload_lens_storage(publication_json).await {
         Ok(con) => con,
         Err(e) => {
-            eprintln!("Error uploading content to Lens Storage: {}", e);
-            return Err(Box::new(io::Error::new(
-                io::ErrorKind::Other,
-                format!("Error uploading content to Lens Storage: {}", e),
-            )));
+            error!("Error uploading content to Lens Storage: {}", e);
+            return Err(e);
         }
     };
 
     let res = make_publication(
         &content,
         agent.id,
-        &tokens.as_ref().unwrap().tokens.access_token,
+        auth_tokens,
         None,
     )
-    .await
-    .map_err(|e| Box::new(e.to_string()));
-
-    println!("Lens response for agent_{}: {:?}", agent.id, res);
+    .await;
 
     match res {
         Ok(success) => {
-            eprintln!("Post success: {:?}", success);
-            Ok(())
+            info!(post_hash = %success, "Post published for agent_{}", agent.id);
+            Ok(success)
         }
         Err(e) => {
-            eprintln!("Error processing message for agent_{}: {:?}", agent.id, e);
-            Err(Box::new(io::Error::new(
-                io::ErrorKind::Other,
-                "Error sending message",
-            )))
+            error!("Error processing message for agent_{}: {:?}", agent.id, e);
+            Err(e)
         }
     }
 }
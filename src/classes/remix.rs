@@ -1,25 +1,19 @@
-use base64::{engine::general_purpose::STANDARD, Engine};
-use ethers::{
-    contract::ContractInstance,
-    core::k256::ecdsa::SigningKey,
-    middleware::SignerMiddleware,
-    providers::{Http, Provider},
-    signers::Wallet,
-};
-use rand::{thread_rng, Rng};
-use reqwest::Client;
-use serde_json::{to_string, Value};
-use std::{env, error::Error, io, sync::Arc};
-use uuid::Uuid;
+use std::sync::Arc;
+use tracing::{debug, error, warn};
 
 use crate::utils::{
+    bindings::{SignerClient, TripleACollectionManager},
     constants::{
-        INFURA_GATEWAY, NEGATIVE_PROMPT, REMIX_FEED, STYLE_PRESETS, VENICE_API, ZERO_ADDRESS,
+        DEFAULT_IMAGE_MODEL, NEGATIVE_PROMPT, ZERO_ADDRESS,
+    },
+    helpers::{hashes_or_error, mint_collection, upload_image_post, text_model},
+    image::{configured_image_model, generate_image, source_image},
+    ipfs::upload_image_to_ipfs,
+    lens::{feed_info, make_publication},
+    network::network,
+    types::{
+        AgentError, Collection, ImageRequest, SavedTokens, TripleAAgent,
     },
-    helpers::mint_collection,
-    ipfs::{upload_image_to_ipfs, upload_lens_storage},
-    lens::make_publication,
-    types::{Collection, Content, Image, Publication, SavedTokens, TripleAAgent},
     venice::{call_image_details, call_prompt},
 };
 
@@ -27,189 +21,157 @@ pub async fn remix(
     agent: &TripleAAgent,
     collection: &Collection,
     tokens: Option<SavedTokens>,
-    collection_manager_contract: Arc<
-        ContractInstance<
-            Arc<SignerMiddleware<Arc<Provider<Http>>, Wallet<SigningKey>>>,
-            SignerMiddleware<Arc<Provider<Http>>, Wallet<SigningKey>>,
-        >,
-    >,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    let model = if agent.model.contains("dolphin") {
-        println!("DEBUG: Overriding deprecated model {} with llama-3.3-70b", agent.model);
-        "llama-3.3-70b"
-    } else {
-        &agent.model
-    };
+    collection_manager_contract: Arc<TripleACollectionManager<SignerClient>>,
+) -> Result<Vec<String>, AgentError> {
+    let model = text_model(&agent.model);
+    let auth_tokens = tokens
+        .as_ref()
+        .map(|tokens| tokens.tokens.access_token.clone())
+        .ok_or_else(|| AgentError::lens("No Lens tokens to announce remixes with"))?;
 
-    match call_prompt(&collection.description, model).await {
-        Ok((prompt, mut image_model)) => {
+    match call_prompt(agent.id, &collection.description, model).await {
+        Ok((prompt, image_model)) => {
+            let mut image_model = match image_model {
+                Some(image_model) => image_model,
+                None => configured_image_model(agent.id)?,
+            };
             if image_model == "flux-dev-uncensored" {
-                println!("DEBUG: Overriding deprecated image model {} with qwen-image", image_model);
-                image_model = "qwen-image".to_string();
+                debug!(
+                    "Overriding deprecated image model {} with {}",
+                    image_model, DEFAULT_IMAGE_MODEL
+                );
+                image_model = DEFAULT_IMAGE_MODEL.to_string();
             }
-            let client = Client::new();
-
-            let image_response = client
-                .get(&format!(
-                    "{}ipfs/{}",
-                    INFURA_GATEWAY,
-                    collection.image.trim_start_matches("ipfs://")
-                ))
-                .send()
-                .await?;
+            let source = source_image(agent.id, &collection.image).await?;
 
-            if image_response.status() == 200 {
-                let bytes = image_response.bytes().await?;
+            let image = generate_image(
+                agent.id,
+                ImageRequest {
+                    task: "remix".to_string(),
+                    model: image_model.clone(),
+                    prompt: prompt.clone(),
+                    negative_prompt: Some(NEGATIVE_PROMPT.to_string()),
+                    width: 768,
+                    height: 768,
+                    seed: None,
+                    style_preset: None,
+                    source,
+                    strength: None,
+                },
+            )
+            .await?;
 
-                let venice_key =
-                    env::var("VENICE_KEY").expect("VENICE_KEY no está configurada en .env");
-                let payload_inicial = serde_json::json!({
-                    "model": image_model,
-                    "prompt": prompt,
-                    "width": 768,
-                    "height": 768,
-                    "negative_prompt": NEGATIVE_PROMPT,
-                });
-
-                let response = client
-                    .post(format!("{}image/generate", VENICE_API))
-                    .header("Content-Type", "application/json")
-                    .header("Authorization", format!("Bearer {}", venice_key))
-                    .json(&payload_inicial)
-                    .send()
-                    .await?;
-
-                if response.status() == 200 {
-                    let json: Value = response.json().await?;
-                    let images = json
-                        .get("images")
-                        .and_then(|v| v.as_array())
-                        .cloned()
-                        .unwrap_or_else(Vec::new);
-                    let image = images
-                        .first()
-                        .and_then(|v| v.as_str())
-                        .unwrap_or("")
-                        .to_string();
-                    match call_image_details(&agent.model, false).await {
-                        Ok((title, description, amount, prices)) => {
-                            match upload_image_to_ipfs(&image).await {
-                                Ok(ipfs) => {
-                                    match mint_collection(
-                                        &description,
-                                        &format!("ipfs://{}", ipfs.Hash),
-                                        &title,
-                                        amount,
-                                        collection_manager_contract,
-                                        prices,
-                                        &agent,
-                                        collection.collection_id,
-                                        &agent.model,
-                                        &prompt,
-                                        &image_model,
-                                        0u8,
-                                        None,
-                                        true,
-                                        ZERO_ADDRESS,
+            match call_image_details(agent.id, &agent.model, false).await {
+                Ok((title, description, amount, prices)) => {
+                    match upload_image_to_ipfs(&image).await {
+                        Ok(ipfs) => {
+                            match mint_collection(
+                                &description,
+                                &format!("ipfs://{}", ipfs.Hash),
+                                &title,
+                                amount,
+                                collection_manager_contract,
+                                prices,
+                                agent,
+                                collection.collection_id,
+                                &agent.model,
+                                &prompt,
+                                &image_model,
+                                0u8,
+                                None,
+                                true,
+                                ZERO_ADDRESS,
+                            )
+                            .await
+                            {
+                                Ok(tx_hash) => {
+                                    // The collection is minted, so a failed announcement
+                                    // must not send the task back to mint it again.
+                                    let mut errors = vec![];
+                                    let mut hashes = vec![tx_hash];
+                                    match announce_remix(
+                                        agent,
+                                        &auth_tokens,
+                                        title,
+                                        description,
+                                        &image.mime_type,
+                                        &ipfs.Hash,
                                     )
                                     .await
                                     {
-                                        Ok(_) => {
-                                            let focus = String::from("IMAGE");
-                                            let schema =
-                                        "https://json-schemas.lens.dev/posts/image/3.0.0.json"
-                                            .to_string();
-                                            let tags = vec![
-                                                "tripleA".to_string(),
-                                                title.replace(" ", "").to_lowercase(),
-                                            ];
-
-                                            let publication = Publication {
-                                                schema,
-                                                lens: Content {
-                                                    mainContentFocus: focus,
-                                                    title,
-                                                    content: description,
-                                                    id: Uuid::new_v4().to_string(),
-                                                    locale: "en".to_string(),
-                                                    tags,
-                                                    image: Some(Image {
-                                                        tipo: "image/png".to_string(),
-                                                        item: format!("ipfs://{}", ipfs.Hash),
-                                                    }),
-                                                },
-                                            };
-
-                                            let publication_json = to_string(&publication)?;
-
-                                            let content =
-                                                match upload_lens_storage(publication_json).await {
-                                                    Ok(con) => con,
-                                                    Err(e) => {
-                                                        eprintln!(
-                                                "Error uploading content to Lens Storage: {}",
-                                                e
-                                            );
-                                                        return Err(Box::new(io::Error::new(
-                                                            io::ErrorKind::Other,
-                                                            format!(
-                                                    "Error uploading content to Lens Storage: {}",
-                                                    e
-                                                ),
-                                                        )));
-                                                    }
-                                                };
-
-                                            let _ = make_publication(
-                                                &content,
-                                                agent.id,
-                                                &tokens.as_ref().unwrap().tokens.access_token,
-                                                // Some(REMIX_FEED.to_string()),
-                                                None,
-                                            )
-                                            .await;
-                                        }
+                                        Ok(hash) => hashes.push(hash),
                                         Err(err) => {
-                                            return Err(Box::new(std::io::Error::new(
-                                                std::io::ErrorKind::Other,
-                                                format!("Error with creating remix {:?}", err),
-                                            )));
+                                            error!(
+                                                "Error announcing remix for agent_{}: {:?}",
+                                                agent.id, err
+                                            );
+                                            errors.push(err);
                                         }
                                     }
 
-                                    Ok(())
-                                }
-                                Err(err) => {
-                                    return Err(Box::new(std::io::Error::new(
-                                        std::io::ErrorKind::Other,
-                                        format!("Error in uploading image to IPFS {:?}", err),
-                                    )));
+                                    hashes_or_error(hashes, errors)
                                 }
+                                Err(err) => Err(err),
                             }
                         }
-                        Err(err) => {
-                            return Err(Box::new(std::io::Error::new(
-                                std::io::ErrorKind::Other,
-                                format!("Error with creating remix {:?}", err),
-                            )));
-                        }
+                        Err(err) => Err(err),
                     }
-                } else {
-                    return Err(Box::new(std::io::Error::new(
-                        std::io::ErrorKind::Other,
-                        format!("Error in sending to Venice {:?}", response.status()),
-                    )));
                 }
-            } else {
-                return Err(Box::new(std::io::Error::new(
-                    std::io::ErrorKind::Other,
-                    "Error in with source image base64 in remix",
-                )));
+                Err(err) => Err(err),
             }
         }
         Err(err) => {
-            eprintln!("Error with image prompt: {}", err);
-            Ok(())
+            error!("Error with image prompt: {}", err);
+            Err(err)
+        }
+    }
+}
+
+async fn announce_remix(
+    agent: &TripleAAgent,
+    auth_tokens: &str,
+    title: String,
+    description: String,
+    mime_type: &str,
+    ipfs_hash: &str,
+) -> Result<String, AgentError> {
+    let content = upload_image_post(title, description, mime_type, ipfs_hash).await?;
+    publish_remix(&content, agent.id, auth_tokens).await
+}
+
+// Remix announcements go to the network's remix feed when one is set. A feed that
+// rejects the post falls back to the global timeline so the remix is still announced.
+async fn publish_remix(
+    content: &str,
+    agent_id: u32,
+    auth_tokens: &str,
+) -> Result<String, AgentError> {
+    let Some(feed) = &network().remix_feed else {
+        return make_publication(content, agent_id, auth_tokens, None).await;
+    };
+
+    match feed_info(feed).await {
+        Ok(_) => {}
+        Err(err) if !err.is_retryable() => {
+            warn!(
+                "Remix feed {} is not usable, posting agent_{} to the global timeline: {}",
+                feed, agent_id, err
+            );
+            return make_publication(content, agent_id, auth_tokens, None).await;
+        }
+        Err(err) => warn!("Could not check remix feed {}: {}", feed, err),
+    }
+
+    // Only a rejection from Lens falls back. Any other error may come after the post was
+    // broadcast, and posting again would publish the remix twice.
+    match make_publication(content, agent_id, auth_tokens, Some(feed.clone())).await {
+        Err(err @ AgentError::Lens { retryable: false, .. }) => {
+            warn!(
+                "Remix feed {} rejected agent_{}, posting to the global timeline: {}",
+                feed, agent_id, err
+            );
+            make_publication(content, agent_id, auth_tokens, None).await
         }
+        result => result,
     }
 }
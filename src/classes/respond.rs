@@ -0,0 +1,236 @@
+use crate::classes::lead::format_response;
+use crate::utils::{
+    helpers::{format_instructions, hashes_or_error, text_model},
+    lens::{make_comment, notifications},
+    memory::{recall_memory, remember},
+    respond::respond_worker,
+    types::{
+        AgentError, AgentMemory, AgentStore, Claims, Collection, MemoryEntry, MemoryKind,
+        MemoryQuery, SavedTokens, StoreState, TripleAAgent,
+    },
+    venice::call_comment_completion,
+};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::{collections::BTreeMap, sync::Mutex};
+use tracing::{error, info};
+
+// Notifications being answered right now, so two Respond tasks never reply to the same post.
+static CLAIMED: Claims = Claims::new();
+// Replies per agent that running Respond tasks may still post but haven't recorded yet.
+static RESERVED: Mutex<BTreeMap<u32, u32>> = Mutex::new(BTreeMap::new());
+
+// A share of the daily reply limit held by one Respond task, so tasks running side by side
+// can't each spend the whole limit. Whatever is left is given back on drop.
+struct Reservation {
+    agent_id: u32,
+    slots: u32,
+}
+
+impl Reservation {
+    fn take(
+        agent: &TripleAAgent,
+        store: &AgentStore,
+        wanted: u32,
+    ) -> Result<(Self, StoreState), AgentError> {
+        let mut reserved = RESERVED.lock().unwrap_or_else(|e| e.into_inner());
+        let state = store.load()?;
+        let held = reserved.get(&agent.id).copied().unwrap_or_default();
+        let slots = wanted.min(
+            agent
+                .respond
+                .daily_limit
+                .saturating_sub(state.replied_today + held),
+        );
+        *reserved.entry(agent.id).or_default() += slots;
+
+        Ok((
+            Reservation {
+                agent_id: agent.id,
+                slots,
+            },
+            state,
+        ))
+    }
+
+    fn release(&mut self, slots: u32) {
+        let slots = slots.min(self.slots);
+        self.slots -= slots;
+
+        let mut reserved = RESERVED.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(held) = reserved.get_mut(&self.agent_id) {
+            *held = held.saturating_sub(slots);
+        }
+    }
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        self.release(self.slots);
+    }
+}
+
+fn is_recent(post: &Value, cutoff: i64) -> bool {
+    match post["timestamp"].as_str().map(DateTime::parse_from_rfc3339) {
+        Some(Ok(timestamp)) => timestamp.timestamp() >= cutoff,
+        _ => true,
+    }
+}
+
+pub async fn respond(
+    agent: &TripleAAgent,
+    collection: &Collection,
+    store: &AgentStore,
+    tokens: Option<SavedTokens>,
+    collection_instructions: &str,
+) -> Result<Vec<String>, AgentError> {
+    let model = text_model(&agent.model);
+    let auth_tokens = tokens
+        .as_ref()
+        .map(|tokens| tokens.tokens.access_token.clone())
+        .ok_or_else(|| AgentError::lens("No Lens tokens to read notifications with"))?;
+
+    let (mut reservation, state) = Reservation::take(
+        agent,
+        store,
+        respond_worker(&agent.respond, collection.collection_id).max_replies,
+    )?;
+
+    if reservation.slots == 0 {
+        info!(
+            "Agent_{} reached its reply limit ({} today), skipping Respond",
+            agent.id, state.replied_today
+        );
+        return Ok(vec![]);
+    }
+
+    // Replies from before the store recorded them are only known to memory.
+    let mut handled = state.replied;
+    handled.extend(
+        AgentMemory::open(agent.id)?
+            .entries()?
+            .into_iter()
+            .filter(|entry| entry.kind == MemoryKind::Reply)
+            .filter_map(|entry| entry.post_id),
+    );
+    let cutoff = Utc::now().timestamp() - agent.respond.lookback as i64;
+
+    let pending: Vec<Value> = notifications(&auth_tokens)
+        .await?
+        .into_iter()
+        .filter(|post| {
+            let post_id = post["id"].as_str().unwrap_or_default();
+            let author = post["author"]["address"].as_str().unwrap_or_default();
+            !handled.contains(post_id)
+                && !author.eq_ignore_ascii_case(&agent.account_address)
+                && post["metadata"]["content"]
+                    .as_str()
+                    .is_some_and(|content| !content.trim().is_empty())
+                && is_recent(post, cutoff)
+        })
+        .filter(|post| CLAIMED.claim(post["id"].as_str().unwrap_or_default()))
+        .take(reservation.slots as usize)
+        .collect();
+    reservation.release(reservation.slots - pending.len() as u32);
+
+    info!(
+        "Agent_{} has {} notification(s) to answer for collection {}",
+        agent.id,
+        pending.len(),
+        collection.collection_id
+    );
+
+    let mut hashes = vec![];
+    let mut errors = vec![];
+
+    for post in pending {
+        let post_id = post["id"].as_str().unwrap_or_default().to_string();
+        let result = reply(
+            agent,
+            collection,
+            &auth_tokens,
+            collection_instructions,
+            model,
+            &post,
+        )
+        .await;
+
+        match result {
+            Ok(hash) => {
+                match store.save_replied(&post_id) {
+                    Ok(()) => CLAIMED.release(&post_id),
+                    // The claim stays, so at least this process won't answer the post again.
+                    Err(err) => {
+                        error!(
+                            "Replied to {} for agent_{} with {} but couldn't record it: {:?}",
+                            post_id, agent.id, hash, err
+                        );
+                        errors.push(err);
+                    }
+                }
+                hashes.push(hash);
+            }
+            Err(err) => {
+                CLAIMED.release(&post_id);
+                error!("Error replying to {} for agent_{}: {:?}", post_id, agent.id, err);
+                errors.push(err);
+            }
+        }
+        reservation.release(1);
+    }
+
+    hashes_or_error(hashes, errors)
+}
+
+async fn reply(
+    agent: &TripleAAgent,
+    collection: &Collection,
+    auth_tokens: &str,
+    collection_instructions: &str,
+    model: &str,
+    post: &Value,
+) -> Result<String, AgentError> {
+    let post_id = post["id"].as_str().unwrap_or_default();
+    let content = post["metadata"]["content"].as_str().unwrap_or_default().to_string();
+    let author = post["author"]["address"].as_str().map(|author| author.to_string());
+
+    let memory = recall_memory(
+        agent.id,
+        &MemoryQuery {
+            author: author.clone(),
+            collection_id: Some(collection.collection_id),
+            text: content.clone(),
+        },
+    );
+    let (llm_response, image) = call_comment_completion(
+        agent.id,
+        &content,
+        &format_instructions(agent),
+        collection_instructions,
+        &collection.description,
+        &memory,
+        model,
+    )
+    .await?;
+
+    let lens_content = format_response(&llm_response, collection, image).await?;
+    let hash = make_comment(&lens_content, agent.id, auth_tokens, post_id).await?;
+
+    remember(
+        agent.id,
+        MemoryEntry {
+            at: Utc::now().timestamp(),
+            kind: MemoryKind::Reply,
+            collection_id: collection.collection_id,
+            collection_title: collection.title.clone(),
+            author,
+            post_id: Some(post_id.to_string()),
+            replied_to: Some(content),
+            feed: None,
+            text: llm_response,
+            hash: hash.clone(),
+        },
+    );
+
+    Ok(hash)
+}
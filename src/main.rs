@@ -1,135 +1,244 @@
-use chrono::{ Timelike, Utc };
-use dotenv::{ dotenv, var };
-use serde_json::Value;
-use std::{ error::Error, time::Duration };
-use tokio::spawn;
+use chrono::Utc;
+use dotenv::dotenv;
+use std::{ collections::HashMap, time::{ Duration, Instant } };
+use tokio::task::JoinSet;
+use tracing::{error, info, info_span, warn, Instrument};
 use utils::{
+    analytics::{ analytics_interval, collect_analytics },
+    control::{
+        control_settings,
+        generation,
+        is_paused,
+        record_activity,
+        register_control,
+        serve_control,
+        trigger,
+    },
+    dry_run::{ dry_run, settings },
+    logging::init_logging,
+    metrics::{ metrics_addr, serve_metrics },
+    network::{init_network, validate_remix_feed},
+    prompts::validate_prompts,
+    roster::load_roster,
+    schedule::{ describe_schedule, due_runs, fires_between, next_fire },
     types::*,
 };
 mod classes;
 mod utils;
 
+const RESTART_DELAY: u64 = 30;
+const MAX_RESTART_DELAY: u64 = 600;
+const HEALTHY_RUN: u64 = 3600;
+
 #[tokio::main]
-async fn main() -> Result<(), Box<dyn Error + Send + Sync>> {
+async fn main() -> Result<(), AgentError> {
     dotenv().ok();
+    init_logging();
+
+    let network = init_network()?;
+    info!(
+        "Network: {} (chain {}, rpc {}, subgraph {})",
+        network.name, network.chain_id, network.rpc_url, network.subgraph_url
+    );
+
+    validate_prompts()?;
+    validate_remix_feed().await?;
+
+    let mut managers = load_roster().await?;
+
+    if dry_run() {
+        let settings = settings();
+        info!(
+            "Dry run enabled: nothing will be posted or sent on chain. Report at {:?}, fixtures {:?} in {:?}",
+            settings.report, settings.fixtures, settings.fixtures_dir
+        );
+    }
+
+    for manager in &managers {
+        info!("Starting agent: {} (ID: {})", manager.agent.name, manager.agent.id);
+        info!("Agent wallet: {}", manager.agent.wallet);
+        info!("Agent schedule: {}", describe_schedule(&manager.agent.schedule));
+        register_control(manager);
+    }
+
+    match control_settings()? {
+        Some(settings) => {
+            tokio::spawn(serve_control(settings));
+        }
+        None => info!("Control API disabled, set AGENT_CONTROL_TOKEN to enable it"),
+    }
+
+    if let Some(addr) = metrics_addr()? {
+        tokio::spawn(serve_metrics(addr));
+    }
+
+    match analytics_interval() {
+        Some(_) if dry_run() => info!("Analytics collector disabled in dry run"),
+        Some(every) => {
+            for manager in &managers {
+                let agent_id = manager.agent.id;
+                tokio::spawn(
+                    collect_analytics(agent_id, every).instrument(info_span!("analytics", agent_id))
+                );
+            }
+        }
+        None => info!("Analytics collector disabled, set AGENT_ANALYTICS_INTERVAL_SECS to enable it"),
+    }
+
+    // Only at startup: a restarted loop must not respawn plans whose tasks from the
+    // crashed loop are still running.
+    for manager in &mut managers {
+        let agent_id = manager.agent.id;
+        manager.resume_pending().instrument(info_span!("agent", agent_id)).await;
+    }
+
+    supervise(managers).await;
+
+    Ok(())
+}
 
-    let agent_id: u32 = var("AGENT_ID")
-        .expect("AGENT_ID not set")
-        .parse()
-        .expect("AGENT_ID must be a number");
-
-    let agent_name = var("AGENT_NAME").expect("AGENT_NAME not set");
-    let agent_bio = var("AGENT_BIO").expect("AGENT_BIO not set");
-    let agent_lore = var("AGENT_LORE").expect("AGENT_LORE not set");
-    let agent_adjectives = var("AGENT_ADJECTIVES").expect("AGENT_ADJECTIVES not set");
-    let agent_style = var("AGENT_STYLE").expect("AGENT_STYLE not set");
-    let agent_knowledge = var("AGENT_KNOWLEDGE").expect("AGENT_KNOWLEDGE not set");
-    let agent_model = var("AGENT_MODEL").unwrap_or_else(|_| "llama-3.3-70b".to_string());
-    let agent_cover = var("AGENT_COVER").expect("AGENT_COVER not set");
-    let agent_custom_instructions = var("AGENT_CUSTOM_INSTRUCTIONS")
-        .unwrap_or_else(|_| "Be creative and engaging".to_string());
-    let agent_wallet = var("AGENT_WALLET").expect("AGENT_WALLET not set");
-    let agent_account_address = var("AGENT_ACCOUNT_ADDRESS").expect("AGENT_ACCOUNT_ADDRESS not set");
-    let agent_private_key = var("AGENT_PRIVATE_KEY").expect("AGENT_PRIVATE_KEY not set");
-
-    let agent_clock: u32 = var("AGENT_CLOCK")
-        .expect("AGENT_CLOCK not set")
-        .parse()
-        .expect("AGENT_CLOCK must be a number");
-
-    let agent_feeds_str = var("AGENT_FEEDS").unwrap_or_else(|_| "[]".to_string());
-    let agent_feeds: Vec<String> = serde_json::from_str(&agent_feeds_str)
-        .unwrap_or_else(|_| Vec::new());
-
-    let message_examples_str = var("AGENT_MESSAGE_EXAMPLES").unwrap_or_else(|_| "[]".to_string());
-    let message_examples_array: Vec<Vec<Value>> = serde_json::from_str(&message_examples_str)
-        .unwrap_or_else(|_| Vec::new());
-
-    let message_examples: Vec<Vec<MessageExample>> = message_examples_array
-        .iter()
-        .map(|group| {
-            group
-                .iter()
-                .map(|msg| MessageExample {
-                    user: msg["user"].as_str().unwrap_or("").to_string(),
-                    content: Text {
-                        text: msg["content"]["text"].as_str().unwrap_or("").to_string(),
-                    },
-                })
-                .collect()
-        })
+async fn supervise(managers: Vec<AgentManager>) {
+    let mut loops = JoinSet::new();
+    let mut running = HashMap::new();
+    let mut restarts: HashMap<u32, u32> = HashMap::new();
+    let templates: HashMap<u32, AgentManager> = managers
+        .into_iter()
+        .map(|manager| (manager.agent.id, manager))
         .collect();
 
-    std::env::set_var(&format!("ID_{}", agent_id), &agent_private_key);
-
-    let agent = TripleAAgent {
-        id: agent_id,
-        name: agent_name,
-        bio: agent_bio,
-        lore: agent_lore,
-        adjectives: agent_adjectives,
-        style: agent_style,
-        knowledge: agent_knowledge,
-        message_examples,
-        model: agent_model,
-        cover: agent_cover,
-        custom_instructions: agent_custom_instructions,
-        wallet: agent_wallet,
-        clock: agent_clock,
-        last_active_time: Utc::now().timestamp() as u32,
-        account_address: agent_account_address,
-        feeds: agent_feeds,
-    };
-
-    println!("Starting agent: {} (ID: {})", agent.name, agent.id);
-    println!("Agent wallet: {}", agent.wallet);
-    println!("Agent clock: {} seconds ({}h {}m {}s)",
-        agent.clock,
-        agent.clock / 3600,
-        (agent.clock % 3600) / 60,
-        agent.clock % 60
-    );
+    for (agent_id, manager) in &templates {
+        let handle = loops.spawn(
+            activity_loop(manager.clone()).instrument(info_span!("agent", agent_id = *agent_id))
+        );
+        running.insert(handle.id(), (*agent_id, Instant::now()));
+    }
 
-    let agent_manager = AgentManager::new(&agent).expect("Failed to create agent manager");
+    while let Some(result) = loops.join_next_with_id().await {
+        let (task_id, crashed) = match result {
+            Ok((task_id, _)) => (task_id, false),
+            Err(err) => (err.id(), err.is_panic()),
+        };
 
-    spawn(activity_loop(agent_manager));
+        let Some((agent_id, started)) = running.remove(&task_id) else {
+            continue;
+        };
 
-    loop {
-        tokio::time::sleep(Duration::from_secs(3600)).await;
+        if !crashed {
+            info!("Activity loop for agent {} finished", agent_id);
+            continue;
+        }
+
+        let attempts = restarts.entry(agent_id).or_insert(0);
+        if started.elapsed().as_secs() >= HEALTHY_RUN {
+            *attempts = 0;
+        }
+        let delay = (RESTART_DELAY << (*attempts).min(5)).min(MAX_RESTART_DELAY);
+        *attempts += 1;
+
+        warn!(
+            "Activity loop for agent {} crashed, restarting in {}s (attempt {})",
+            agent_id, delay, attempts
+        );
+
+        let manager = templates[&agent_id].clone();
+        let handle = loops.spawn(
+            async move {
+                tokio::time::sleep(Duration::from_secs(delay)).await;
+                activity_loop(manager).await
+            }.instrument(info_span!("agent", agent_id))
+        );
+        running.insert(handle.id(), (agent_id, Instant::now()));
     }
 }
 
 async fn activity_loop(mut agent_manager: AgentManager) {
-    loop {
-        if should_trigger(&agent_manager.agent) {
-            println!("\n=== Agent {} triggering activity ===", agent_manager.agent.id);
+    let mut last_check = Utc::now();
+    let mut seen_generation = generation(agent_manager.agent.id);
+    let triggered = trigger(agent_manager.agent.id);
+
+    if dry_run() {
+        info!("=== Agent {} dry run activity ===", agent_manager.agent.id);
+        run_activity(&mut agent_manager, &mut seen_generation).await;
+    }
 
-            if let Err(err) = agent_manager.resolve_activity().await {
-                eprintln!("Error resolving activity for agent {}: {:?}", agent_manager.agent.id, err);
+    loop {
+        let next = match next_fire(&agent_manager.agent.schedule, last_check) {
+            Some(next) => next,
+            None => {
+                warn!(
+                    "No upcoming activation for agent {}, stopping activity loop",
+                    agent_manager.agent.id
+                );
+                return;
+            }
+        };
+
+        info!(
+            "Agent {} waiting for next activation at {} ({}s)",
+            agent_manager.agent.id,
+            next,
+            (next - Utc::now()).num_seconds().max(0)
+        );
+
+        let manual = loop {
+            let remaining = (next - Utc::now()).num_seconds();
+            if remaining <= 0 {
+                break false;
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(Duration::from_secs(remaining.min(300) as u64)) => {}
+                _ = triggered.notified() => break true,
             }
+        };
 
-            println!("=== Agent {} activity complete ===\n", agent_manager.agent.id);
-        } else {
-            let now = Utc::now();
-            let seconds_since_midnight = (now.hour() * 3600 + now.minute() * 60 + now.second()) as i32;
-            let diff = ((agent_manager.agent.clock as i32) - seconds_since_midnight).abs();
-
-            println!(
-                "Waiting for trigger... (clock: {}s, now: {}s, diff: {}s)",
-                agent_manager.agent.clock,
-                seconds_since_midnight,
-                diff
+        if manual {
+            info!("=== Agent {} triggering activity on request ===", agent_manager.agent.id);
+            agent_manager.current_queue.clear();
+            run_activity(&mut agent_manager, &mut seen_generation).await;
+            continue;
+        }
+
+        let now = Utc::now();
+        let fires = fires_between(&agent_manager.agent.schedule, last_check, now);
+        let runs = due_runs(&agent_manager.agent.schedule, &fires, now);
+        last_check = now;
+
+        if is_paused(agent_manager.agent.id) {
+            info!(
+                "Agent {} is paused, skipping {} activation(s)",
+                agent_manager.agent.id,
+                runs
+            );
+            continue;
+        }
+
+        if runs == 0 {
+            info!(
+                "Agent {} skipping {} missed activation(s)",
+                agent_manager.agent.id,
+                fires.len()
             );
+            continue;
         }
 
-        tokio::time::sleep(Duration::from_secs(500)).await;
+        for _ in 0..runs {
+            info!("=== Agent {} triggering activity ===", agent_manager.agent.id);
+            run_activity(&mut agent_manager, &mut seen_generation).await;
+            info!("=== Agent {} activity complete ===", agent_manager.agent.id);
+        }
     }
 }
 
-fn should_trigger(agent: &TripleAAgent) -> bool {
-    let now = Utc::now();
-    let seconds_since_midnight = (now.hour() * 3600 + now.minute() * 60 + now.second()) as i32;
-    let diff = ((agent.clock as i32) - seconds_since_midnight).abs();
+async fn run_activity(agent_manager: &mut AgentManager, seen_generation: &mut u64) {
+    let current = generation(agent_manager.agent.id);
+    if current != *seen_generation {
+        agent_manager.current_queue.clear();
+        *seen_generation = current;
+    }
 
-    diff <= 500
-}
\ No newline at end of file
+    if let Err(err) = agent_manager.resolve_activity().await {
+        error!("Error resolving activity for agent {}: {:?}", agent_manager.agent.id, err);
+    }
+
+    record_activity(agent_manager);
+}
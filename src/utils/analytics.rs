@@ -0,0 +1,446 @@
+use crate::utils::{
+    lens::post_stats,
+    store::{open_private, state_path},
+    subgraph::collection_sales,
+    types::{
+        ActivityType, AgentAnalytics, AgentError, AnalyticsPost, AnalyticsTable,
+        AnalyticsWeights, PostStats, SalesSample,
+    },
+};
+use chrono::Utc;
+use dotenv::var;
+use ethers::types::U256;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    hash::Hash,
+    io::{ErrorKind, Write},
+    str::FromStr,
+    sync::Mutex,
+    time::Duration,
+};
+use tracing::{error, info, warn};
+
+type Tasks = Vec<(usize, ActivityType)>;
+type Slots = HashMap<(U256, ActivityType), usize>;
+
+static ANALYTICS_LOCK: Mutex<()> = Mutex::new(());
+
+const DEFAULT_INTERVAL_SECS: u64 = 3600;
+const DEFAULT_WINDOW_DAYS: i64 = 7;
+const DEFAULT_MIN_SHARE: f64 = 0.5;
+// Lookups that find no post before a hash is given up on. Mint transactions and
+// posts Lens hasn't indexed yet both come back empty.
+const MAX_MISSES: u32 = 3;
+const SALE_POINTS: f64 = 5.0;
+
+fn env_or<T: FromStr>(name: &str, default: T) -> T {
+    var(name)
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(default)
+}
+
+fn window() -> i64 {
+    env_or("AGENT_ANALYTICS_WINDOW_DAYS", DEFAULT_WINDOW_DAYS) * 86400
+}
+
+pub fn analytics_interval() -> Option<Duration> {
+    let secs = env_or("AGENT_ANALYTICS_INTERVAL_SECS", DEFAULT_INTERVAL_SECS);
+    (secs > 0).then(|| Duration::from_secs(secs))
+}
+
+impl AgentAnalytics {
+    pub fn open(agent_id: u32) -> Result<Self, AgentError> {
+        Ok(AgentAnalytics {
+            agent_id,
+            path: state_path(agent_id, "analytics.json")?,
+        })
+    }
+
+    pub fn load(&self) -> Result<AnalyticsTable, AgentError> {
+        match fs::read_to_string(&self.path) {
+            Ok(contents) => Ok(serde_json::from_str(&contents).unwrap_or_else(|err| {
+                warn!(
+                    "Resetting corrupt analytics for agent_{} in {:?}: {}",
+                    self.agent_id, self.path, err
+                );
+                AnalyticsTable::default()
+            })),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(AnalyticsTable::default()),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    pub fn update(&self, change: impl FnOnce(&mut AnalyticsTable)) -> Result<(), AgentError> {
+        let _guard = ANALYTICS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut table = self.load()?;
+        change(&mut table);
+
+        let tmp = self.path.with_extension("json.tmp");
+        let mut file = open_private(&tmp, false)?;
+        file.write_all(serde_json::to_string(&table)?.as_bytes())?;
+        file.flush()?;
+        fs::rename(&tmp, &self.path)?;
+
+        Ok(())
+    }
+}
+
+pub fn track_posts(agent_id: u32, task: &ActivityType, collection_id: U256, hashes: &[String]) {
+    if hashes.is_empty() {
+        return;
+    }
+
+    let at = Utc::now().timestamp();
+    let result = AgentAnalytics::open(agent_id).and_then(|analytics| {
+        analytics.update(|table| {
+            for hash in hashes {
+                table.posts.entry(hash.clone()).or_insert(AnalyticsPost {
+                    at,
+                    task: task.clone(),
+                    collection_id,
+                    post_id: None,
+                    stats: PostStats::default(),
+                    checked: None,
+                    misses: 0,
+                });
+            }
+        })
+    });
+    if let Err(err) = result {
+        error!("Error tracking posts for agent_{}: {:?}", agent_id, err);
+    }
+}
+
+pub async fn refresh_analytics(agent_id: u32) -> Result<(), AgentError> {
+    let analytics = AgentAnalytics::open(agent_id)?;
+    let now = Utc::now().timestamp();
+    let cutoff = now - window();
+    let table = analytics.load()?;
+
+    let mut found = HashMap::new();
+    let mut missing = HashSet::new();
+    for (hash, post) in &table.posts {
+        if post.at < cutoff || post.misses >= MAX_MISSES {
+            continue;
+        }
+        match post_stats(hash).await {
+            Ok(Some(stats)) => {
+                found.insert(hash.clone(), stats);
+            }
+            Ok(None) => {
+                missing.insert(hash.clone());
+            }
+            Err(err) => {
+                warn!("Stopping post stats for agent_{} early: {}", agent_id, err);
+                break;
+            }
+        }
+    }
+
+    let collection_ids: Vec<U256> = table
+        .posts
+        .values()
+        .filter(|post| post.at >= cutoff)
+        .map(|post| post.collection_id)
+        .collect::<HashSet<_>>()
+        .into_iter()
+        .collect();
+    let mut sold: HashMap<U256, U256> = HashMap::new();
+    if !collection_ids.is_empty() {
+        match collection_sales(&collection_ids).await {
+            Ok(prices) => {
+                for price in prices {
+                    let entry = sold.entry(price.collectionId).or_default();
+                    *entry = (*entry).max(price.amountSold);
+                }
+            }
+            Err(err) => warn!("Error reading collection sales for agent_{}: {}", agent_id, err),
+        }
+    }
+
+    info!(
+        "Analytics for agent_{}: {} post(s) updated, {} not found, sales for {} collection(s)",
+        agent_id,
+        found.len(),
+        missing.len(),
+        sold.len()
+    );
+
+    analytics.update(|table| {
+        for (hash, (post_id, stats)) in found {
+            if let Some(post) = table.posts.get_mut(&hash) {
+                post.post_id = Some(post_id);
+                post.stats = stats;
+                post.checked = Some(now);
+            }
+        }
+        for hash in missing {
+            if let Some(post) = table.posts.get_mut(&hash) {
+                post.misses += 1;
+                post.checked = Some(now);
+            }
+        }
+        for (collection_id, sold) in sold {
+            table
+                .sales
+                .entry(collection_id.to_string())
+                .or_default()
+                .push(SalesSample { at: now, sold });
+        }
+
+        table.posts.retain(|_, post| post.at >= cutoff);
+        for samples in table.sales.values_mut() {
+            samples.retain(|sample| sample.at >= cutoff);
+        }
+        table.sales.retain(|_, samples| !samples.is_empty());
+    })
+}
+
+pub async fn collect_analytics(agent_id: u32, every: Duration) {
+    loop {
+        if let Err(err) = refresh_analytics(agent_id).await {
+            error!("Error collecting analytics for agent_{}: {:?}", agent_id, err);
+        }
+        tokio::time::sleep(every).await;
+    }
+}
+
+fn engagement(stats: &PostStats) -> f64 {
+    (stats.reactions + 2 * (stats.comments + stats.quotes) + 3 * stats.collects) as f64
+}
+
+fn average<K: Hash + Eq>(scores: HashMap<K, (f64, usize)>) -> HashMap<K, f64> {
+    scores
+        .into_iter()
+        .map(|(key, (total, count))| (key, total / count.max(1) as f64))
+        .collect()
+}
+
+// The best scorer keeps its full share and the rest scale down to `min_share`. With
+// nothing measured yet, every key keeps its full share.
+fn normalize<K: Hash + Eq>(scores: HashMap<K, f64>, min_share: f64) -> HashMap<K, f64> {
+    let max = scores.values().copied().fold(0.0, f64::max);
+    if max <= 0.0 {
+        return HashMap::new();
+    }
+
+    scores
+        .into_iter()
+        .map(|(key, score)| (key, min_share + (1.0 - min_share) * score / max))
+        .collect()
+}
+
+pub fn analytics_weights(agent_id: u32) -> AnalyticsWeights {
+    let table = match AgentAnalytics::open(agent_id).and_then(|analytics| analytics.load()) {
+        Ok(table) => table,
+        Err(err) => {
+            error!("Error reading analytics for agent_{}: {:?}", agent_id, err);
+            return AnalyticsWeights::default();
+        }
+    };
+    let cutoff = Utc::now().timestamp() - window();
+    let min_share = env_or("AGENT_ANALYTICS_MIN_SHARE", DEFAULT_MIN_SHARE).clamp(0.0, 1.0);
+
+    let mut activities: HashMap<ActivityType, (f64, usize)> = HashMap::new();
+    let mut posts: HashMap<U256, (f64, usize)> = HashMap::new();
+    for post in table.posts.values() {
+        if post.at < cutoff || post.post_id.is_none() {
+            continue;
+        }
+        let score = engagement(&post.stats);
+        for (total, count) in [
+            activities.entry(post.task.clone()).or_default(),
+            posts.entry(post.collection_id).or_default(),
+        ] {
+            *total += score;
+            *count += 1;
+        }
+    }
+
+    let mut collections: HashMap<U256, f64> = average(posts);
+    for (collection_id, samples) in &table.sales {
+        let (Ok(collection_id), Some(first), Some(last)) =
+            (U256::from_dec_str(collection_id), samples.first(), samples.last())
+        else {
+            continue;
+        };
+        let sold = last.sold.saturating_sub(first.sold).min(U256::from(u32::MAX)).as_u32();
+        *collections.entry(collection_id).or_default() += SALE_POINTS * sold as f64;
+    }
+
+    AnalyticsWeights {
+        activities: normalize(average(activities), min_share),
+        collections: normalize(collections, min_share),
+    }
+}
+
+// How many planned tasks each collection and activity type may run. Weaker pairs give
+// up slots, keeping at least one so they are still measured. Each freed slot then goes
+// to the strongest pair that scores higher than the one that gave it up and is still
+// below its worker frequency.
+pub fn allot_slots(weights: &AnalyticsWeights, planned: &[(U256, Vec<ActivityType>)]) -> Slots {
+    let weight = |(collection_id, task): &(U256, ActivityType)| {
+        weights.activities.get(task).copied().unwrap_or(1.0)
+            * weights.collections.get(collection_id).copied().unwrap_or(1.0)
+    };
+
+    let mut ranked: Vec<(U256, ActivityType)> = vec![];
+    let mut caps: Slots = HashMap::new();
+    for (collection_id, tasks) in planned {
+        for task in tasks {
+            let key = (*collection_id, task.clone());
+            if !caps.contains_key(&key) {
+                ranked.push(key.clone());
+            }
+            *caps.entry(key).or_default() += 1;
+        }
+    }
+    ranked.sort_by(|a, b| weight(b).total_cmp(&weight(a)));
+
+    let mut slots: Slots = caps
+        .iter()
+        .map(|(key, cap)| {
+            let kept = ((*cap as f64 * weight(key)).ceil() as usize).clamp(1, *cap);
+            (key.clone(), kept)
+        })
+        .collect();
+
+    let mut freed: Vec<f64> = ranked
+        .iter()
+        .flat_map(|key| std::iter::repeat_n(weight(key), caps[key] - slots[key]))
+        .collect();
+    freed.sort_by(f64::total_cmp);
+
+    for donor in freed {
+        if let Some(key) = ranked
+            .iter()
+            .find(|key| weight(key) > donor && slots[*key] < caps[*key])
+        {
+            *slots.entry(key.clone()).or_default() += 1;
+        }
+    }
+
+    slots
+}
+
+// Splits the pending tasks of one collection into the ones to run and the ones to skip.
+pub fn weigh_tasks(slots: &Slots, collection_id: U256, pending: Tasks) -> (Tasks, Tasks) {
+    let mut left: HashMap<ActivityType, usize> = slots
+        .iter()
+        .filter(|((id, _), _)| *id == collection_id)
+        .map(|((_, task), count)| (task.clone(), *count))
+        .collect();
+
+    pending.into_iter().partition(|(_, task)| match left.get_mut(task) {
+        Some(left) if *left > 0 => {
+            *left -= 1;
+            true
+        }
+        _ => false,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn weights(
+        activities: &[(ActivityType, f64)],
+        collections: &[(u64, f64)],
+    ) -> AnalyticsWeights {
+        AnalyticsWeights {
+            activities: activities.iter().cloned().collect(),
+            collections: collections
+                .iter()
+                .map(|(id, weight)| (U256::from(*id), *weight))
+                .collect(),
+        }
+    }
+
+    fn count(slots: &Slots, collection_id: u64, task: ActivityType) -> usize {
+        slots.get(&(U256::from(collection_id), task)).copied().unwrap_or_default()
+    }
+
+    #[test]
+    fn normalize_scales_to_min_share() {
+        let scores = HashMap::from([("lead", 10.0), ("remix", 5.0), ("mint", 0.0)]);
+        let normalized = normalize(scores, 0.5);
+
+        assert_eq!(normalized["lead"], 1.0);
+        assert_eq!(normalized["remix"], 0.75);
+        assert_eq!(normalized["mint"], 0.5);
+        assert!(normalize(HashMap::from([("lead", 0.0)]), 0.5).is_empty());
+    }
+
+    #[test]
+    fn unmeasured_tasks_keep_all_slots() {
+        let planned = vec![(U256::from(1), vec![ActivityType::Lead, ActivityType::Lead])];
+        let slots = allot_slots(&AnalyticsWeights::default(), &planned);
+
+        assert_eq!(count(&slots, 1, ActivityType::Lead), 2);
+    }
+
+    #[test]
+    fn freed_slots_go_to_stronger_activities() {
+        let planned = vec![(
+            U256::from(1),
+            [vec![ActivityType::Lead; 4], vec![ActivityType::Remix; 4]].concat(),
+        )];
+        let weights = weights(&[(ActivityType::Lead, 0.75), (ActivityType::Remix, 0.5)], &[]);
+        let slots = allot_slots(&weights, &planned);
+
+        // Lead keeps 3 of 4 and remix 2 of 4; one freed remix slot fills lead back up.
+        assert_eq!(count(&slots, 1, ActivityType::Lead), 4);
+        assert_eq!(count(&slots, 1, ActivityType::Remix), 2);
+    }
+
+    #[test]
+    fn freed_slots_go_to_stronger_collections() {
+        let planned = vec![
+            (U256::from(1), vec![ActivityType::Publish; 3]),
+            (U256::from(2), vec![ActivityType::Publish; 3]),
+        ];
+        let weights = weights(&[], &[(1, 0.6), (2, 0.3)]);
+        let slots = allot_slots(&weights, &planned);
+
+        assert_eq!(count(&slots, 1, ActivityType::Publish), 3);
+        assert_eq!(count(&slots, 2, ActivityType::Publish), 1);
+    }
+
+    #[test]
+    fn weakest_keeps_one_and_nothing_passes_its_cap() {
+        let planned = vec![
+            (U256::from(1), vec![ActivityType::Mint; 5]),
+            (U256::from(2), vec![ActivityType::Mint]),
+        ];
+        let weights = weights(&[], &[(1, 0.01), (2, 1.0)]);
+        let slots = allot_slots(&weights, &planned);
+
+        assert_eq!(count(&slots, 1, ActivityType::Mint), 1);
+        assert_eq!(count(&slots, 2, ActivityType::Mint), 1);
+    }
+
+    #[test]
+    fn weigh_tasks_keeps_the_first_of_each_type() {
+        let slots = HashMap::from([
+            ((U256::from(1), ActivityType::Lead), 1),
+            ((U256::from(1), ActivityType::Remix), 2),
+            ((U256::from(2), ActivityType::Lead), 2),
+        ]);
+        let pending = vec![
+            (0, ActivityType::Lead),
+            (1, ActivityType::Remix),
+            (2, ActivityType::Lead),
+            (3, ActivityType::Remix),
+        ];
+
+        let (kept, skipped) = weigh_tasks(&slots, U256::from(1), pending);
+        assert_eq!(
+            kept,
+            vec![(0, ActivityType::Lead), (1, ActivityType::Remix), (3, ActivityType::Remix)]
+        );
+        assert_eq!(skipped, vec![(2, ActivityType::Lead)]);
+    }
+}
@@ -0,0 +1,18 @@
+use crate::utils::types::RetryHttp;
+use ethers::{
+    contract::abigen,
+    middleware::SignerMiddleware,
+    providers::Provider,
+    signers::LocalWallet,
+};
+use std::sync::Arc;
+
+pub type SignerClient = SignerMiddleware<Arc<Provider<RetryHttp>>, LocalWallet>;
+
+abigen!(
+    TripleAAgents, "./abis/TripleAAgents.json";
+    TripleAMarket, "./abis/TripleAMarket.json";
+    TripleACollectionManager, "./abis/TripleACollectionManager.json";
+    TripleAAccessControls, "./abis/TripleAAccessControls.json";
+    AgentFeedRule, "./abis/AgentFeedRule.json";
+);
@@ -4,11 +4,13 @@ pub static AGENTS: &'static str = "0x424Fa11D84e5674809Fd0112eBa4f86d6C4ed2aD";
 pub static ACCESS_CONTROLS: &'static str = "0x4F276081A4AC2d50eEE2aA6c78a3C4C06AAE9562";
 pub static COLLECTION_MANAGER: &'static str = "0xBa53Fd19053fceFc91D091A02c71AbDcD79d856f";
 pub static MARKET: &'static str = "0x6c7a9d566F6c2a9829B940b7571A220c70817c1a";
-pub static REMIX_FEED: &'static str = "0x";
 pub static ZERO_ADDRESS: &'static str = "0x0000000000000000000000000000000000000000";
 pub static WGHO: &'static str = "0x6bDc36E20D267Ff0dd6097799f82e78907105e2F";
 pub static BONSAI: &'static str = "0xB0588f9A9cADe7CD5f194a5fe77AcD6A58250f82";
 pub static MONA: &'static str = "0x28547B5b6B405A1444A17694AC84aa2d6A03b3Bd";
+// Answered notifications are kept this long, so the Respond lookback can't exceed it.
+pub static REPLY_RETENTION_HOURS: u64 = 720;
+pub static DEFAULT_IMAGE_MODEL: &str = "qwen-image";
 pub static MODELS: &[&str] = &[
     "flux-dev-uncensored",
     "qwen-image",
@@ -18,6 +20,10 @@ pub static MODELS: &[&str] = &[
 
 pub static VENICE_API: &'static str = "https://api.venice.ai/api/v1/";
 pub static LENS_API: &'static str = "https://api.lens.xyz/graphql";
+pub static TRIPLEA_GRAPH: &str = "https://triplea.digitalax.xyz";
+pub static GROVE_API: &str = "https://api.grove.storage/";
+pub static IPFS_API: &str = "https://ipfs.infura.io:5001/api/v0/";
+pub static LENS_ORIGIN: &str = "https://triplea-66ij.onrender.com";
 pub static LENS_RPC_URL: &'static str = "https://rpc.lens.xyz";
 pub static INFURA_GATEWAY: &'static str = "https://thedial.infura-ipfs.io/";
 pub static LENS_CHAIN_ID: LazyLock<u64> = LazyLock::new(|| 232);
@@ -1,5 +1,10 @@
-use crate::utils::constants::{
-    ACCESS_CONTROLS, AGENTS, COLLECTION_MANAGER, LENS_CHAIN_ID, LENS_RPC_URL, MARKET,
+use crate::utils::{
+    bindings::{
+        SignerClient, TripleAAccessControls, TripleAAgents, TripleACollectionManager,
+        TripleAMarket,
+    },
+    network::network,
+    types::{AgentError, RetryHttp},
 };
 use aes_gcm::{
     aead::{Aead, KeyInit},
@@ -8,46 +13,44 @@ use aes_gcm::{
 use base64::{engine::general_purpose, Engine};
 use dotenv::{dotenv, from_filename, var};
 use ethers::{
-    abi::{Abi, Address},
-    contract::Contract,
+    abi::Address,
     middleware::{SignerMiddleware, Middleware},
-    providers::{Http, Provider, ProviderExt},
+    providers::{Http, Provider},
     signers::{LocalWallet, Signer},
     types::Chain,
 };
 use reqwest::Client;
 use serde_json::{from_str, Value};
 use std::{
-    error::Error,
+    str::FromStr,
     sync::{Arc, Mutex, Once},
 };
+use tracing::{error, info, warn};
 
 static INIT_PROVIDER: Once = Once::new();
 static INIT_LENS: Once = Once::new();
-static ACCESS_CONTROLS_CONTRACT: Mutex<
-    Option<Arc<Contract<SignerMiddleware<Arc<Provider<Http>>, LocalWallet>>>>,
-> = Mutex::new(None);
-static AGENTS_CONTRACT: Mutex<
-    Option<Arc<Contract<SignerMiddleware<Arc<Provider<Http>>, LocalWallet>>>>,
-> = Mutex::new(None);
-static COLLECTION_MANAGER_CONTRACT: Mutex<
-    Option<Arc<Contract<SignerMiddleware<Arc<Provider<Http>>, LocalWallet>>>>,
-> = Mutex::new(None);
-static MARKET_CONTRACT: Mutex<
-    Option<Arc<Contract<SignerMiddleware<Arc<Provider<Http>>, LocalWallet>>>>,
-> = Mutex::new(None);
-static PROVIDER: Mutex<Option<Arc<Provider<Http>>>> = Mutex::new(None);
+static ACCESS_CONTROLS_CONTRACT: Mutex<Option<Arc<TripleAAccessControls<SignerClient>>>> =
+    Mutex::new(None);
+static AGENTS_CONTRACT: Mutex<Option<Arc<TripleAAgents<SignerClient>>>> = Mutex::new(None);
+static COLLECTION_MANAGER_CONTRACT: Mutex<Option<Arc<TripleACollectionManager<SignerClient>>>> =
+    Mutex::new(None);
+static MARKET_CONTRACT: Mutex<Option<Arc<TripleAMarket<SignerClient>>>> = Mutex::new(None);
+static PROVIDER: Mutex<Option<Arc<Provider<RetryHttp>>>> = Mutex::new(None);
 static LENS_CLIENT: Mutex<Option<Arc<Client>>> = Mutex::new(None);
 static WALLET: Mutex<Option<LocalWallet>> = Mutex::new(None);
 
-pub fn initialize_provider() -> Arc<Provider<Http>> {
+pub fn initialize_provider() -> Arc<Provider<RetryHttp>> {
     INIT_PROVIDER.call_once(|| {
         dotenv().ok();
-        let chain_id = *LENS_CHAIN_ID;
-        let chain: Chain = unsafe { std::mem::transmute(chain_id as u64) };
-        let mut provider =
-            Provider::<Http>::try_from(LENS_RPC_URL).expect("Error in creating the provider");
-        let provider = provider.set_chain(chain).clone();
+        let network = network();
+        let http = Http::from_str(&network.rpc_url).expect("Error in creating the provider");
+        let mut provider = Provider::new(RetryHttp::new(http));
+        if let Some(blocktime) = Chain::try_from(network.chain_id)
+            .ok()
+            .and_then(|chain| chain.average_blocktime_hint())
+        {
+            provider.set_interval(blocktime / 2);
+        }
         *PROVIDER.lock().unwrap() = Some(Arc::new(provider));
     });
 
@@ -75,7 +78,7 @@ pub fn initialize_access_wallet() -> LocalWallet {
     let wallet = match var("FAUCET_KEY") {
         Ok(key) => match key.parse::<LocalWallet>() {
             Ok(mut wallet) => {
-                let chain_id = *LENS_CHAIN_ID;
+                let chain_id = network().chain_id;
                 wallet = wallet.with_chain_id(chain_id);
                 wallet
             }
@@ -93,18 +96,18 @@ pub fn initialize_wallet(private_key: u32) -> Option<LocalWallet> {
     match var(format!("ID_{}", private_key.to_string())) {
         Ok(key) => match key.parse::<LocalWallet>() {
             Ok(mut wallet) => {
-                let chain_id = *LENS_CHAIN_ID;
+                let chain_id = network().chain_id;
                 wallet = wallet.with_chain_id(chain_id);
                 *WALLET.lock().unwrap() = Some(wallet.clone());
                 Some(wallet)
             }
             Err(e) => {
-                eprintln!("Error in parsing private key: {:?}", e);
+                error!("Error in parsing private key: {:?}", e);
                 None
             }
         },
         Err(_) => {
-            eprintln!(
+            warn!(
                 "PRIVATE_KEY not found in .env for agent_{}, looking in /var/data/data.json...",
                 private_key
             );
@@ -123,33 +126,33 @@ pub fn initialize_wallet(private_key: u32) -> Option<LocalWallet> {
                             Ok(decrypted_private_key) => {
                                 match decrypted_private_key.parse::<LocalWallet>() {
                                     Ok(mut wallet) => {
-                                        let chain_id = *LENS_CHAIN_ID;
+                                        let chain_id = network().chain_id;
                                         wallet = wallet.with_chain_id(chain_id);
                                         *WALLET.lock().unwrap() = Some(wallet.clone());
-                                        println!("Key Found for ID_{} in var/data", private_key);
+                                        info!("Key Found for ID_{} in var/data", private_key);
                                         Some(wallet)
                                     }
                                     Err(e) => {
-                                        eprintln!("Error parsing decrypted private key: {:?}", e);
+                                        error!("Error parsing decrypted private key: {:?}", e);
                                         read_from_secret(private_key);
                                         None
                                     }
                                 }
                             }
                             Err(e) => {
-                                eprintln!("Error decrypting private key: {:?}", e);
+                                error!("Error decrypting private key: {:?}", e);
                                 read_from_secret(private_key);
                                 None
                             }
                         }
                     } else {
-                        eprintln!("ID_{} not found in data.json", private_key);
+                        warn!("ID_{} not found in data.json", private_key);
                         read_from_secret(private_key);
                         None
                     }
                 }
                 Err(e) => {
-                    eprintln!("Failed to read /var/data/data.json: {:?}", e);
+                    error!("Failed to read /var/data/data.json: {:?}", e);
                     read_from_secret(private_key);
                     None
                 }
@@ -159,7 +162,7 @@ pub fn initialize_wallet(private_key: u32) -> Option<LocalWallet> {
 }
 
 fn read_from_secret(private_key: u32) -> Option<LocalWallet> {
-    eprintln!(
+    warn!(
         "PRIVATE_KEY not found in .env or /var/data/data.json, looking in /etc/secrets/data.txt..."
     );
 
@@ -173,25 +176,25 @@ fn read_from_secret(private_key: u32) -> Option<LocalWallet> {
                             Ok(decrypted_private_key) => {
                                 match decrypted_private_key.parse::<LocalWallet>() {
                                     Ok(mut wallet) => {
-                                        let chain_id = *LENS_CHAIN_ID;
+                                        let chain_id = network().chain_id;
                                         wallet = wallet.with_chain_id(chain_id);
                                         *WALLET.lock().unwrap() = Some(wallet.clone());
-                                        println!("Key Found for ID_{} in etc/secrets", private_key);
+                                        info!("Key Found for ID_{} in etc/secrets", private_key);
                                         return Some(wallet);
                                     }
                                     Err(e) => {
-                                        eprintln!("Error parsing decrypted private key: {:?}", e);
+                                        error!("Error parsing decrypted private key: {:?}", e);
                                         return None;
                                     }
                                 }
                             }
                             Err(e) => {
-                                eprintln!("Error decrypting private key: {:?}", e);
+                                error!("Error decrypting private key: {:?}", e);
                                 return None;
                             }
                         }
                     } else {
-                        eprintln!(
+                        warn!(
                             "Details not found for ID_{} in /etc/secrets/data.txt",
                             private_key
                         );
@@ -199,11 +202,11 @@ fn read_from_secret(private_key: u32) -> Option<LocalWallet> {
                     }
                 }
             }
-            eprintln!("ID_{} not found in /etc/secrets/data.txt", private_key);
+            warn!("ID_{} not found in /etc/secrets/data.txt", private_key);
             return None;
         }
         Err(e) => {
-            eprintln!("Failed to read /etc/secrets/data.txt: {:?}", e);
+            error!("Failed to read /etc/secrets/data.txt: {:?}", e);
             return None;
         }
     }
@@ -212,10 +215,10 @@ fn read_from_secret(private_key: u32) -> Option<LocalWallet> {
 pub fn initialize_contracts(
     private_key: u32,
 ) -> Option<(
-    Arc<Contract<SignerMiddleware<Arc<Provider<Http>>, LocalWallet>>>,
-    Arc<Contract<SignerMiddleware<Arc<Provider<Http>>, LocalWallet>>>,
-    Arc<Contract<SignerMiddleware<Arc<Provider<Http>>, LocalWallet>>>,
-    Arc<Contract<SignerMiddleware<Arc<Provider<Http>>, LocalWallet>>>,
+    Arc<TripleAAccessControls<SignerClient>>,
+    Arc<TripleAAgents<SignerClient>>,
+    Arc<TripleACollectionManager<SignerClient>>,
+    Arc<TripleAMarket<SignerClient>>,
 )> {
     dotenv().ok();
     let provider = initialize_provider();
@@ -224,7 +227,7 @@ pub fn initialize_contracts(
     let wallet = match initialize_wallet(private_key) {
         Some(wallet) => wallet,
         None => {
-            eprintln!("Wallet initialization failed. Skipping agent creation.");
+            error!("Wallet initialization failed. Skipping agent creation.");
             return None;
         }
     };
@@ -236,42 +239,30 @@ pub fn initialize_contracts(
 
     let access_client = Arc::new(SignerMiddleware::new(provider.clone(), access_wallet));
 
-    let access_controls_address = ACCESS_CONTROLS
+    let network = network();
+
+    let access_controls_address = network
+        .access_controls
         .parse::<Address>()
         .expect("Error in parsing ACCESS_CONTROLS");
-    let access_controls_abi: Abi =
-        from_str(include_str!("./../../abis/TripleAAccessControls.json"))
-            .expect("Error in loading AccessControls ABI");
-    let access_controls_contract = Contract::new(
-        access_controls_address,
-        access_controls_abi,
-        access_client.clone(),
-    );
+    let access_controls_contract =
+        TripleAAccessControls::new(access_controls_address, access_client.clone());
     *ACCESS_CONTROLS_CONTRACT.lock().unwrap() = Some(Arc::new(access_controls_contract));
 
-    let agents_address = AGENTS.parse::<Address>().expect("Error in parsing AGENTS");
-    let agents_abi: Abi = from_str(include_str!("./../../abis/TripleAAgents.json"))
-        .expect("Error in loading Agents ABI");
-    let agents_contract = Contract::new(agents_address, agents_abi, client.clone());
+    let agents_address = network.agents.parse::<Address>().expect("Error in parsing AGENTS");
+    let agents_contract = TripleAAgents::new(agents_address, client.clone());
     *AGENTS_CONTRACT.lock().unwrap() = Some(Arc::new(agents_contract));
 
-    let collection_manager_address = COLLECTION_MANAGER
+    let collection_manager_address = network
+        .collection_manager
         .parse::<Address>()
         .expect("Error in parsing COLLECTION_MANAGER");
-    let collection_manager_abi: Abi =
-        from_str(include_str!("./../../abis/TripleACollectionManager.json"))
-            .expect("Error in loading CollectionManager ABI");
-    let collection_manager_contract = Contract::new(
-        collection_manager_address,
-        collection_manager_abi,
-        client.clone(),
-    );
+    let collection_manager_contract =
+        TripleACollectionManager::new(collection_manager_address, client.clone());
     *COLLECTION_MANAGER_CONTRACT.lock().unwrap() = Some(Arc::new(collection_manager_contract));
 
-    let market_address = MARKET.parse::<Address>().expect("Error in parsing MARKET");
-    let market_abi: Abi = from_str(include_str!("./../../abis/TripleAMarket.json"))
-        .expect("Error in loading Market ABI");
-    let market_contract = Contract::new(market_address, market_abi, client.clone());
+    let market_address = network.market.parse::<Address>().expect("Error in parsing MARKET");
+    let market_contract = TripleAMarket::new(market_address, client.clone());
     *MARKET_CONTRACT.lock().unwrap() = Some(Arc::new(market_contract));
 
     Some((
@@ -298,7 +289,7 @@ pub fn initialize_contracts(
     ))
 }
 
-pub fn configure_key(encryption_details: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+pub fn configure_key(encryption_details: &str) -> Result<String, AgentError> {
     let encryption_details_parse: Value =
         from_str(encryption_details).expect("Failed to parse encryption_details JSON");
     let encrypted_private_key = encryption_details_parse["encrypted"].as_str().unwrap();
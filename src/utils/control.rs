@@ -0,0 +1,339 @@
+use crate::utils::{
+    schedule::{describe_schedule, next_fire},
+    types::{AgentControl, AgentError, AgentManager, ControlSettings, StoreRecord},
+};
+use chrono::Utc;
+use dotenv::var;
+use http_body_util::{BodyExt, Full};
+use hyper::{
+    body::{Bytes, Incoming},
+    header::{AUTHORIZATION, CONTENT_TYPE},
+    server::conn::http1,
+    service::service_fn,
+    Method, Request, Response, StatusCode,
+};
+use hyper_util::rt::TokioIo;
+use serde_json::{json, Value};
+use std::{
+    collections::HashMap,
+    convert::Infallible,
+    net::SocketAddr,
+    sync::{Arc, Mutex},
+};
+use tokio::{net::TcpListener, sync::Notify};
+use tracing::{error, info, warn};
+
+static CONTROLS: Mutex<Option<HashMap<u32, AgentControl>>> = Mutex::new(None);
+
+pub fn control_settings() -> Result<Option<ControlSettings>, AgentError> {
+    let token = match var("AGENT_CONTROL_TOKEN") {
+        Ok(token) if !token.trim().is_empty() => token.trim().to_string(),
+        _ => return Ok(None),
+    };
+
+    let addr: SocketAddr = var("AGENT_CONTROL_ADDR")
+        .unwrap_or_else(|_| "127.0.0.1:7878".to_string())
+        .trim()
+        .parse()
+        .map_err(|e| AgentError::config(format!("Invalid AGENT_CONTROL_ADDR: {}", e)))?;
+
+    Ok(Some(ControlSettings { addr, token }))
+}
+
+pub fn register_control(manager: &AgentManager) {
+    let mut controls = CONTROLS.lock().unwrap();
+    controls.get_or_insert_with(HashMap::new).insert(
+        manager.agent.id,
+        AgentControl {
+            agent: manager.agent.clone(),
+            store: manager.store.clone(),
+            queue: manager.current_queue.clone(),
+            last_activity: None,
+            paused: false,
+            generation: 0,
+            trigger: Arc::new(Notify::new()),
+            resumed: Arc::new(Notify::new()),
+        },
+    );
+}
+
+pub fn record_activity(manager: &AgentManager) {
+    with_control(manager.agent.id, |control| {
+        control.agent = manager.agent.clone();
+        control.queue = manager.current_queue.clone();
+        control.last_activity = Some(Utc::now().timestamp());
+    });
+}
+
+pub fn is_paused(agent_id: u32) -> bool {
+    with_control(agent_id, |control| control.paused).unwrap_or(false)
+}
+
+pub fn generation(agent_id: u32) -> u64 {
+    with_control(agent_id, |control| control.generation).unwrap_or(0)
+}
+
+pub fn is_cancelled(agent_id: u32, since: u64) -> bool {
+    generation(agent_id) != since
+}
+
+// Holds a task while its agent is paused. Returns false once the task's cycle is
+// cancelled, true as soon as it may run.
+pub async fn wait_while_paused(agent_id: u32, since: u64) -> bool {
+    let Some(resumed) = with_control(agent_id, |control| control.resumed.clone()) else {
+        return true;
+    };
+
+    loop {
+        let notified = resumed.notified();
+        tokio::pin!(notified);
+        notified.as_mut().enable();
+
+        if is_cancelled(agent_id, since) {
+            return false;
+        }
+        if !is_paused(agent_id) {
+            return true;
+        }
+        notified.await;
+    }
+}
+
+pub fn trigger(agent_id: u32) -> Arc<Notify> {
+    with_control(agent_id, |control| control.trigger.clone()).unwrap_or_else(|| Arc::new(Notify::new()))
+}
+
+fn with_control<T>(agent_id: u32, f: impl FnOnce(&mut AgentControl) -> T) -> Option<T> {
+    CONTROLS
+        .lock()
+        .unwrap()
+        .as_mut()
+        .and_then(|controls| controls.get_mut(&agent_id))
+        .map(f)
+}
+
+pub async fn serve_control(settings: ControlSettings) {
+    let listener = match TcpListener::bind(settings.addr).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            error!("Control API failed to bind {}: {:?}", settings.addr, err);
+            return;
+        }
+    };
+
+    if !settings.addr.ip().is_loopback() {
+        warn!(
+            "Control API is listening on {}, which is reachable from other machines",
+            settings.addr
+        );
+    }
+    info!("Control API listening on http://{}", settings.addr);
+
+    let token = Arc::new(settings.token);
+
+    loop {
+        let (stream, _) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(err) => {
+                error!("Control API accept error: {:?}", err);
+                continue;
+            }
+        };
+
+        let token = token.clone();
+        tokio::spawn(async move {
+            let service = service_fn(move |request| {
+                let token = token.clone();
+                async move { Ok::<_, Infallible>(handle(request, &token).await) }
+            });
+
+            if let Err(err) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                error!("Control API connection error: {:?}", err);
+            }
+        });
+    }
+}
+
+async fn handle(request: Request<Incoming>, token: &str) -> Response<Full<Bytes>> {
+    if !authorized(&request, token) {
+        return reply(StatusCode::UNAUTHORIZED, json!({ "error": "unauthorized" }));
+    }
+
+    let method = request.method().clone();
+    let path = request.uri().path().to_string();
+    let _ = request.into_body().collect().await;
+
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+
+    match (method, segments.as_slice()) {
+        (Method::GET, ["agents"]) => {
+            blocking(|| {
+                let agents: Vec<Value> = controls().iter().map(status).collect();
+                reply(StatusCode::OK, json!({ "agents": agents }))
+            })
+            .await
+        }
+        (method, ["agents", id, rest @ ..]) => {
+            let Some(agent_id) = id.parse::<u32>().ok().filter(|id| has_control(*id)) else {
+                return reply(StatusCode::NOT_FOUND, json!({ "error": "unknown agent" }));
+            };
+
+            match (method, rest) {
+                (Method::GET, []) => {
+                    blocking(move || match controls().iter().find(|c| c.agent.id == agent_id) {
+                        Some(control) => reply(StatusCode::OK, status(control)),
+                        None => reply(StatusCode::NOT_FOUND, json!({ "error": "unknown agent" })),
+                    })
+                    .await
+                }
+                (Method::GET, ["tasks"]) => blocking(move || tasks(agent_id)).await,
+                (Method::POST, ["trigger"]) if is_paused(agent_id) => reply(
+                    StatusCode::CONFLICT,
+                    json!({ "error": "agent is paused, resume it before triggering" }),
+                ),
+                (Method::POST, ["trigger"]) => {
+                    trigger(agent_id).notify_one();
+                    info!("Control API triggered activity for agent_{}", agent_id);
+                    reply(StatusCode::ACCEPTED, json!({ "triggered": agent_id }))
+                }
+                (Method::POST, ["pause"]) => set_paused(agent_id, true),
+                (Method::POST, ["resume"]) => set_paused(agent_id, false),
+                (Method::POST, ["cancel"]) => {
+                    with_control(agent_id, |control| {
+                        control.generation += 1;
+                        control.queue.clear();
+                        control.resumed.notify_waiters();
+                    });
+                    info!("Control API cancelled queued tasks for agent_{}", agent_id);
+                    reply(StatusCode::OK, json!({ "cancelled": agent_id }))
+                }
+                _ => reply(StatusCode::NOT_FOUND, json!({ "error": "not found" })),
+            }
+        }
+        _ => reply(StatusCode::NOT_FOUND, json!({ "error": "not found" })),
+    }
+}
+
+// Status and tasks read the state files, which must not stall the executor the agents run on.
+async fn blocking<F>(respond: F) -> Response<Full<Bytes>>
+where
+    F: FnOnce() -> Response<Full<Bytes>> + Send + 'static,
+{
+    tokio::task::spawn_blocking(respond).await.unwrap_or_else(|err| {
+        reply(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            json!({ "error": err.to_string() }),
+        )
+    })
+}
+
+fn authorized(request: &Request<Incoming>, token: &str) -> bool {
+    let provided = request
+        .headers()
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .unwrap_or("");
+
+    provided.len() == token.len()
+        && provided
+            .bytes()
+            .zip(token.bytes())
+            .fold(0u8, |diff, (a, b)| diff | (a ^ b))
+            == 0
+}
+
+fn controls() -> Vec<AgentControl> {
+    let mut controls: Vec<AgentControl> = CONTROLS
+        .lock()
+        .unwrap()
+        .as_ref()
+        .map(|controls| controls.values().cloned().collect())
+        .unwrap_or_default();
+    controls.sort_by_key(|control| control.agent.id);
+    controls
+}
+
+fn has_control(agent_id: u32) -> bool {
+    with_control(agent_id, |_| ()).is_some()
+}
+
+fn status(control: &AgentControl) -> Value {
+    let state = control.store.load().unwrap_or_default();
+    let agent = &control.agent;
+
+    json!({
+        "agent": {
+            "id": agent.id,
+            "name": agent.name,
+            "wallet": agent.wallet,
+            "account_address": agent.account_address,
+            "model": agent.model,
+            "feeds": agent.feeds,
+            "feed_frequency": agent.feed_frequency,
+            "llm": agent.llm,
+            "image": agent.image,
+            "respond": agent.respond,
+            "schedule": describe_schedule(&agent.schedule),
+        },
+        "paused": control.paused,
+        "queue": control.queue,
+        "token_expiry": state.tokens.map(|tokens| tokens.expiry),
+        "last_activity": control.last_activity,
+        "next_activation": next_fire(&agent.schedule, Utc::now()).map(|next| next.to_rfc3339()),
+    })
+}
+
+fn tasks(agent_id: u32) -> Response<Full<Bytes>> {
+    let Some(store) = with_control(agent_id, |control| control.store.clone()) else {
+        return reply(StatusCode::NOT_FOUND, json!({ "error": "unknown agent" }));
+    };
+
+    match store.records() {
+        Ok(records) => {
+            let day = store.day();
+            let records: Vec<StoreRecord> = records
+                .into_iter()
+                .filter(|record| match record {
+                    StoreRecord::Planned { day: record_day, .. }
+                    | StoreRecord::Completed { day: record_day, .. }
+                    | StoreRecord::Cancelled { day: record_day, .. }
+                    | StoreRecord::Skipped { day: record_day, .. } => *record_day == day,
+                    _ => false,
+                })
+                .collect();
+
+            reply(StatusCode::OK, json!({ "day": day, "tasks": records }))
+        }
+        Err(err) => reply(
+            StatusCode::INTERNAL_SERVER_ERROR,
+            json!({ "error": err.to_string() }),
+        ),
+    }
+}
+
+fn set_paused(agent_id: u32, paused: bool) -> Response<Full<Bytes>> {
+    with_control(agent_id, |control| {
+        control.paused = paused;
+        control.resumed.notify_waiters();
+    });
+    info!(
+        "Control API {} scheduling for agent_{}",
+        if paused { "paused" } else { "resumed" },
+        agent_id
+    );
+
+    reply(StatusCode::OK, json!({ "agent": agent_id, "paused": paused }))
+}
+
+fn reply(status: StatusCode, body: Value) -> Response<Full<Bytes>> {
+    let mut response = Response::new(Full::new(Bytes::from(body.to_string())));
+    *response.status_mut() = status;
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, "application/json".parse().unwrap());
+    response
+}
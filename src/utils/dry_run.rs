@@ -0,0 +1,165 @@
+use crate::utils::{
+    retry::send_with_retry,
+    types::{AgentError, DryRunSettings, FixtureMode, Upstream},
+};
+use chrono::Utc;
+use dotenv::var;
+use ethers::{types::Eip1559TransactionRequest, utils::keccak256};
+use hyper::http;
+use reqwest::{RequestBuilder, Response};
+use serde_json::{json, Value};
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    sync::{Mutex, Once},
+};
+use tracing::{error, info};
+use uuid::Uuid;
+
+static INIT: Once = Once::new();
+static SETTINGS: Mutex<Option<DryRunSettings>> = Mutex::new(None);
+static REPORT_LOCK: Mutex<()> = Mutex::new(());
+
+pub fn settings() -> DryRunSettings {
+    INIT.call_once(|| {
+        let enabled = var("AGENT_DRY_RUN")
+            .map(|value| matches!(value.trim().to_lowercase().as_str(), "1" | "true" | "yes"))
+            .unwrap_or(false);
+
+        let fixtures = match var("AGENT_FIXTURES")
+            .unwrap_or_default()
+            .trim()
+            .to_lowercase()
+            .as_str()
+        {
+            "replay" => FixtureMode::Replay,
+            "record" => FixtureMode::Record,
+            _ => FixtureMode::Off,
+        };
+
+        *SETTINGS.lock().unwrap() = Some(DryRunSettings {
+            enabled,
+            report: PathBuf::from(
+                var("AGENT_DRY_RUN_REPORT").unwrap_or_else(|_| "dry_run_report.jsonl".to_string()),
+            ),
+            fixtures,
+            fixtures_dir: PathBuf::from(
+                var("AGENT_FIXTURES_DIR").unwrap_or_else(|_| "fixtures".to_string()),
+            ),
+        });
+    });
+
+    SETTINGS
+        .lock()
+        .unwrap()
+        .clone()
+        .expect("Dry run settings not initialized")
+}
+
+pub fn dry_run() -> bool {
+    settings().enabled
+}
+
+pub fn report(agent_id: u32, action: &str, payload: Value) -> String {
+    let id = format!("dry-run-{}", Uuid::new_v4());
+    let entry = json!({
+        "id": id,
+        "at": Utc::now().to_rfc3339(),
+        "agent_id": agent_id,
+        "action": action,
+        "payload": payload,
+    });
+
+    info!(agent_id, action, id = %id, "Dry run: {}", entry);
+
+    let path = settings().report;
+    let _guard = REPORT_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+    let written = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| writeln!(file, "{}", entry));
+
+    if let Err(err) = written {
+        error!("Error writing dry run report {:?}: {:?}", path, err);
+    }
+
+    id
+}
+
+pub fn report_transaction(
+    agent_id: u32,
+    method: &str,
+    contract: &str,
+    tx: &Eip1559TransactionRequest,
+) -> String {
+    report(
+        agent_id,
+        method,
+        json!({
+            "contract": contract,
+            "transaction": tx,
+        }),
+    )
+}
+
+pub async fn send(
+    upstream: Upstream,
+    fixture: &str,
+    key: &str,
+    build: impl Fn() -> RequestBuilder,
+) -> Result<Response, AgentError> {
+    let settings = settings();
+    let general = settings.fixtures_dir.join(format!("{}.json", fixture));
+    let specific = settings
+        .fixtures_dir
+        .join(fixture)
+        .join(format!("{}.json", hex_key(key)));
+
+    match settings.fixtures {
+        FixtureMode::Off => send_with_retry(upstream, build).await,
+        FixtureMode::Replay => {
+            for path in [&specific, &general] {
+                if let Ok(body) = fs::read_to_string(path) {
+                    info!("Replaying fixture {:?}", path);
+                    return Ok(Response::from(http::Response::new(body)));
+                }
+            }
+
+            info!("No fixture for {}, calling live", fixture);
+            send_with_retry(upstream, build).await
+        }
+        FixtureMode::Record => {
+            let response = send_with_retry(upstream, build).await?;
+            let status = response.status();
+            if !status.is_success() {
+                return Ok(response);
+            }
+
+            let body = response.bytes().await?.to_vec();
+            for path in [&specific, &general] {
+                let written = path
+                    .parent()
+                    .map(fs::create_dir_all)
+                    .unwrap_or(Ok(()))
+                    .and_then(|_| fs::write(path, &body));
+
+                if let Err(err) = written {
+                    error!("Error recording fixture {:?}: {:?}", path, err);
+                }
+            }
+
+            let mut recorded = http::Response::new(body);
+            *recorded.status_mut() = status;
+            Ok(Response::from(recorded))
+        }
+    }
+}
+
+fn hex_key(key: &str) -> String {
+    keccak256(key.as_bytes())[..8]
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
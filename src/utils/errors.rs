@@ -0,0 +1,226 @@
+use crate::utils::types::{AgentError, SubgraphError, TaskOutcome};
+use ethers::{
+    contract::ContractError,
+    middleware::signer::SignerMiddlewareError,
+    providers::{Middleware, ProviderError},
+    signers::{Signer, WalletError},
+};
+use std::{error::Error, fmt};
+
+impl AgentError {
+    pub fn venice(status: Option<u16>, message: impl Into<String>) -> Self {
+        AgentError::Venice {
+            status,
+            message: message.into(),
+        }
+    }
+
+    pub fn llm(status: Option<u16>, message: impl Into<String>) -> Self {
+        AgentError::Llm {
+            status,
+            message: message.into(),
+        }
+    }
+
+    pub fn image(status: Option<u16>, message: impl Into<String>) -> Self {
+        AgentError::Image {
+            status,
+            message: message.into(),
+        }
+    }
+
+    pub fn lens(message: impl Into<String>) -> Self {
+        AgentError::Lens {
+            message: message.into(),
+            retryable: true,
+        }
+    }
+
+    pub fn lens_rejected(message: impl Into<String>) -> Self {
+        AgentError::Lens {
+            message: message.into(),
+            retryable: false,
+        }
+    }
+
+    pub fn chain(message: impl Into<String>) -> Self {
+        AgentError::Chain {
+            message: message.into(),
+            retryable: true,
+        }
+    }
+
+    pub fn reverted(message: impl Into<String>) -> Self {
+        AgentError::Chain {
+            message: message.into(),
+            retryable: false,
+        }
+    }
+
+    pub fn ipfs(message: impl fmt::Display) -> Self {
+        AgentError::Ipfs(message.to_string())
+    }
+
+    pub fn config(message: impl fmt::Display) -> Self {
+        AgentError::Config(message.to_string())
+    }
+
+    pub fn parse(message: impl fmt::Display) -> Self {
+        AgentError::Parse(message.to_string())
+    }
+
+    pub fn is_retryable(&self) -> bool {
+        match self {
+            AgentError::Venice { status, .. }
+            | AgentError::Llm { status, .. }
+            | AgentError::Image { status, .. } => {
+                status.is_none_or(|status| status == 429 || status >= 500)
+            }
+            AgentError::Lens { retryable, .. } | AgentError::Chain { retryable, .. } => *retryable,
+            AgentError::Ipfs(_) | AgentError::Subgraph(_) | AgentError::Unavailable { .. } => true,
+            AgentError::Http(err) => err
+                .status()
+                .is_none_or(|status| status.as_u16() == 429 || status.is_server_error()),
+            AgentError::Io(_) | AgentError::Config(_) | AgentError::Parse(_) => false,
+        }
+    }
+}
+
+impl TaskOutcome {
+    pub fn of<T>(result: &Result<T, AgentError>) -> Self {
+        match result {
+            Ok(_) => TaskOutcome::Succeeded,
+            Err(err) if err.is_retryable() => TaskOutcome::Retryable,
+            Err(_) => TaskOutcome::Permanent,
+        }
+    }
+}
+
+impl fmt::Display for AgentError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AgentError::Venice {
+                status: Some(status),
+                message,
+            } => write!(f, "Venice error {}: {}", status, message),
+            AgentError::Venice { message, .. } => write!(f, "Venice error: {}", message),
+            AgentError::Llm {
+                status: Some(status),
+                message,
+            } => write!(f, "LLM error {}: {}", status, message),
+            AgentError::Llm { message, .. } => write!(f, "LLM error: {}", message),
+            AgentError::Image {
+                status: Some(status),
+                message,
+            } => write!(f, "Image error {}: {}", status, message),
+            AgentError::Image { message, .. } => write!(f, "Image error: {}", message),
+            AgentError::Lens { message, .. } => write!(f, "Lens error: {}", message),
+            AgentError::Ipfs(message) => write!(f, "IPFS error: {}", message),
+            AgentError::Subgraph(err) => write!(f, "{}", err),
+            AgentError::Chain { message, .. } => write!(f, "Chain error: {}", message),
+            AgentError::Http(err) => write!(f, "HTTP error: {}", err),
+            AgentError::Io(err) => write!(f, "IO error: {}", err),
+            AgentError::Config(message) => write!(f, "Config error: {}", message),
+            AgentError::Parse(message) => write!(f, "Parse error: {}", message),
+            AgentError::Unavailable { upstream, retry_in } => write!(
+                f,
+                "{} unavailable, circuit open for another {}s",
+                upstream,
+                retry_in.as_secs()
+            ),
+        }
+    }
+}
+
+impl Error for AgentError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            AgentError::Subgraph(err) => Some(err),
+            AgentError::Http(err) => Some(err),
+            AgentError::Io(err) => Some(err),
+            _ => None,
+        }
+    }
+}
+
+impl From<SubgraphError> for AgentError {
+    fn from(err: SubgraphError) -> Self {
+        AgentError::Subgraph(err)
+    }
+}
+
+impl From<reqwest::Error> for AgentError {
+    fn from(err: reqwest::Error) -> Self {
+        AgentError::Http(err)
+    }
+}
+
+impl From<std::io::Error> for AgentError {
+    fn from(err: std::io::Error) -> Self {
+        AgentError::Io(err)
+    }
+}
+
+impl From<serde_json::Error> for AgentError {
+    fn from(err: serde_json::Error) -> Self {
+        AgentError::parse(err)
+    }
+}
+
+impl From<toml::de::Error> for AgentError {
+    fn from(err: toml::de::Error) -> Self {
+        AgentError::parse(err)
+    }
+}
+
+impl From<std::num::ParseIntError> for AgentError {
+    fn from(err: std::num::ParseIntError) -> Self {
+        AgentError::parse(err)
+    }
+}
+
+impl From<regex::Error> for AgentError {
+    fn from(err: regex::Error) -> Self {
+        AgentError::parse(err)
+    }
+}
+
+impl From<std::time::SystemTimeError> for AgentError {
+    fn from(err: std::time::SystemTimeError) -> Self {
+        AgentError::config(err)
+    }
+}
+
+impl From<base64::DecodeError> for AgentError {
+    fn from(err: base64::DecodeError) -> Self {
+        AgentError::parse(err)
+    }
+}
+
+impl From<ProviderError> for AgentError {
+    fn from(err: ProviderError) -> Self {
+        AgentError::chain(err.to_string())
+    }
+}
+
+impl<M: Middleware, S: Signer> From<SignerMiddlewareError<M, S>> for AgentError {
+    fn from(err: SignerMiddlewareError<M, S>) -> Self {
+        AgentError::chain(err.to_string())
+    }
+}
+
+impl From<WalletError> for AgentError {
+    fn from(err: WalletError) -> Self {
+        AgentError::config(err)
+    }
+}
+
+impl<M: Middleware> From<ContractError<M>> for AgentError {
+    fn from(err: ContractError<M>) -> Self {
+        if err.is_revert() {
+            AgentError::reverted(err.to_string())
+        } else {
+            AgentError::chain(err.to_string())
+        }
+    }
+}
@@ -1,38 +1,41 @@
 use crate::utils::{
-    constants::{
-        BONSAI,
-        COLLECTION_MANAGER,
-        INFURA_GATEWAY,
-        LENS_CHAIN_ID,
-        MONA,
-        WGHO,
-    },
     contracts::initialize_provider,
-    ipfs::upload_ipfs,
+    ipfs::{ upload_ipfs, upload_lens_storage },
     lens::handle_lens_account,
+    image::image_from_env,
+    llm::llm_from_env,
+    respond::respond_from_env,
+    network::network,
+    persona::{ compose_persona, persona_budget },
+    retry::send_with_retry,
+    roster::{ assign_clock, feed_frequency_from_env },
+    schedule::daily_schedule,
+    subgraph::{ agent_remix, agents, collection_prices, token_details },
+    transactions::{ gas_from_env, send_transaction, succeeded },
+    bindings::{ CollectionInput, CollectionWorker, SignerClient, TripleACollectionManager },
     types::{
+        AgentError,
         AgentManager,
-        CollectionInput,
-        CollectionWorker,
+        Claims,
+        Content,
+        Image,
         MessageExample,
         PriceCollection,
+        Publication,
+        SubgraphAgentMetadata,
         Text,
         TripleAAgent,
+        Upstream,
     },
     venice::call_drop_details,
 };
 use chrono::Utc;
-use dotenv::{ from_filename, var };
 use ethers::{
-    contract::{ ContractInstance, FunctionCall },
-    core::k256::ecdsa::SigningKey,
-    middleware::SignerMiddleware,
-    providers::{ Http, Middleware, Provider },
-    signers::Wallet,
+    contract::FunctionCall,
+    providers::Middleware,
     types::{
         transaction::eip2718::TypedTransaction,
         Address,
-        Eip1559TransactionRequest,
         NameOrAddress,
         TransactionRequest,
         H160,
@@ -46,31 +49,32 @@ use rand::{ rngs::StdRng, Rng, SeedableRng };
 use regex::Regex;
 use reqwest::Client;
 use serde_json::{ json, to_string, Value };
-use std::{ collections::HashMap, error::Error, io, str::FromStr, sync::Arc };
+use std::{ collections::{ BTreeSet, HashMap }, str::FromStr, sync::{ Arc, Mutex } };
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
 
 pub fn extract_values_prompt(
     input: &str
-) -> Result<(String, String), Box<dyn Error + Send + Sync>> {
+) -> Result<(String, Option<String>), AgentError> {
     let image_prompt_re = Regex::new(r"(?m)^Image Prompt:\s*(.+)")?;
     let model_re = Regex::new(r"(?m)^Model:\s*(.+)")?;
 
     let image_prompt = image_prompt_re
         .captures(input)
-        .and_then(|cap| cap.get(1).map(|m| m.as_str()))
-        .unwrap_or_default()
-        .to_string();
+        .and_then(|cap| cap.get(1).map(|m| m.as_str().trim().to_string()))
+        .filter(|prompt| !prompt.is_empty())
+        .ok_or_else(|| AgentError::parse(format!("No image prompt in completion: {}", input)))?;
     let model = model_re
         .captures(input)
-        .and_then(|cap| cap.get(1).map(|m| m.as_str()))
-        .unwrap_or_default()
-        .to_string();
+        .and_then(|cap| cap.get(1).map(|m| m.as_str().trim().to_string()))
+        .filter(|model| !model.is_empty());
 
     Ok((image_prompt, model))
 }
 
 pub fn extract_values_image(
     input: &str
-) -> Result<(String, String, U256, Vec<U256>), Box<dyn Error + Send + Sync>> {
+) -> Result<(String, String, U256, Vec<U256>), AgentError> {
     let title_re = Regex::new(r"(?m)^Title:\s*(.+)")?;
     let description_re = Regex::new(r"(?m)^Description:\s*(.+)")?;
     let amount_re = Regex::new(r"(?m)^Amount:\s*(\d+)")?;
@@ -115,7 +119,7 @@ pub fn extract_values_image(
     Ok((title, description, U256::from(amount), vec![wgho, bonsai, mona]))
 }
 
-pub fn extract_values_drop(input: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+pub fn extract_values_drop(input: &str) -> Result<String, AgentError> {
     let title_re = Regex::new(r"(?m)^Title:\s*(.+)")?;
 
     let title = title_re
@@ -131,28 +135,79 @@ fn strip_quotes(s: &str) -> String {
     s.trim().trim_matches('"').trim().to_string()
 }
 
+impl Claims {
+    pub const fn new() -> Self {
+        Claims(Mutex::new(BTreeSet::new()))
+    }
+
+    pub fn claim(&self, key: &str) -> bool {
+        self.0.lock().unwrap().insert(key.to_lowercase())
+    }
+
+    pub fn release(&self, key: &str) {
+        self.0.lock().unwrap().remove(&key.to_lowercase());
+    }
+}
+
+pub fn text_model(model: &str) -> &str {
+    if model.contains("dolphin") {
+        debug!("Overriding deprecated model {} with llama-3.3-70b", model);
+        "llama-3.3-70b"
+    } else {
+        model
+    }
+}
+
+// A task that posted anything succeeded; otherwise it fails with its first error.
+pub fn hashes_or_error(
+    hashes: Vec<String>,
+    errors: Vec<AgentError>,
+) -> Result<Vec<String>, AgentError> {
+    match errors.into_iter().next() {
+        Some(err) if hashes.is_empty() => Err(err),
+        _ => Ok(hashes),
+    }
+}
+
+// Uploads a Lens image post for a freshly minted collection and returns its content URI.
+pub async fn upload_image_post(
+    title: String,
+    description: String,
+    mime_type: &str,
+    ipfs_hash: &str,
+) -> Result<String, AgentError> {
+    let tags = vec!["tripleA".to_string(), title.replace(" ", "").to_lowercase()];
+
+    let publication = Publication {
+        schema: "https://json-schemas.lens.dev/posts/image/3.0.0.json".to_string(),
+        lens: Content {
+            mainContentFocus: String::from("IMAGE"),
+            title,
+            content: description,
+            id: Uuid::new_v4().to_string(),
+            locale: "en".to_string(),
+            tags,
+            image: Some(Image {
+                tipo: mime_type.to_string(),
+                item: format!("ipfs://{}", ipfs_hash),
+            }),
+        },
+    };
+
+    upload_lens_storage(to_string(&publication)?).await.inspect_err(|e| {
+        error!("Error uploading content to Lens Storage: {}", e);
+    })
+}
+
 pub fn format_instructions(agent: &TripleAAgent) -> String {
-    format!(
-        r#"
-Custom Instructions: {}
-Lore: {}
-Knowledge: {}
-Style: {}
-Adjectives: {}
-"#,
-        agent.custom_instructions,
-        agent.lore,
-        agent.knowledge,
-        agent.style,
-        agent.adjectives
-    )
+    compose_persona(agent, persona_budget()).instructions
 }
 
 pub async fn fetch_metadata(uri: &str) -> Option<Value> {
     if let Some(ipfs_hash) = uri.strip_prefix("ipfs://") {
         let client = Client::new();
-        let url = format!("{}ipfs/{}", INFURA_GATEWAY, ipfs_hash);
-        if let Ok(response) = client.get(&url).send().await {
+        let url = format!("{}ipfs/{}", network().ipfs_gateway, ipfs_hash);
+        if let Ok(response) = send_with_retry(Upstream::Ipfs, || client.get(&url)).await {
             if let Ok(json) = response.json::<Value>().await {
                 return Some(json);
             }
@@ -161,237 +216,134 @@ pub async fn fetch_metadata(uri: &str) -> Option<Value> {
     None
 }
 
-pub async fn handle_agents() -> Result<HashMap<u32, AgentManager>, Box<dyn Error + Send + Sync>> {
-    let client = Client::new();
-
-    let query =
-        json!({
-        "query": r#"
-        query {
-            agentCreateds(first: 100) {
-                wallets
-                SkyhuntersAgentManager_id
-                creator
-                uri
-                metadata {
-                    title
-                    bio
-                    lore
-                    adjectives
-                    style
-                    knowledge
-                    messageExamples
-                    model
-                    cover
-                    customInstructions
-                    feeds
-                }
-            }
+pub async fn handle_agents() -> Result<HashMap<u32, AgentManager>, AgentError> {
+    let agent_createds = agents().await?;
+    let llm = llm_from_env()?;
+    let image = image_from_env()?;
+    let respond = respond_from_env()?;
+    let feed_frequency = feed_frequency_from_env()?;
+
+    let mut agents_snapshot: HashMap<u32, AgentManager> = HashMap::new();
+
+    for agent_created in agent_createds {
+        if agent_created.id > U256::from(u32::MAX) {
+            warn!("Skipping agent with out of range id {}", agent_created.id);
+            continue;
         }
-        "#,
-    });
-    from_filename(".env").ok();
-    let graph_url: String = var("GRAPH_NODE_URL").expect(
-        "GRAPH_NODE_URL not configured in .env"
-    );
-    let res = client.post(graph_url).json(&query).send().await;
-
-    match res {
-        Ok(response) => {
-            let parsed: Value = response.json().await?;
-            let empty_vec = vec![];
-            let agent_createds = parsed["data"]["agentCreateds"].as_array().unwrap_or(&empty_vec);
-
-            let mut agents_snapshot: HashMap<u32, AgentManager> = HashMap::new();
-
-            for agent_created in agent_createds {
-                let new_id: u32 = agent_created["SkyhuntersAgentManager_id"]
-                    .as_str()
-                    .unwrap_or("0")
-                    .parse()
-                    .map_err(|_| "Failed to parse ID")?;
+        let new_id = agent_created.id.as_u32();
+
+        let clock = assign_clock(
+            &agents_snapshot
+                .values()
+                .map(|agent| agent.agent.clock)
+                .collect::<Vec<u32>>()
+        );
+        let wallet = agent_created.wallets.first().cloned().unwrap_or_default();
+        let account_address = handle_lens_account(&wallet, false).await.unwrap_or_default();
+
+        let metadata = match (agent_created.metadata, &agent_created.uri) {
+            (Some(metadata), _) => metadata,
+            (None, Some(uri)) =>
+                fetch_metadata(uri)
+                    .await
+                    .and_then(|value| serde_json::from_value::<SubgraphAgentMetadata>(value).ok())
+                    .unwrap_or_default(),
+            (None, None) => SubgraphAgentMetadata::default(),
+        };
 
-                let mut rng = StdRng::from_entropy();
-                let mut clock;
-                loop {
-                    let random_hour = rng.gen_range(0..5);
-                    let random_minute = rng.gen_range(0..60);
-                    let random_second = rng.gen_range(0..60);
-                    clock = random_hour * 3600 + random_minute * 60 + random_second;
-
-                    if
-                        !agents_snapshot.values().any(|agent| {
-                            let agent_clock = agent.agent.clock;
-                            ((clock as i32) - (agent_clock as i32)).abs() < 60
-                        })
-                    {
-                        break;
-                    }
-                }
-                let wallet = agent_created["wallets"]
-                    .as_array()
-                    .unwrap_or(&vec![])
-                    .get(0)
-                    .and_then(|w| w.as_str())
-                    .unwrap_or("")
-                    .to_string();
-                let account_address = handle_lens_account(&wallet, false).await.unwrap_or_default();
-
-                let metadata = agent_created["metadata"].clone();
-                let is_metadata_empty =
-                    metadata.is_null() ||
-                    metadata
-                        .as_object()
-                        .map(|o| o.is_empty())
-                        .unwrap_or(false);
-
-                let metadata_filled = if is_metadata_empty {
-                    if let Some(uri) = agent_created["uri"].as_str() {
-                        fetch_metadata(uri).await.unwrap_or(json!({}))
-                    } else {
-                        json!({})
-                    }
-                } else {
-                    metadata
-                };
-
-                let manager = AgentManager::new(
-                    &(TripleAAgent {
-                        id: new_id,
-                        name: metadata_filled["title"].as_str().unwrap_or("").to_string(),
-                        bio: metadata_filled["bio"].as_str().unwrap_or("").to_string(),
-                        lore: metadata_filled["lore"].as_str().unwrap_or("").to_string(),
-                        adjectives: metadata_filled["adjectives"]
-                            .as_str()
-                            .unwrap_or("")
-                            .to_string(),
-                        style: metadata_filled["style"].as_str().unwrap_or("").to_string(),
-                        knowledge: metadata_filled["knowledge"].as_str().unwrap_or("").to_string(),
-                        message_examples: metadata_filled["message_examples"]
-                            .as_array()
-                            .unwrap_or(&vec![])
+        let manager = AgentManager::new(
+            &(TripleAAgent {
+                id: new_id,
+                name: metadata.title,
+                bio: metadata.bio,
+                lore: metadata.lore,
+                adjectives: metadata.adjectives,
+                style: metadata.style,
+                knowledge: metadata.knowledge,
+                message_examples: metadata.message_examples
+                    .iter()
+                    .map(|group| {
+                        group
                             .iter()
-                            .map(|v| {
-                                v.as_array()
-                                    .unwrap_or(&vec![])
-                                    .iter()
-                                    .map(|con| {
-                                        let parsed_con: MessageExample = serde_json
-                                            ::from_str(con.as_str().unwrap_or("{}"))
-                                            .unwrap_or(MessageExample {
-                                                user: "".to_string(),
-                                                content: Text {
-                                                    text: "".to_string(),
-                                                },
-                                            });
-
-                                        parsed_con
-                                    })
-                                    .collect::<Vec<MessageExample>>()
+                            .map(|con| {
+                                let parsed_con = match con {
+                                    Value::String(con) => serde_json::from_str(con).ok(),
+                                    other => serde_json::from_value(other.clone()).ok(),
+                                };
+
+                                parsed_con.unwrap_or(MessageExample {
+                                    user: "".to_string(),
+                                    content: Text {
+                                        text: "".to_string(),
+                                    },
+                                })
                             })
-                            .collect::<Vec<Vec<MessageExample>>>(),
-                        model: metadata_filled["model"].as_str().unwrap_or("qwen3-4b").to_string(),
-                        cover: metadata_filled["cover"].as_str().unwrap_or("").to_string(),
-                        custom_instructions: metadata_filled["customInstructions"]
-                            .as_str()
-                            .unwrap_or("")
-                            .to_string(),
-                        feeds: metadata_filled["feeds"]
-                            .as_array()
-                            .unwrap_or(&Vec::new())
-                            .iter()
-                            .filter_map(|value| value.as_str().map(|s| s.to_string()))
-                            .collect(),
-                        wallet,
-                        clock,
-                        last_active_time: Utc::now().timestamp() as u32,
-                        account_address,
+                            .collect::<Vec<MessageExample>>()
                     })
-                );
-
-                match manager {
-                    Some(man) => {
-                        agents_snapshot.insert(new_id, man);
-                    }
-                    None => {
-                        eprintln!("Agent Not Added at id {}", new_id);
-                    }
-                }
+                    .collect::<Vec<Vec<MessageExample>>>(),
+                model: metadata.model.unwrap_or_else(|| "qwen3-4b".to_string()),
+                cover: metadata.cover,
+                custom_instructions: metadata.custom_instructions,
+                feeds: metadata.feeds,
+                feed_frequency,
+                wallet,
+                clock,
+                last_active_time: Utc::now().timestamp() as u32,
+                account_address,
+                schedule: daily_schedule(clock),
+                gas: gas_from_env(),
+                llm: llm.clone(),
+                image: image.clone(),
+                respond: respond.clone(),
+            })
+        );
+
+        match manager {
+            Some(man) => {
+                agents_snapshot.insert(new_id, man);
+            }
+            None => {
+                warn!("Agent Not Added at id {}", new_id);
             }
-            Ok(agents_snapshot)
         }
-        Err(err) => Err(Box::new(err)),
     }
+
+    Ok(agents_snapshot)
 }
 
-pub async fn handle_token_thresholds(irl: bool) -> Result<Vec<U256>, Box<dyn Error + Send + Sync>> {
-    let client = Client::new();
-
-    let query =
-        json!({
-        "query": r#"
-        query {
-            tokenDetailsSets {
-                threshold
-                token
-                base 
-            }
+pub async fn handle_token_thresholds(irl: bool) -> Result<Vec<U256>, AgentError> {
+    let token_details = match token_details().await {
+        Ok(token_details) => token_details,
+        Err(err) => {
+            error!("Error loading token thresholds: {}", err);
+            return Ok(vec![]);
         }
-        "#,
-    });
-    from_filename(".env").ok();
-    let graph_url: String = var("GRAPH_NODE_URL").expect(
-        "GRAPH_NODE_URL not configured in .env"
-    );
-    let res = client.post(graph_url).json(&query).send().await;
-
-    match res {
-        Ok(response) => {
-            let parsed: Value = response.json().await?;
-            let empty_vec = vec![];
-            let token_details = parsed["data"]["tokenDetailsSets"].as_array().unwrap_or(&empty_vec);
-
-            let mut wgho_price: Option<U256> = None;
-            let mut bonsai_price: Option<U256> = None;
-            let mut mona_price: Option<U256> = None;
-
-            for token in token_details {
-                if
-                    let (Some(token_address), Some(threshold_str), Some(base_str)) = (
-                        token["token"].as_str(),
-                        token["threshold"].as_str(),
-                        token["base"].as_str(),
-                    )
-                {
-                    if
-                        let (Ok(threshold), Ok(base)) = (
-                            U256::from_dec_str(threshold_str),
-                            U256::from_dec_str(base_str),
-                        )
-                    {
-                        let total_price = if irl { U256::max(threshold, base) } else { threshold };
-
-                        if token_address.eq_ignore_ascii_case(WGHO) {
-                            wgho_price = Some(total_price);
-                        } else if token_address.eq_ignore_ascii_case(BONSAI) {
-                            bonsai_price = Some(total_price);
-                        } else if token_address.eq_ignore_ascii_case(MONA) {
-                            mona_price = Some(total_price);
-                        }
-                    }
-                }
-            }
+    };
+
+    let network = network();
+    let mut wgho_price: Option<U256> = None;
+    let mut bonsai_price: Option<U256> = None;
+    let mut mona_price: Option<U256> = None;
+
+    for token in token_details {
+        let total_price = if irl { U256::max(token.threshold, token.base) } else { token.threshold };
 
-            Ok(
-                vec![
-                    wgho_price.unwrap_or(U256::zero()),
-                    bonsai_price.unwrap_or(U256::zero()),
-                    mona_price.unwrap_or(U256::zero())
-                ]
-            )
+        if token.token.eq_ignore_ascii_case(&network.wgho) {
+            wgho_price = Some(total_price);
+        } else if token.token.eq_ignore_ascii_case(&network.bonsai) {
+            bonsai_price = Some(total_price);
+        } else if token.token.eq_ignore_ascii_case(&network.mona) {
+            mona_price = Some(total_price);
         }
-        Err(_) => Ok(vec![]),
     }
+
+    Ok(
+        vec![
+            wgho_price.unwrap_or(U256::zero()),
+            bonsai_price.unwrap_or(U256::zero()),
+            mona_price.unwrap_or(U256::zero())
+        ]
+    )
 }
 
 pub async fn validate_and_fix_prices(prices: Vec<U256>, irl: bool) -> Vec<U256> {
@@ -429,12 +381,7 @@ pub async fn mint_collection(
     image: &str,
     title: &str,
     amount: U256,
-    collection_manager_contract: Arc<
-        ContractInstance<
-            Arc<SignerMiddleware<Arc<Provider<Http>>, Wallet<SigningKey>>>,
-            SignerMiddleware<Arc<Provider<Http>>, Wallet<SigningKey>>
-        >
-    >,
+    collection_manager_contract: Arc<TripleACollectionManager<SignerClient>>,
     prices: Vec<U256>,
     agent: &TripleAAgent,
     remix_collection_id: U256,
@@ -445,20 +392,13 @@ pub async fn mint_collection(
     format: Option<String>,
     worker: bool,
     for_artist: &str
-) -> Result<(), Box<dyn Error + Send + Sync>> {
-    let model_override = if model.contains("dolphin") {
-        println!("DEBUG: Overriding deprecated model {} with llama-3.3-70b", model);
-        "llama-3.3-70b"
-    } else {
-        model
-    };
+) -> Result<String, AgentError> {
+    let model_override = text_model(model);
     match get_drop_details(remix_collection_id, description, agent.id, image, model_override).await {
         Ok((drop_metadata, drop_id)) => {
             if drop_metadata.trim() == "" || !drop_metadata.contains("ipfs://") {
-                eprintln!("Error with drop metadata: {}", drop_metadata);
-                return Err(
-                    Box::new(io::Error::new(io::ErrorKind::Other, "Error with drop metadata"))
-                );
+                error!("Error with drop metadata: {}", drop_metadata);
+                return Err(AgentError::parse(format!("Invalid drop metadata: {}", drop_metadata)));
             }
 
             match
@@ -496,42 +436,40 @@ pub async fn mint_collection(
                         true
                     }).await;
 
-                    let method = collection_manager_contract.method::<
-                        (CollectionInput, Vec<CollectionWorker>, String, U256),
-                        H256
-                    >("create", (
+                    let network = network();
+                    let call = collection_manager_contract.create(
                         CollectionInput {
                             tokens: vec![
-                                H160::from_str(WGHO).unwrap(),
-                                H160::from_str(BONSAI).unwrap(),
-                                H160::from_str(MONA).unwrap()
+                                H160::from_str(&network.wgho).unwrap(),
+                                H160::from_str(&network.bonsai).unwrap(),
+                                H160::from_str(&network.mona).unwrap()
                             ],
 
                             prices,
-                            agentIds: if worker {
+                            agent_ids: if worker {
                                 vec![U256::from(agent.id)]
                             } else {
                                 vec![]
                             },
                             metadata: format!("ipfs://{}", response.Hash),
-                            forArtist: H160::from_str(for_artist).unwrap(),
-                            collectionType: collection_type,
+                            for_artist: H160::from_str(for_artist).unwrap(),
+                            collection_type,
                             amount,
-                            fulfillerId: if collection_type == 0u8 {
+                            fulfiller_id: if collection_type == 0u8 {
                                 U256::from(0)
                             } else {
                                 U256::from(1)
                             },
                             remixable: true,
-                            remixId: remix_collection_id,
+                            remix_id: remix_collection_id,
                         },
                         if worker {
                             vec![CollectionWorker {
                                 instructions: agent.custom_instructions.to_string(),
-                                publishFrequency: U256::from(1),
-                                remixFrequency: U256::from(0),
-                                leadFrequency: U256::from(0),
-                                mintFrequency: U256::from(1),
+                                publish_frequency: U256::from(1),
+                                remix_frequency: U256::from(0),
+                                lead_frequency: U256::from(0),
+                                mint_frequency: U256::from(1),
                                 publish: true,
                                 remix: false,
                                 lead: false,
@@ -541,164 +479,104 @@ pub async fn mint_collection(
                             vec![]
                         },
                         drop_metadata,
-                        drop_id,
-                    ));
-
-                    match method {
-                        Ok(call) => {
-                            let FunctionCall { tx, .. } = call;
-
-                            if let Some(tx_request) = tx.as_eip1559_ref() {
-                                let gas_price = U256::from(500_000_000_000u64);
-                                let max_priority_fee = U256::from(25_000_000_000u64);
-                                let gas_limit = U256::from(300_000);
-
-                                let client = collection_manager_contract.client().clone();
-                                let chain_id = *LENS_CHAIN_ID;
-                                let req = Eip1559TransactionRequest {
-                                    from: Some(agent.wallet.parse::<Address>().unwrap()),
-                                    to: Some(
-                                        NameOrAddress::Address(
-                                            COLLECTION_MANAGER.parse::<Address>().unwrap()
-                                        )
-                                    ),
-                                    gas: Some(gas_limit),
-                                    value: tx_request.value,
-                                    data: tx_request.data.clone(),
-                                    max_priority_fee_per_gas: Some(max_priority_fee),
-                                    max_fee_per_gas: Some(gas_price + max_priority_fee),
-                                    chain_id: Some(chain_id.into()),
-                                    ..Default::default()
-                                };
-
-                                let pending_tx = match client.send_transaction(req, None).await {
-                                    Ok(tx) => tx,
-                                    Err(e) => {
-                                        eprintln!(
-                                            "Error sending the transaction for mint collection: {:?}",
-                                            e
-                                        );
-                                        Err(Box::new(e))?
-                                    }
-                                };
-
-                                let tx_hash = match pending_tx.confirmations(1).await {
-                                    Ok(hash) => hash,
-                                    Err(e) => {
-                                        eprintln!("Error with transaction confirmation: {:?}", e);
-                                        Err(Box::new(e))?
-                                    }
-                                };
-
-                                println!("Mint Hash: {:?}", tx_hash);
-
-                                match tx_hash {
-                                    Some(tx) => {
-                                        if tx.status == Some(U64::from(1)) {
-                                            Ok(())
-                                        } else {
-                                            eprintln!("Error in sending Transaction");
-
-                                            let provider = initialize_provider();
-
-                                            let tx_hash: H256 = tx.transaction_hash;
-
-                                            if
-                                                let Ok(Some(transaction)) =
-                                                    provider.get_transaction(tx_hash).await
-                                            {
-                                                let typed_tx = TypedTransaction::Legacy(
-                                                    TransactionRequest {
-                                                        from: Some(transaction.from),
-                                                        to: transaction.to.map(
-                                                            NameOrAddress::Address
-                                                        ),
-                                                        gas: Some(transaction.gas),
-                                                        gas_price: transaction.gas_price,
-                                                        value: Some(transaction.value),
-                                                        data: Some(transaction.input.clone()),
-                                                        nonce: Some(transaction.nonce),
-                                                        chain_id: Some(
-                                                            U64::from_str(
-                                                                &transaction.chain_id
-                                                                    .unwrap()
-                                                                    .to_string()
-                                                            ).unwrap()
-                                                        ),
-                                                    }
-                                                );
-                                                let call_result = provider.call(
-                                                    &typed_tx,
-                                                    None
-                                                ).await;
-
-                                                if let Ok(result) = call_result {
-                                                    if !result.is_empty() {
-                                                        println!(
-                                                            "Empty result {:?}",
-                                                            hex::encode(result.0)
-                                                        );
-                                                    }
-                                                }
-                                            }
-
-                                            if !tx.logs.is_empty() {
-                                                eprintln!(
-                                                    "Transaction logs may contain error events. {:?}",
-                                                    tx.logs
-                                                );
-                                            }
-
-                                            Err(
-                                                Box::new(
-                                                    io::Error::new(
-                                                        io::ErrorKind::Other,
-                                                        "Error in sending Transaction"
-                                                    )
-                                                )
-                                            )
-                                        }
+                        drop_id
+                    );
+
+                    let FunctionCall { tx, .. } = call;
+                    let client = collection_manager_contract.client();
+
+                    let sent = match
+                        send_transaction(
+                            &client,
+                            agent.id,
+                            "create",
+                            network.collection_manager
+                                .parse::<Address>()
+                                .map_err(AgentError::parse)?,
+                            tx.data().cloned(),
+                            tx.value().cloned()
+                        ).await
+                    {
+                        Ok(sent) => sent,
+                        Err(e) => {
+                            error!(
+                                "Error sending the transaction for mint collection: {:?}",
+                                e
+                            );
+                            return Err(e);
+                        }
+                    };
+
+                    info!(tx_hash = ?sent.hash, "Mint collection sent");
+
+                    match sent.receipt {
+                        Some(tx) if !succeeded(&tx) => {
+                            error!("Error in sending Transaction");
+
+                            let provider = initialize_provider();
+
+                            let tx_hash: H256 = tx.transaction_hash;
+
+                            if
+                                let Ok(Some(transaction)) =
+                                    provider.get_transaction(tx_hash).await
+                            {
+                                let typed_tx = TypedTransaction::Legacy(
+                                    TransactionRequest {
+                                        from: Some(transaction.from),
+                                        to: transaction.to.map(
+                                            NameOrAddress::Address
+                                        ),
+                                        gas: Some(transaction.gas),
+                                        gas_price: transaction.gas_price,
+                                        value: Some(transaction.value),
+                                        data: Some(transaction.input.clone()),
+                                        nonce: Some(transaction.nonce),
+                                        chain_id: Some(
+                                            U64::from_str(
+                                                &transaction.chain_id
+                                                    .unwrap()
+                                                    .to_string()
+                                            ).unwrap()
+                                        ),
                                     }
-                                    None => {
-                                        eprintln!("Error in sending Transaction");
-                                        Err(
-                                            Box::new(
-                                                io::Error::new(
-                                                    io::ErrorKind::Other,
-                                                    "Error in sending Transaction"
-                                                )
-                                            )
-                                        )
+                                );
+                                let call_result = provider.call(
+                                    &typed_tx,
+                                    None
+                                ).await;
+
+                                if let Ok(result) = call_result {
+                                    if !result.is_empty() {
+                                        info!(
+                                            "Empty result {:?}",
+                                            hex::encode(result.0)
+                                        );
                                     }
                                 }
-                            } else {
-                                eprintln!("Error in sending Transaction");
-                                Err(
-                                    Box::new(
-                                        io::Error::new(
-                                            io::ErrorKind::Other,
-                                            "Error in sending Transaction"
-                                        )
-                                    )
-                                )
                             }
-                        }
 
-                        Err(err) => {
-                            eprintln!("Error in create method for create collection: {:?}", err);
-                            Err(Box::new(err))
+                            if !tx.logs.is_empty() {
+                                error!(
+                                    "Transaction logs may contain error events. {:?}",
+                                    tx.logs
+                                );
+                            }
+
+                            Err(AgentError::reverted(format!("create reverted: {:?}", sent.hash)))
                         }
+                        _ => Ok(sent.hash),
                     }
                 }
                 Err(err) => {
-                    eprintln!("Error in IPFS upload for create collection: {:?}", err);
-                    Err(Box::new(io::Error::new(io::ErrorKind::Other, "Error in IPFS upload")))
+                    error!("Error in IPFS upload for create collection: {:?}", err);
+                    Err(err)
                 }
             }
         }
         Err(err) => {
-            eprintln!("Error with drop details: {}", err);
-            Err(Box::new(io::Error::new(io::ErrorKind::Other, "Error with drop details")))
+            error!("Error with drop details: {}", err);
+            Err(err)
         }
     }
 }
@@ -709,78 +587,42 @@ async fn get_drop_details(
     agent_id: u32,
     image: &str,
     model: &str
-) -> Result<(String, U256), Box<dyn Error + Send + Sync>> {
+) -> Result<(String, U256), AgentError> {
     let mut drop_metadata = String::from("");
     let mut drop_id = U256::from(0);
 
-    let client = Client::new();
-
-    let query =
-        json!({
-        "query": r#"
-        query(TripleAAgents_id: Int!, remixId: Int!) {
-            agentRemixes(first: 1, where: {
-            TripleAAgents_id: $TripleAAgents_id, remixId: $remixId
-            }) {
-                dropId
-            }
+    match agent_remix(agent_id, remix_collection_id).await {
+        Ok(Some(remix)) => {
+            drop_id = remix.drop_id;
         }
-        "#,
-        "variables": {
-                "TripleAAgents_id": agent_id,
-                "remixId": remix_collection_id
-    }
-    });
-    from_filename(".env").ok();
-    let graph_url: String = var("GRAPH_NODE_URL").expect(
-        "GRAPH_NODE_URL not configured in .env"
-    );
-    let res = client.post(graph_url).json(&query).send().await;
-
-    match res {
-        Ok(response) => {
-            let parsed: Value = response.json().await?;
-
-            if
-                let Some(value) = parsed["data"]["agentRemixes"]
-                    .as_array()
-                    .map(|arr| arr.first())
-                    .flatten()
-                    .and_then(|value| value.get("dropId"))
-                    .and_then(|drop_id| drop_id.as_str())
-                    .map(String::from)
-            {
-                let id: u32 = value.parse().expect("Error converting drop value to u32");
-                drop_id = U256::from(id);
-            } else {
-                match call_drop_details(&remix_collection_description, &model).await {
-                    Ok(title) => {
-                        match
-                            upload_ipfs(
-                                to_string(
-                                    &json!({
+        Ok(None) => {
+            match call_drop_details(agent_id, &remix_collection_description, &model).await {
+                Ok(title) => {
+                    match
+                        upload_ipfs(
+                            to_string(
+                                &json!({
                             "title": title,
                             "cover": image,
                         })
-                                )?
-                            ).await
-                        {
-                            Ok(ipfs) => {
-                                drop_metadata = format!("ipfs://{}", ipfs.Hash);
-                            }
-                            Err(err) => {
-                                eprintln!("Error with IPFS upload for drop: {}", err);
-                            }
+                            )?
+                        ).await
+                    {
+                        Ok(ipfs) => {
+                            drop_metadata = format!("ipfs://{}", ipfs.Hash);
+                        }
+                        Err(err) => {
+                            error!("Error with IPFS upload for drop: {}", err);
                         }
                     }
-                    Err(err) => {
-                        eprintln!("Error with drop AI call: {}", err);
-                    }
+                }
+                Err(err) => {
+                    error!("Error with drop AI call: {}", err);
                 }
             }
         }
         Err(err) => {
-            eprintln!("Error with drop details: {}", err);
+            error!("Error with drop details: {}", err);
         }
     }
 
@@ -791,67 +633,6 @@ pub async fn find_collection(
     balance: U256,
     token: &str,
     artist: &str
-) -> Result<Vec<PriceCollection>, Box<dyn Error + Send + Sync>> {
-    let client = Client::new();
-
-    let query =
-        json!({
-        "query": r#"
-        query($token: $String!, $artist: String!, $soldOut: Bool!, $maxPrice: Int!) {
-            collectionPrices(where: { token: $token, artist: $artist, soldOut: $soldOut, price_lte: $maxPrice }, first: 100) {
-                collectionId
-                amount
-                amountSold
-            }
-        }
-        "#,
-        "variables": {
-
-                "soldOut": false,
-                "maxPrice": balance,
-                "artist": artist,
-                "token": token
-
-        }
-    });
-
-    from_filename(".env").ok();
-    let graph_url: String = var("GRAPH_NODE_URL").expect(
-        "GRAPH_NODE_URL not configured in .env"
-    );
-    let res = client.post(graph_url).json(&query).send().await;
-
-    match res {
-        Ok(response) => {
-            let parsed: Value = response.json().await?;
-            let empty_vec = vec![];
-            let collections_snapshot = parsed["data"]["collectionPrices"]
-                .as_array()
-                .unwrap_or(&empty_vec);
-
-            let mut collections: Vec<PriceCollection> = vec![];
-
-            for collection in collections_snapshot {
-                collections.push(PriceCollection {
-                    collectionId: collection["collectionId"]
-                        .as_str()
-                        .unwrap_or("0")
-                        .parse()
-                        .map_err(|_| "Failed to parse collectionId")?,
-                    amount: collection["amount"]
-                        .as_str()
-                        .unwrap_or("0")
-                        .parse()
-                        .map_err(|_| "Failed to parse amount")?,
-                    amountSold: collection["amountSold"]
-                        .as_str()
-                        .unwrap_or("0")
-                        .parse()
-                        .map_err(|_| "Failed to parse amountSold")?,
-                });
-            }
-            Ok(collections)
-        }
-        Err(err) => Err(Box::new(err)),
-    }
+) -> Result<Vec<PriceCollection>, AgentError> {
+    collection_prices(token, artist, balance).await
 }
@@ -0,0 +1,371 @@
+use crate::utils::{
+    constants::DEFAULT_IMAGE_MODEL,
+    dry_run::send,
+    ipfs::fetch_ipfs,
+    types::{
+        AgentError, FixtureImage, GeneratedImage, ImageBackend, ImageRequest, ImageSettings,
+        LocalImage, RosterImage, Upstream, VeniceImage,
+    },
+};
+use async_trait::async_trait;
+use base64::{engine::general_purpose::STANDARD, Engine};
+use dotenv::{from_filename, var};
+use reqwest::{Client, Response};
+use serde_json::{json, Value};
+use std::{collections::HashMap, fs, path::PathBuf, sync::Mutex};
+use tracing::{error, info, warn};
+
+static IMAGE_SETTINGS: Mutex<Option<HashMap<u32, ImageSettings>>> = Mutex::new(None);
+
+// 1x1 transparent PNG, used when no fixture image is configured.
+const FIXTURE_PNG: &[u8] = &[
+    0x89, 0x50, 0x4e, 0x47, 0x0d, 0x0a, 0x1a, 0x0a, 0x00, 0x00, 0x00, 0x0d, 0x49, 0x48, 0x44,
+    0x52, 0x00, 0x00, 0x00, 0x01, 0x00, 0x00, 0x00, 0x01, 0x08, 0x06, 0x00, 0x00, 0x00, 0x1f,
+    0x15, 0xc4, 0x89, 0x00, 0x00, 0x00, 0x0b, 0x49, 0x44, 0x41, 0x54, 0x78, 0xda, 0x63, 0x60,
+    0x00, 0x02, 0x00, 0x00, 0x05, 0x00, 0x01, 0xe9, 0xfa, 0xdc, 0xd8, 0x00, 0x00, 0x00, 0x00,
+    0x49, 0x45, 0x4e, 0x44, 0xae, 0x42, 0x60, 0x82,
+];
+
+#[async_trait]
+pub trait ImageGenerator: Send + Sync {
+    fn name(&self) -> &str;
+
+    async fn generate(&self, request: &ImageRequest) -> Result<GeneratedImage, AgentError>;
+}
+
+pub fn parse_image_backend(input: &str) -> Result<ImageBackend, AgentError> {
+    match input.trim().to_lowercase().as_str() {
+        "venice" => Ok(ImageBackend::Venice),
+        "local" => Ok(ImageBackend::Local),
+        "fixture" => Ok(ImageBackend::Fixture),
+        other => Err(AgentError::config(format!("Unknown image provider: {}", other))),
+    }
+}
+
+pub fn image_from_env() -> Result<ImageSettings, AgentError> {
+    let optional = |name: &str| {
+        var(name)
+            .ok()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    let mut settings = ImageSettings {
+        provider: match optional("AGENT_IMAGE_PROVIDER") {
+            Some(provider) => parse_image_backend(&provider)?,
+            None => ImageBackend::Venice,
+        },
+        base_url: optional("AGENT_IMAGE_BASE_URL"),
+        model: optional("AGENT_IMAGE_MODEL"),
+        fixture: optional("AGENT_IMAGE_FIXTURE").map(PathBuf::from),
+        img2img: false,
+        strength: match optional("AGENT_IMAGE_STRENGTH") {
+            Some(strength) => Some(strength.parse().map_err(|_| {
+                AgentError::config(format!("Invalid AGENT_IMAGE_STRENGTH: {}", strength))
+            })?),
+            None => None,
+        },
+    };
+    settings.img2img = img2img_from_env().unwrap_or(settings.provider != ImageBackend::Venice);
+    validate_image(&settings)?;
+
+    Ok(settings)
+}
+
+// Venice's image/edit ignores the model, size and strength, so img2img is only on by
+// default for the other providers.
+fn img2img_from_env() -> Option<bool> {
+    var("AGENT_IMAGE_IMG2IMG")
+        .ok()
+        .map(|value| value.trim().to_lowercase())
+        .filter(|value| !value.is_empty())
+        .map(|value| matches!(value.as_str(), "1" | "true" | "yes"))
+}
+
+pub fn image_from_entry(entry: Option<&RosterImage>) -> Result<ImageSettings, AgentError> {
+    let mut image = image_from_env()?;
+
+    if let Some(entry) = entry {
+        if let Some(provider) = &entry.provider {
+            image.provider = parse_image_backend(provider)?;
+        }
+        if let Some(base_url) = &entry.base_url {
+            image.base_url = Some(base_url.clone());
+        }
+        if let Some(model) = &entry.model {
+            image.model = Some(model.clone());
+        }
+        if let Some(fixture) = &entry.fixture {
+            image.fixture = Some(PathBuf::from(fixture));
+        }
+        image.img2img = entry
+            .img2img
+            .or_else(img2img_from_env)
+            .unwrap_or(image.provider != ImageBackend::Venice);
+        if let Some(strength) = entry.strength {
+            image.strength = Some(strength);
+        }
+    }
+    validate_image(&image)?;
+
+    Ok(image)
+}
+
+fn validate_image(settings: &ImageSettings) -> Result<(), AgentError> {
+    if let Some(strength) = settings.strength.filter(|strength| !(0.0..=1.0).contains(strength)) {
+        return Err(AgentError::config(format!(
+            "Image strength must be between 0 and 1, got {}",
+            strength
+        )));
+    }
+
+    if settings.provider != ImageBackend::Local {
+        return Ok(());
+    }
+
+    let base_url = settings
+        .base_url
+        .as_ref()
+        .ok_or_else(|| AgentError::config("The local image provider needs a base_url"))?;
+    reqwest::Url::parse(base_url)
+        .map_err(|_| AgentError::config(format!("Invalid image base_url {}", base_url)))?;
+
+    Ok(())
+}
+
+pub fn register_image_settings(agent_id: u32, image: ImageSettings) {
+    if image.provider == ImageBackend::Venice {
+        if image.img2img {
+            warn!(
+                agent_id,
+                "img2img with Venice uses image/edit, which ignores the model, size and strength"
+            );
+        } else {
+            info!(
+                agent_id,
+                "Remixes and mints with Venice are text-to-image and don't use the source image, \
+                 set AGENT_IMAGE_IMG2IMG=true to edit it instead"
+            );
+        }
+        if let Some(strength) = image.strength {
+            warn!(agent_id, "Image strength {} is ignored by Venice", strength);
+        }
+    }
+
+    IMAGE_SETTINGS
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(agent_id, image);
+}
+
+pub fn image_settings(agent_id: u32) -> Result<ImageSettings, AgentError> {
+    let registered = IMAGE_SETTINGS
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|settings| settings.get(&agent_id).cloned());
+
+    match registered {
+        Some(settings) => Ok(settings),
+        None => image_from_env(),
+    }
+}
+
+// The image Remix and Mint start from, or None when img2img is off for the agent. A
+// source that can't be fetched falls back to text-to-image rather than failing the task.
+pub async fn source_image(agent_id: u32, uri: &str) -> Result<Option<Vec<u8>>, AgentError> {
+    if !image_settings(agent_id)?.img2img {
+        return Ok(None);
+    }
+
+    match fetch_ipfs(uri).await {
+        Ok(bytes) => Ok(Some(bytes)),
+        Err(err) => {
+            warn!(agent_id, "Could not fetch source image {}, using the prompt alone: {}", uri, err);
+            Ok(None)
+        }
+    }
+}
+
+pub fn configured_image_model(agent_id: u32) -> Result<String, AgentError> {
+    Ok(image_settings(agent_id)?
+        .model
+        .unwrap_or_else(|| DEFAULT_IMAGE_MODEL.to_string()))
+}
+
+pub fn image_generator(settings: &ImageSettings) -> Result<Box<dyn ImageGenerator>, AgentError> {
+    from_filename(".env").ok();
+
+    Ok(match settings.provider {
+        ImageBackend::Venice => Box::new(VeniceImage {
+            client: Client::new(),
+            api_key: var("VENICE_KEY")
+                .map_err(|_| AgentError::config("VENICE_KEY not configured in .env"))?,
+        }),
+        ImageBackend::Local => Box::new(LocalImage {
+            client: Client::new(),
+            base_url: settings
+                .base_url
+                .clone()
+                .ok_or_else(|| AgentError::config("The local image provider needs a base_url"))?,
+            checkpoint: settings.model.clone(),
+        }),
+        ImageBackend::Fixture => Box::new(FixtureImage {
+            fixture: settings.fixture.clone(),
+        }),
+    })
+}
+
+pub async fn generate_image(
+    agent_id: u32,
+    mut request: ImageRequest,
+) -> Result<GeneratedImage, AgentError> {
+    let settings = image_settings(agent_id)?;
+    let generator = image_generator(&settings)?;
+    if let Some(model) = settings.model {
+        request.model = model;
+    }
+    if !settings.img2img {
+        request.source = None;
+    }
+    if request.source.is_some() && request.strength.is_none() {
+        request.strength = settings.strength;
+    }
+
+    match generator.generate(&request).await {
+        Ok(image) => {
+            info!(
+                agent_id,
+                "{} generated {} ({} bytes) for {}{}",
+                generator.name(),
+                image.mime_type,
+                image.bytes.len(),
+                request.task,
+                if request.source.is_some() { " from a source image" } else { "" }
+            );
+            Ok(image)
+        }
+        Err(err) => {
+            error!(agent_id, "{} image error ({}): {}", generator.name(), request.task, err);
+            Err(err)
+        }
+    }
+}
+
+// Venice and Automatic1111 both answer with `{"images": ["<base64>", ...]}`.
+pub async fn first_image(
+    response: Response,
+    on_error: impl FnOnce(u16, String) -> AgentError,
+) -> Result<GeneratedImage, AgentError> {
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Could not read error".to_string());
+        return Err(on_error(status.as_u16(), error_text));
+    }
+
+    let json: Value = response.json().await?;
+    let encoded = json["images"][0]
+        .as_str()
+        .ok_or_else(|| AgentError::parse("Image response had no images"))?;
+    let bytes = STANDARD.decode(encoded.split(',').next_back().unwrap_or(encoded))?;
+
+    Ok(GeneratedImage::new(bytes))
+}
+
+pub fn sniff_mime(bytes: &[u8]) -> &'static str {
+    match bytes {
+        [0x89, b'P', b'N', b'G', ..] => "image/png",
+        [0xff, 0xd8, 0xff, ..] => "image/jpeg",
+        [b'R', b'I', b'F', b'F', _, _, _, _, b'W', b'E', b'B', b'P', ..] => "image/webp",
+        [b'G', b'I', b'F', b'8', ..] => "image/gif",
+        _ => "application/octet-stream",
+    }
+}
+
+impl GeneratedImage {
+    pub fn new(bytes: Vec<u8>) -> Self {
+        GeneratedImage {
+            mime_type: sniff_mime(&bytes).to_string(),
+            bytes,
+        }
+    }
+
+    pub fn extension(&self) -> &str {
+        match self.mime_type.as_str() {
+            "image/jpeg" => "jpg",
+            "image/webp" => "webp",
+            "image/gif" => "gif",
+            _ => "png",
+        }
+    }
+}
+
+#[async_trait]
+impl ImageGenerator for LocalImage {
+    fn name(&self) -> &str {
+        "Local"
+    }
+
+    async fn generate(&self, request: &ImageRequest) -> Result<GeneratedImage, AgentError> {
+        let mode = if request.source.is_some() { "img2img" } else { "txt2img" };
+        let url = format!("{}/sdapi/v1/{}", self.base_url.trim_end_matches('/'), mode);
+        // Local checkpoints don't know Venice's presets, so the preset is folded into the prompt.
+        let prompt = match &request.style_preset {
+            Some(preset) => format!("{}, {} style", request.prompt, preset),
+            None => request.prompt.clone(),
+        };
+        let mut payload = json!({
+            "prompt": prompt,
+            "negative_prompt": request.negative_prompt.clone().unwrap_or_default(),
+            "width": request.width,
+            "height": request.height,
+            "seed": request.seed.map(|seed| seed as i64).unwrap_or(-1),
+        });
+        if let Some(source) = &request.source {
+            payload["init_images"] = json!([STANDARD.encode(source)]);
+            payload["denoising_strength"] = json!(request.strength.unwrap_or(0.6));
+        }
+        if let Some(checkpoint) = &self.checkpoint {
+            payload["override_settings"] = json!({ "sd_model_checkpoint": checkpoint });
+        }
+
+        let response = send(
+            Upstream::Image,
+            &format!("image/{}", request.task),
+            &payload.to_string(),
+            || self.client.post(&url).json(&payload),
+        )
+        .await?;
+
+        first_image(response, |status, error_text| {
+            AgentError::image(
+                Some(status),
+                format!("Error generating image {} - {}", status, error_text),
+            )
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl ImageGenerator for FixtureImage {
+    fn name(&self) -> &str {
+        "Fixture"
+    }
+
+    async fn generate(&self, request: &ImageRequest) -> Result<GeneratedImage, AgentError> {
+        let bytes = match (&self.fixture, &request.source) {
+            (Some(path), _) => fs::read(path).map_err(|e| {
+                AgentError::config(format!("Failed to read image fixture {:?}: {}", path, e))
+            })?,
+            (None, Some(source)) => source.clone(),
+            (None, None) => FIXTURE_PNG.to_vec(),
+        };
+
+        Ok(GeneratedImage::new(bytes))
+    }
+}
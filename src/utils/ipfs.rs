@@ -1,23 +1,21 @@
-use crate::IPFSResponse;
-use base64::{
-    engine::general_purpose::{self, STANDARD},
-    Engine as _,
+use crate::{
+    utils::{
+        dry_run::send,
+        metrics::upload_failed,
+        network::network,
+        retry::send_with_retry,
+        types::{AgentError, GeneratedImage, Upstream},
+    },
+    IPFSResponse,
 };
+use base64::{engine::general_purpose::STANDARD, Engine as _};
 use dotenv::{dotenv, from_filename, var};
 use reqwest::{
     multipart::{Form, Part},
     Client,
 };
 use serde_json::{from_str, Value};
-use std::{
-    error::Error,
-    sync::{Arc, Once},
-};
-use tokio::{
-    fs::{create_dir_all, remove_file, File, OpenOptions},
-    io::{AsyncReadExt, AsyncWriteExt},
-};
-use uuid::Uuid;
+use std::sync::{Arc, Once};
 
 static INIT: Once = Once::new();
 static mut CLIENT: Option<Arc<Client>> = None;
@@ -43,84 +41,62 @@ pub fn authentication() -> String {
 }
 
 pub async fn upload_image_to_ipfs(
-    base64_str: &str,
-) -> Result<IPFSResponse, Box<dyn Error + Send + Sync>> {
-    let base64_data = base64_str.split(',').last().unwrap_or(base64_str);
-    let image_bytes = general_purpose::STANDARD.decode(base64_data)?;
-
-    let temp_dir = std::env::temp_dir();
-    let path = temp_dir.join(format!("{}.png", Uuid::new_v4()));
-
-    let file_result = OpenOptions::new()
-        .write(true)
-        .create(true)
-        .open(&path)
-        .await;
-    match file_result {
-        Ok(mut file) => {
-            if let Err(err) = file.write_all(&image_bytes).await {
-                eprintln!("Error writing image: {:?}", err);
-                return Err(Box::new(err));
-            }
-            if let Err(err) = file.flush().await {
-                eprintln!("Error flushing file: {:?}", err);
-                return Err(Box::new(err));
-            }
-
-            drop(file);
-            let file_read_result = File::open(&path).await;
-            match file_read_result {
-                Ok(mut file) => {
-                    let mut buffer = Vec::new();
-                    if let Err(err) = file.read_to_end(&mut buffer).await {
-                        eprintln!("Error reading file: {:?}", err);
-                        return Err(Box::new(err));
-                    }
-
-                    let client = create_client();
-                    let aut_encoded = authentication();
-                    let form = Form::new().part("file", Part::bytes(buffer).file_name("image.png"));
-
-                    let response = client
-                        .post("https://ipfs.infura.io:5001/api/v0/add")
-                        .header("Authorization", format!("Basic {}", aut_encoded))
-                        .multipart(form)
-                        .send()
-                        .await?;
-
-                    let text_response = response.text().await?;
-                    let ipfs_response: IPFSResponse = serde_json::from_str(&text_response)?;
-
-                    if let Err(err) = remove_file(&path).await {
-                        eprintln!("Error deleting file: {:?}", err);
-                    }
-                    Ok(ipfs_response)
-                }
-                Err(err) => {
-                    eprintln!("Error opening file for reading: {:?}", err);
-                    Err(Box::new(err))
-                }
-            }
-        }
-        Err(err) => {
-            eprintln!("Error creating file: {:?}", err);
-            Err(Box::new(err))
-        }
+    image: &GeneratedImage,
+) -> Result<IPFSResponse, AgentError> {
+    let result = try_upload_image(image).await;
+    if result.is_err() {
+        upload_failed("ipfs");
     }
+    result
 }
 
-pub async fn upload_ipfs(data: String) -> Result<IPFSResponse, Box<dyn Error + Send + Sync>> {
+async fn try_upload_image(
+    image: &GeneratedImage,
+) -> Result<IPFSResponse, AgentError> {
     let client = create_client();
     let aut_encoded = authentication();
+    let file_name = format!("image.{}", image.extension());
+
+    let key = STANDARD.encode(&image.bytes);
+
+    let response = send(Upstream::Ipfs, "ipfs/upload_image", &key, || {
+        let part = Part::bytes(image.bytes.clone())
+            .file_name(file_name.clone())
+            .mime_str(&image.mime_type)
+            .unwrap_or_else(|_| Part::bytes(image.bytes.clone()).file_name(file_name.clone()));
+        client
+            .post(format!("{}add", network().ipfs_api))
+            .header("Authorization", format!("Basic {}", aut_encoded))
+            .multipart(Form::new().part("file", part))
+    })
+    .await?;
+
+    let text_response = response.text().await?;
+    let ipfs_response: IPFSResponse = serde_json::from_str(&text_response)?;
+
+    Ok(ipfs_response)
+}
+
+pub async fn upload_ipfs(data: String) -> Result<IPFSResponse, AgentError> {
+    let result = try_upload_ipfs(data).await;
+    if result.is_err() {
+        upload_failed("ipfs");
+    }
+    result
+}
 
-    let form: Form = Form::new().part("file", Part::text(data.clone()).file_name("data.json"));
+async fn try_upload_ipfs(data: String) -> Result<IPFSResponse, AgentError> {
+    let client = create_client();
+    let aut_encoded = authentication();
 
-    let response = client
-        .post("https://ipfs.infura.io:5001/api/v0/add")
-        .header("Authorization", format!("Basic {}", aut_encoded))
-        .multipart(form)
-        .send()
-        .await?;
+    let response = send(Upstream::Ipfs, "ipfs/upload", &data, || {
+        let form = Form::new().part("file", Part::text(data.clone()).file_name("data.json"));
+        client
+            .post(format!("{}add", network().ipfs_api))
+            .header("Authorization", format!("Basic {}", aut_encoded))
+            .multipart(form)
+    })
+    .await?;
 
     let text_response = response.text().await?;
     let ipfs_response: IPFSResponse = from_str(&text_response)?;
@@ -128,17 +104,33 @@ pub async fn upload_ipfs(data: String) -> Result<IPFSResponse, Box<dyn Error + S
     Ok(ipfs_response)
 }
 
-pub async fn get_storage_key() -> Result<String, Box<dyn Error>> {
+pub async fn fetch_ipfs(uri: &str) -> Result<Vec<u8>, AgentError> {
+    let client = Client::new();
+    let url = format!("{}ipfs/{}", network().ipfs_gateway, uri.trim_start_matches("ipfs://"));
+
+    let response = send_with_retry(Upstream::Ipfs, || client.get(&url)).await?;
+    if !response.status().is_success() {
+        return Err(AgentError::ipfs(format!(
+            "Error fetching {}: {}",
+            uri,
+            response.status()
+        )));
+    }
+
+    Ok(response.bytes().await?.to_vec())
+}
+
+pub async fn get_storage_key() -> Result<String, AgentError> {
     let client = Client::new();
 
-    let response = client
-        .post("https://api.grove.storage/link/new")
-        .send()
-        .await?;
+    let response = send_with_retry(Upstream::Grove, || {
+        client.post(format!("{}link/new", network().grove_api))
+    })
+    .await?;
 
     if !response.status().is_success() {
         let error_text = response.text().await?;
-        return Err(format!("Error obtaining storage_key: {}", error_text).into());
+        return Err(AgentError::ipfs(format!("Error obtaining storage_key: {}", error_text)));
     }
 
     let text_response = response.text().await?;
@@ -153,24 +145,34 @@ pub async fn get_storage_key() -> Result<String, Box<dyn Error>> {
         }
     }
 
-    Err("Couldn't obtain storage_key.".into())
+    Err(AgentError::ipfs("Couldn't obtain storage_key."))
+}
+
+pub async fn upload_lens_storage(data: String) -> Result<String, AgentError> {
+    let result = try_upload_lens_storage(data).await;
+    if result.is_err() {
+        upload_failed("lens_storage");
+    }
+    result
 }
 
-pub async fn upload_lens_storage(data: String) -> Result<String, Box<dyn Error>> {
+async fn try_upload_lens_storage(data: String) -> Result<String, AgentError> {
     let client = create_client();
     // let storage_key = get_storage_key().await?;
-    let url = format!("https://api.grove.storage/?chain_id=232");
+    let network = network();
+    let url = format!("{}?chain_id={}", network.grove_api, network.chain_id);
 
-    let response = client
-        .post(url)
-        .header("Content-Type", "application/json")
-        .body(data)
-        .send()
-        .await?;
+    let response = send(Upstream::Grove, "lens_storage/upload", &data, || {
+        client
+            .post(&url)
+            .header("Content-Type", "application/json")
+            .body(data.clone())
+    })
+    .await?;
 
     if !response.status().is_success() {
         let error_text = response.text().await?;
-        return Err(format!("Error uploading to Lens Storage: {}", error_text).into());
+        return Err(AgentError::ipfs(format!("Error uploading to Lens Storage: {}", error_text)));
     }
 
     let text_response = response.text().await?;
@@ -182,5 +184,5 @@ pub async fn upload_lens_storage(data: String) -> Result<String, Box<dyn Error>>
         }
     }
 
-    Err("Couldn't get URI.".into())
+    Err(AgentError::ipfs("Couldn't get URI."))
 }
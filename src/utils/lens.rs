@@ -1,29 +1,37 @@
 use crate::utils::{
-    constants::LENS_API,
     contracts::{initialize_api, initialize_provider, initialize_wallet},
-    types::{LensTokens, SavedTokens},
+    dry_run::{dry_run, report},
+    logging::redact,
+    metrics::{lens_operation, lens_poll_timeout},
+    network::network,
+    retry::{send_once, send_with_retry},
+    transactions::{send_hinted_transaction, signer_client},
+    types::{AgentError, FeedInfo, GasHints, LensTokens, PostStats, SavedTokens, Upstream},
 };
 use dotenv::{from_filename, var};
 use ethers::{
-    middleware::Middleware,
     signers::{LocalWallet, Signer},
-    types::{transaction::eip2718::TypedTransaction, Bytes, Eip1559TransactionRequest},
+    types::U256,
     utils::hex,
 };
 use futures::future::join_all;
 use reqwest::Client;
-use serde_json::{json, Value};
+use serde_json::{json, Map, Value};
 use std::{
-    error::Error,
     sync::Arc,
     time::{SystemTime, UNIX_EPOCH},
 };
+use tracing::{debug, error, info, warn};
+
+fn server_key() -> Result<String, AgentError> {
+    var("SERVER_KEY").map_err(|_| AgentError::config("SERVER_KEY not configured in .env"))
+}
 
 async fn refresh(
     client: Arc<Client>,
     refresh_tokens: &str,
     auth_tokens: &str,
-) -> Result<LensTokens, Box<dyn Error + Send + Sync>> {
+) -> Result<LensTokens, AgentError> {
     let query = json!({
         "query": r#"
             mutation Refresh($request: RefreshRequest!) {
@@ -48,17 +56,18 @@ async fn refresh(
     });
 
     from_filename(".env").ok();
-    let server_key: String = var("SERVER_KEY").expect("SERVER_KEY not configured in .env");
-
-    let response = client
-        .post(LENS_API)
-        .header("Authorization", format!("Bearer {}", auth_tokens))
-        .header("Content-Type", "application/json")
-        .header("Origin", "https://triplea-66ij.onrender.com")
-        .header("x-api-key", server_key)
-        .json(&query)
-        .send()
-        .await?;
+    let server_key = server_key()?;
+
+    let response = send_with_retry(Upstream::Lens, || {
+        client
+            .post(&network().lens_api)
+            .header("Authorization", format!("Bearer {}", auth_tokens))
+            .header("Content-Type", "application/json")
+            .header("Origin", &network().lens_origin)
+            .header("x-api-key", &server_key)
+            .json(&query)
+    })
+    .await?;
 
     if response.status().is_success() {
         let json: Value = response.json().await?;
@@ -82,10 +91,10 @@ async fn refresh(
                     .to_string(),
             })
         } else {
-            return Err("Unexpected Structure.".into());
+            return Err(AgentError::lens("Unexpected Structure."));
         }
     } else {
-        return Err(format!("Error: {}", response.status()).into());
+        return Err(AgentError::lens(format!("Error: {}", response.status())));
     }
 }
 
@@ -93,7 +102,7 @@ pub async fn authenticate(
     client: Arc<Client>,
     wallet: &LocalWallet,
     account_address: &str,
-) -> Result<LensTokens, Box<dyn Error + Send + Sync>> {
+) -> Result<LensTokens, AgentError> {
     let mutation = json!({
         "query": r#"
         mutation Challenge($request: ChallengeRequest!) {
@@ -115,17 +124,18 @@ pub async fn authenticate(
     });
 
     from_filename(".env").ok();
-    let server_key: String = var("SERVER_KEY").expect("SERVER_KEY not configured in .env");
-
-    let res = client
-        .post(LENS_API)
-        .header("Content-Type", "application/json")
-        .header("x-api-key", server_key)
-        .header("Origin", "https://triplea-66ij.onrender.com")
-        // .header("Origin", "http://localhost:3000")
-        .json(&mutation)
-        .send()
-        .await;
+    let server_key = server_key()?;
+
+    let res = send_with_retry(Upstream::Lens, || {
+        client
+            .post(&network().lens_api)
+            .header("Content-Type", "application/json")
+            .header("x-api-key", &server_key)
+            .header("Origin", &network().lens_origin)
+            // .header("Origin", "http://localhost:3000")
+            .json(&mutation)
+    })
+    .await;
 
     match res {
         Ok(response) => {
@@ -170,19 +180,16 @@ pub async fn authenticate(
                         }
                     });
 
-                    from_filename(".env").ok();
-                    let server_key: String =
-                        var("SERVER_KEY").expect("SERVER_KEY not configured in .env");
-
-                    let response = client
-                        .post(LENS_API)
-                        .header("Content-Type", "application/json")
-                        .header("x-api-key", server_key)
-                        .header("Origin", "https://triplea-66ij.onrender.com")
-                        // .header("Origin", "http://localhost:3000")
-                        .json(&authenticate_mutation)
-                        .send()
-                        .await?;
+                    let response = send_with_retry(Upstream::Lens, || {
+                        client
+                            .post(&network().lens_api)
+                            .header("Content-Type", "application/json")
+                            .header("x-api-key", &server_key)
+                            .header("Origin", &network().lens_origin)
+                            // .header("Origin", "http://localhost:3000")
+                            .json(&authenticate_mutation)
+                    })
+                    .await?;
 
                     if response.status().is_success() {
                         let json: Value = response.json().await?;
@@ -205,20 +212,20 @@ pub async fn authenticate(
                                     .to_string(),
                             });
                         } else {
-                            return Err("Authentication failed.".into());
+                            return Err(AgentError::lens_rejected("Authentication failed."));
                         }
                     } else {
-                        return Err(format!("Error: {}", response.status()).into());
+                        return Err(AgentError::lens(format!("Error: {}", response.status())));
                     }
                 } else {
-                    return Err("Challenge response structure invalid.".into());
+                    return Err(AgentError::lens("Challenge response structure invalid."));
                 }
             } else {
-                return Err(format!("Error: {}", response.status()).into());
+                return Err(AgentError::lens(format!("Error: {}", response.status())));
             }
         }
         Err(err) => {
-            return Err(format!("Error: {}", err).into());
+            return Err(AgentError::lens(format!("Error: {}", err)));
         }
     }
 }
@@ -227,24 +234,22 @@ pub async fn handle_tokens(
     private_key: u32,
     account_address: &str,
     tokens: Option<SavedTokens>,
-) -> Result<SavedTokens, Box<dyn Error + Send + Sync>> {
+) -> Result<SavedTokens, AgentError> {
     let client = initialize_api();
 
     let wallet = match initialize_wallet(private_key) {
         Some(wallet) => wallet,
         None => {
-            eprintln!("Wallet initialization failed. Skipping agent tokens.");
-            return Err("Wallet initialization failed. Skipping agent tokens.".into());
+            error!("Wallet initialization failed. Skipping agent tokens.");
+            return Err(AgentError::config("Wallet initialization failed. Skipping agent tokens."));
         }
     };
 
-    println!("Saved Tokens: {:?}", tokens);
-
     if let Some(saved) = tokens {
         let now = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
         let expiry: u64 = saved.expiry.try_into().unwrap();
 
-        println!("Expired: {:?}", now < (expiry - 3600));
+        debug!(agent_id = private_key, expiry, "Saved Lens tokens, expired: {}", now >= (expiry - 3600));
         if now < (expiry - 3600) {
             return Ok(saved);
         } else {
@@ -275,14 +280,25 @@ pub async fn make_publication(
     private_key: u32,
     auth_tokens: &str,
     feed: Option<String>,
-) -> Result<String, Box<dyn Error + Send + Sync>> {
+) -> Result<String, AgentError> {
+    let result = post_publication(content, private_key, auth_tokens, feed).await;
+    lens_operation("post", result.is_ok());
+    result
+}
+
+async fn post_publication(
+    content: &str,
+    private_key: u32,
+    auth_tokens: &str,
+    feed: Option<String>,
+) -> Result<String, AgentError> {
     let client = initialize_api();
 
     let wallet = match initialize_wallet(private_key) {
         Some(wallet) => wallet,
         None => {
-            eprintln!("Wallet initialization failed. Skipping publication.");
-            return Err("Wallet initialization failed. Skipping publication.".into());
+            error!("Wallet initialization failed. Skipping publication.");
+            return Err(AgentError::config("Wallet initialization failed. Skipping publication."));
         }
     };
 
@@ -329,105 +345,101 @@ pub async fn make_publication(
         }
     });
 
+    if dry_run() {
+        return Ok(report(private_key, "post", query["variables"]["request"].clone()));
+    }
+
     from_filename(".env").ok();
-    let server_key: String = var("SERVER_KEY").expect("SERVER_KEY not configured in .env");
-
-    let response = client
-        .post(LENS_API)
-        .header("Authorization", format!("Bearer {}", auth_tokens))
-        .header("x-api-key", server_key)
-        .header("Content-Type", "application/json")
-        .header("Origin", "https://triplea-66ij.onrender.com")
-        // .header("Origin", "http://localhost:3000")
-        .json(&query)
-        .send()
-        .await?;
+    let server_key = server_key()?;
+
+    let response = send_once(Upstream::Lens, || {
+        client
+            .post(&network().lens_api)
+            .header("Authorization", format!("Bearer {}", auth_tokens))
+            .header("x-api-key", &server_key)
+            .header("Content-Type", "application/json")
+            .header("Origin", &network().lens_origin)
+            // .header("Origin", "http://localhost:3000")
+            .json(&query)
+    })
+    .await?;
 
     let json: Value = response.json().await?;
-    println!("Post JSON response {:?}", json);
+    debug!("Post response: {}", redact(&json));
 
     if let Some(post_response) = json["data"]["post"].as_object() {
         if let Some(hash) = post_response.get("hash").and_then(|v| v.as_str()) {
-            println!("Post Hash: {:?}", hash);
-            return poll(hash, auth_tokens).await;
+            info!(post_hash = %hash, "Post submitted");
+            poll(hash, auth_tokens).await?;
+            return Ok(hash.to_string());
         }
 
         if let Some(raw) = post_response.get("raw").and_then(|v| v.as_object()) {
-            let to = raw.get("to").and_then(|v| v.as_str()).unwrap_or_default();
-            let from = raw.get("from").and_then(|v| v.as_str()).unwrap_or_default();
-            let data = raw.get("data").and_then(|v| v.as_str()).unwrap_or_default();
-
-            if to.is_empty() || from.is_empty() || data.is_empty() {
-                return Err("Invalid transaction data: missing required fields.".into());
-            }
-
-            let gas_limit = raw
-                .get("gasLimit")
-                .and_then(|v| v.as_u64())
-                .ok_or("Invalid gasLimit")?;
-            let max_fee_per_gas = raw
-                .get("maxFeePerGas")
-                .and_then(|v| v.as_str())
-                .ok_or("Invalid maxFeePerGas")?
-                .parse::<u128>()?;
-            let max_priority_fee_per_gas = raw
-                .get("maxPriorityFeePerGas")
-                .and_then(|v| v.as_str())
-                .ok_or("Invalid maxPriorityFeePerGas")?
-                .parse::<u128>()?;
-            let value = raw
-                .get("value")
-                .and_then(|v| v.as_str())
-                .ok_or("Invalid value")?
-                .parse::<u128>()?;
-            let chain_id = raw
-                .get("chainId")
-                .and_then(|v| v.as_u64())
-                .ok_or("Invalid chainId")?;
-
-            let provider = initialize_provider();
-            let current_nonce = provider
-                .get_transaction_count(wallet.address(), None)
-                .await?;
-
-            let tx = Eip1559TransactionRequest {
-                to: Some(to.parse()?),
-                from: Some(from.parse()?),
-                gas: Some(gas_limit.into()),
-                max_fee_per_gas: Some(max_fee_per_gas.into()),
-                max_priority_fee_per_gas: Some(max_priority_fee_per_gas.into()),
-                value: Some(value.into()),
-                data: Some(data.parse()?),
-                chain_id: Some(chain_id.into()),
-                nonce: Some(current_nonce.into()),
-                access_list: vec![].into(),
-            };
-
-            let typed_tx = TypedTransaction::Eip1559(tx);
-            let signed_tx = wallet.sign_transaction(&typed_tx).await?;
-            let signed_tx_bytes = typed_tx.rlp_signed(&signed_tx);
-
-            let pending_tx = provider
-                .send_raw_transaction(Bytes::from(signed_tx_bytes))
-                .await?;
-            return Ok(format!("Transaction sent: {}", pending_tx.tx_hash()));
+            return send_sponsored(raw, private_key, wallet, "post").await;
         }
 
         if let Some(reason) = post_response.get("reason").and_then(|v| v.as_str()) {
-            return Err(format!("Transaction failed: {}", reason).into());
+            return Err(AgentError::lens_rejected(format!("Transaction failed: {}", reason)));
         }
     }
 
-    Err("Unexpected response format.".into())
+    Err(AgentError::lens("Unexpected response format."))
+}
+
+async fn send_sponsored(
+    raw: &Map<String, Value>,
+    agent_id: u32,
+    wallet: LocalWallet,
+    method: &str,
+) -> Result<String, AgentError> {
+    let to = raw.get("to").and_then(|v| v.as_str()).unwrap_or_default();
+    let from = raw.get("from").and_then(|v| v.as_str()).unwrap_or_default();
+    let data = raw.get("data").and_then(|v| v.as_str()).unwrap_or_default();
+
+    if to.is_empty() || from.is_empty() || data.is_empty() {
+        return Err(AgentError::lens_rejected("Invalid transaction data: missing required fields."));
+    }
+
+    let value = raw
+        .get("value")
+        .and_then(|v| v.as_str())
+        .ok_or_else(|| AgentError::lens_rejected("Invalid transaction data: missing value."))?
+        .parse::<u128>()?;
+
+    // Lens sends the gas limit as a number and fees as decimal strings.
+    let amount = |name: &str| match raw.get(name) {
+        Some(Value::Number(number)) => number.as_u64().map(U256::from),
+        Some(Value::String(text)) => U256::from_dec_str(text).ok(),
+        _ => None,
+    };
+    let hints = GasHints {
+        gas_limit: amount("gasLimit"),
+        max_fee: amount("maxFeePerGas"),
+        max_priority_fee: amount("maxPriorityFeePerGas"),
+    };
+
+    let client = signer_client(initialize_provider(), wallet);
+    let sent = send_hinted_transaction(
+        &client,
+        agent_id,
+        method,
+        to.parse().map_err(AgentError::parse)?,
+        Some(data.parse().map_err(AgentError::parse)?),
+        Some(value.into()),
+        &hints,
+    )
+    .await?;
+
+    Ok(sent.hash)
 }
 
-async fn poll(hash: &str, auth_tokens: &str) -> Result<String, Box<dyn Error + Send + Sync>> {
+async fn poll(hash: &str, auth_tokens: &str) -> Result<String, AgentError> {
     let client = initialize_api();
     from_filename(".env").ok();
-    let server_key: String = var("SERVER_KEY").expect("SERVER_KEY not configured in .env");
+    let server_key = server_key()?;
 
     for attempt in 1..=10 {
-        println!("Poll attempt {}/10 for hash: {}", attempt, hash);
+        debug!(tx_hash = %hash, "Poll attempt {}/10", attempt);
 
         let query = json!({
             "query": r#"
@@ -457,55 +469,58 @@ async fn poll(hash: &str, auth_tokens: &str) -> Result<String, Box<dyn Error + S
             }
         });
 
-        let response = client
-            .post(LENS_API)
-            .header("Authorization", format!("Bearer {}", auth_tokens))
-            .header("x-api-key", &server_key)
-            .header("Content-Type", "application/json")
-            .header("Origin", "https://triplea-66ij.onrender.com")
-            .json(&query)
-            .send()
-            .await?;
+        let response = send_with_retry(Upstream::Lens, || {
+            client
+                .post(&network().lens_api)
+                .header("Authorization", format!("Bearer {}", auth_tokens))
+                .header("x-api-key", &server_key)
+                .header("Content-Type", "application/json")
+                .header("Origin", &network().lens_origin)
+                .json(&query)
+        })
+        .await?;
 
         if response.status().is_success() {
             let json: Value = response.json().await?;
-            println!("📡 Full API response: {:?}", json);
-
             if let Some(status) = json["data"]["transactionStatus"].as_object() {
-                println!("📊 Transaction status object: {:?}", status);
+                debug!(tx_hash = %hash, status = ?status, "Lens transaction status");
 
                 if let Some(timestamp) = status.get("blockTimestamp").and_then(|v| v.as_str()) {
-                    println!("✅ Transaction indexed successfully at: {}", timestamp);
+                    info!(tx_hash = %hash, "Transaction indexed at {}", timestamp);
                     return Ok(format!("Transaction finished at: {}", timestamp));
                 }
 
                 if let Some(reason) = status.get("reason").and_then(|v| v.as_str()) {
                     if reason.contains("not indexed yet") || reason.contains("keep trying") {
-                        println!("⏳ Transaction not indexed yet, retrying in 3 seconds...");
+                        debug!(tx_hash = %hash, "Transaction not indexed yet, retrying in 3 seconds");
                         tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
                         continue;
                     } else {
-                        println!("❌ Transaction failed with reason: {}", reason);
-                        return Err(format!("Transaction failed: {}", reason).into());
+                        error!(tx_hash = %hash, "Transaction failed: {}", reason);
+                        return Err(AgentError::lens_rejected(format!(
+                            "Transaction failed: {}",
+                            reason
+                        )));
                     }
                 }
             } else {
-                println!("⚠️ No transactionStatus in response");
+                warn!(tx_hash = %hash, "No transactionStatus in response: {}", redact(&json));
             }
         } else {
             let status = response.status();
             let error_body = response.text().await.unwrap_or_else(|_| "Unable to read error body".to_string());
-            println!("❌ Poll request failed - Status: {}, Body: {}", status, error_body);
+            warn!(tx_hash = %hash, "Poll request failed with status {}: {}", status, error_body);
         }
 
         tokio::time::sleep(tokio::time::Duration::from_secs(3)).await;
     }
 
-    println!("⚠️ Transaction polling timed out after 10 attempts");
+    warn!(tx_hash = %hash, "Transaction polling timed out after 10 attempts");
+    lens_poll_timeout();
     Ok("Transaction submitted but indexing timed out".to_string())
 }
 
-pub async fn handle_lens_account(wallet: &str, username: bool) -> Result<String, Box<dyn Error>> {
+pub async fn handle_lens_account(wallet: &str, username: bool) -> Result<String, AgentError> {
     let client = initialize_api();
     let query = json!({
         "query": r#"
@@ -541,17 +556,18 @@ pub async fn handle_lens_account(wallet: &str, username: bool) -> Result<String,
     });
 
     from_filename(".env").ok();
-    let server_key: String = var("SERVER_KEY").expect("SERVER_KEY not configured in .env");
-
-    let response = client
-        .post(LENS_API)
-        .header("Content-Type", "application/json")
-        .header("x-api-key", server_key)
-        .header("Origin", "https://triplea-66ij.onrender.com")
-        // .header("Origin", "http://localhost:3000")
-        .json(&query)
-        .send()
-        .await?;
+    let server_key = server_key()?;
+
+    let response = send_with_retry(Upstream::Lens, || {
+        client
+            .post(&network().lens_api)
+            .header("Content-Type", "application/json")
+            .header("x-api-key", &server_key)
+            .header("Origin", &network().lens_origin)
+            // .header("Origin", "http://localhost:3000")
+            .json(&query)
+    })
+    .await?;
 
     if response.status().is_success() {
         let json: Value = response.json().await?;
@@ -576,16 +592,16 @@ pub async fn handle_lens_account(wallet: &str, username: bool) -> Result<String,
                 }
             }
         }
-        return Err("No valid accounts found in the response.".into());
+        return Err(AgentError::lens("No valid accounts found in the response."));
     } else {
-        return Err(format!("Error: {}", response.status()).into());
+        return Err(AgentError::lens(format!("Error: {}", response.status())));
     }
 }
 
 pub async fn search_posts(
     wallet: &str,
     search_query: &str,
-) -> Result<(Vec<Value>, Vec<String>), Box<dyn Error + Send + Sync>> {
+) -> Result<(Vec<Value>, Vec<String>), AgentError> {
     let client = initialize_api();
 
     let query = json!({
@@ -630,16 +646,17 @@ pub async fn search_posts(
     });
 
     from_filename(".env").ok();
-    let server_key: String = var("SERVER_KEY").expect("SERVER_KEY not configured in .env");
-
-    let res = client
-        .post(LENS_API)
-        .header("Content-Type", "application/json")
-        .header("x-api-key", server_key)
-        .header("Origin", "https://triplea-66ij.onrender.com")
-        .json(&query)
-        .send()
-        .await?;
+    let server_key = server_key()?;
+
+    let res = send_with_retry(Upstream::Lens, || {
+        client
+            .post(&network().lens_api)
+            .header("Content-Type", "application/json")
+            .header("x-api-key", &server_key)
+            .header("Origin", &network().lens_origin)
+            .json(&query)
+    })
+    .await?;
 
     if res.status().is_success() {
         let json: Value = res.json().await?;
@@ -678,17 +695,110 @@ pub async fn search_posts(
                 .collect();
             return Ok((filtered_posts, filtered_profiles));
         } else {
-            return Err("Error: Unexpected Structure for search posts".into());
+            return Err(AgentError::lens("Error: Unexpected Structure for search posts"));
         }
     } else {
-        return Err(format!("Error: {}", res.status()).into());
+        return Err(AgentError::lens(format!("Error: {}", res.status())));
+    }
+}
+
+// Comments on and mentions of the authenticated account, newest first.
+pub async fn notifications(auth_tokens: &str) -> Result<Vec<Value>, AgentError> {
+    let client = initialize_api();
+
+    let query = json!({
+        "query": r#"
+        query Notifications($request: NotificationRequest!) {
+            notifications(request: $request) {
+                items {
+                    __typename
+                    ... on CommentNotification {
+                        id
+                        comment {
+                            ...NotifiedPost
+                        }
+                    }
+                    ... on MentionNotification {
+                        id
+                        post {
+                            ...NotifiedPost
+                        }
+                    }
+                }
+            }
+        }
+
+        fragment NotifiedPost on Post {
+            id
+            timestamp
+            author {
+                address
+                username {
+                    localName
+                }
+            }
+            metadata {
+                __typename
+                ... on TextOnlyMetadata {
+                    content
+                }
+                ... on ArticleMetadata {
+                    content
+                }
+                ... on ImageMetadata {
+                    content
+                }
+            }
+        }
+        "#,
+        "variables": {
+            "request": {
+                "filter": {
+                    "notificationTypes": ["COMMENTED", "MENTIONED"]
+                }
+            }
+        }
+    });
+
+    from_filename(".env").ok();
+    let server_key = server_key()?;
+
+    let res = send_with_retry(Upstream::Lens, || {
+        client
+            .post(&network().lens_api)
+            .header("Authorization", format!("Bearer {}", auth_tokens))
+            .header("x-api-key", &server_key)
+            .header("Content-Type", "application/json")
+            .header("Origin", &network().lens_origin)
+            .json(&query)
+    })
+    .await?;
+
+    if !res.status().is_success() {
+        return Err(AgentError::lens(format!("Error: {}", res.status())));
     }
+
+    let json: Value = res.json().await?;
+    let items = json["data"]["notifications"]["items"]
+        .as_array()
+        .ok_or_else(|| AgentError::lens("Error: Unexpected Structure for notifications"))?;
+
+    Ok(items
+        .iter()
+        .filter_map(|item| match item["__typename"].as_str() {
+            Some("CommentNotification") => Some(item["comment"].clone()),
+            Some("MentionNotification") => Some(item["post"].clone()),
+            _ => None,
+        })
+        .filter(|post| post["id"].is_string())
+        .collect())
 }
 
 pub async fn follow_profiles(
     profiles: Vec<String>,
     auth_tokens: &str,
-) -> Result<(), Box<dyn Error + Send + Sync>> {
+    agent_id: u32,
+) -> Result<(), AgentError> {
     let client = initialize_api();
 
     let follow_futures = profiles.into_iter().map(|profile| {
@@ -713,47 +823,65 @@ pub async fn follow_profiles(
                 }
             });
 
-            from_filename(".env").ok();
-            let server_key: String = var("SERVER_KEY").expect("SERVER_KEY not configured in .env");
+            if dry_run() {
+                report(agent_id, "follow", query["variables"]["request"].clone());
+                return Ok(());
+            }
 
-            let response = client
-                .post(LENS_API)
-                .header("Authorization", format!("Bearer {}", auth_tokens))
-                .header("x-api-key", server_key)
-                .header("Content-Type", "application/json")
-                .header("Origin", "https://triplea-66ij.onrender.com")
-                .json(&query)
-                .send()
-                .await?;
+            from_filename(".env").ok();
+            let server_key = server_key()?;
+
+            let response = send_once(Upstream::Lens, || {
+                client
+                    .post(&network().lens_api)
+                    .header("Authorization", format!("Bearer {}", auth_tokens))
+                    .header("x-api-key", &server_key)
+                    .header("Content-Type", "application/json")
+                    .header("Origin", &network().lens_origin)
+                    .json(&query)
+            })
+            .await?;
 
             if response.status().is_success() {
                 let json: Value = response.json().await?;
 
                 if let Some(follow_response) = json["data"]["follow"].as_object() {
                     if let Some(hash) = follow_response.get("hash").and_then(|v| v.as_str()) {
-                        println!("Follow Hash for {}: {:?}", profile, hash);
-                        let _ = poll(hash, &auth_tokens).await;
+                        info!(tx_hash = %hash, "Follow submitted for {}", profile);
+                        lens_operation("follow", true);
+                        poll(hash, &auth_tokens).await?;
+                        return Ok(());
                     }
-                } else {
-                    println!("Unexpected structure for profile: {}", profile);
                 }
+
+                warn!("Unexpected structure for profile: {}", profile);
+                Err(AgentError::lens(format!("Unexpected follow response for {}", profile)))
             } else {
-                println!("Error following profile {}: {}", profile, response.status());
+                error!("Error following profile {}: {}", profile, response.status());
+                Err(AgentError::lens(format!(
+                    "Error following profile {}: {}",
+                    profile,
+                    response.status()
+                )))
             }
-
-            Ok::<(), Box<dyn Error + Send + Sync>>(())
         }
     });
 
     let results: Vec<_> = join_all(follow_futures).await;
+    let mut first_error = None;
 
     for result in results {
         if let Err(e) = result {
-            println!("Error with following: {:?}", e);
+            error!("Error with following: {:?}", e);
+            lens_operation("follow", false);
+            first_error.get_or_insert(e);
         }
     }
 
-    Ok(())
+    match first_error {
+        Some(err) => Err(err),
+        None => Ok(()),
+    }
 }
 
 pub async fn make_comment(
@@ -761,14 +889,25 @@ pub async fn make_comment(
     private_key: u32,
     auth_tokens: &str,
     comment_id: &str,
-) -> Result<String, Box<dyn Error + Send + Sync>> {
+) -> Result<String, AgentError> {
+    let result = post_comment(content, private_key, auth_tokens, comment_id).await;
+    lens_operation("comment", result.is_ok());
+    result
+}
+
+async fn post_comment(
+    content: &str,
+    private_key: u32,
+    auth_tokens: &str,
+    comment_id: &str,
+) -> Result<String, AgentError> {
     let client = initialize_api();
 
     let wallet = match initialize_wallet(private_key) {
         Some(wallet) => wallet,
         None => {
-            eprintln!("Wallet initialization failed. Skipping comment.");
-            return Err("Wallet initialization failed. Skipping comment.".into());
+            error!("Wallet initialization failed. Skipping comment.");
+            return Err(AgentError::config("Wallet initialization failed. Skipping comment."));
         }
     };
 
@@ -808,95 +947,44 @@ pub async fn make_comment(
         }
     });
 
+    if dry_run() {
+        return Ok(report(private_key, "comment", query["variables"]["request"].clone()));
+    }
+
     from_filename(".env").ok();
-    let server_key: String = var("SERVER_KEY").expect("SERVER_KEY not configured in .env");
-
-    let response = client
-        .post(LENS_API)
-        .header("Authorization", format!("Bearer {}", auth_tokens))
-        .header("x-api-key", server_key)
-        .header("Content-Type", "application/json")
-        .header("Origin", "https://triplea-66ij.onrender.com")
-        // .header("Origin", "http://localhost:3000")
-        .json(&query)
-        .send()
-        .await?;
+    let server_key = server_key()?;
+
+    let response = send_once(Upstream::Lens, || {
+        client
+            .post(&network().lens_api)
+            .header("Authorization", format!("Bearer {}", auth_tokens))
+            .header("x-api-key", &server_key)
+            .header("Content-Type", "application/json")
+            .header("Origin", &network().lens_origin)
+            // .header("Origin", "http://localhost:3000")
+            .json(&query)
+    })
+    .await?;
 
     let json: Value = response.json().await?;
 
     if let Some(post_response) = json["data"]["post"].as_object() {
         if let Some(hash) = post_response.get("hash").and_then(|v| v.as_str()) {
-            println!("Comment Hash: {:?}", hash);
-            return poll(hash, auth_tokens).await;
+            info!(post_hash = %hash, "Comment submitted");
+            poll(hash, auth_tokens).await?;
+            return Ok(hash.to_string());
         }
 
         if let Some(raw) = post_response.get("raw").and_then(|v| v.as_object()) {
-            let to = raw.get("to").and_then(|v| v.as_str()).unwrap_or_default();
-            let from = raw.get("from").and_then(|v| v.as_str()).unwrap_or_default();
-            let data = raw.get("data").and_then(|v| v.as_str()).unwrap_or_default();
-
-            if to.is_empty() || from.is_empty() || data.is_empty() {
-                return Err("Invalid transaction data: missing required fields.".into());
-            }
-
-            let gas_limit = raw
-                .get("gasLimit")
-                .and_then(|v| v.as_u64())
-                .ok_or("Invalid gasLimit")?;
-            let max_fee_per_gas = raw
-                .get("maxFeePerGas")
-                .and_then(|v| v.as_str())
-                .ok_or("Invalid maxFeePerGas")?
-                .parse::<u128>()?;
-            let max_priority_fee_per_gas = raw
-                .get("maxPriorityFeePerGas")
-                .and_then(|v| v.as_str())
-                .ok_or("Invalid maxPriorityFeePerGas")?
-                .parse::<u128>()?;
-            let value = raw
-                .get("value")
-                .and_then(|v| v.as_str())
-                .ok_or("Invalid value")?
-                .parse::<u128>()?;
-            let chain_id = raw
-                .get("chainId")
-                .and_then(|v| v.as_u64())
-                .ok_or("Invalid chainId")?;
-
-            let provider = initialize_provider();
-            let current_nonce = provider
-                .get_transaction_count(wallet.address(), None)
-                .await?;
-
-            let tx = Eip1559TransactionRequest {
-                to: Some(to.parse()?),
-                from: Some(from.parse()?),
-                gas: Some(gas_limit.into()),
-                max_fee_per_gas: Some(max_fee_per_gas.into()),
-                max_priority_fee_per_gas: Some(max_priority_fee_per_gas.into()),
-                value: Some(value.into()),
-                data: Some(data.parse()?),
-                chain_id: Some(chain_id.into()),
-                nonce: Some(current_nonce.into()),
-                access_list: vec![].into(),
-            };
-
-            let typed_tx = TypedTransaction::Eip1559(tx);
-            let signed_tx = wallet.sign_transaction(&typed_tx).await?;
-            let signed_tx_bytes = typed_tx.rlp_signed(&signed_tx);
-
-            let pending_tx = provider
-                .send_raw_transaction(Bytes::from(signed_tx_bytes))
-                .await?;
-            return Ok(format!("Transaction sent: {}", pending_tx.tx_hash()));
+            return send_sponsored(raw, private_key, wallet, "comment").await;
         }
 
         if let Some(reason) = post_response.get("reason").and_then(|v| v.as_str()) {
-            return Err(format!("Transaction failed: {}", reason).into());
+            return Err(AgentError::lens_rejected(format!("Transaction failed: {}", reason)));
         }
     }
 
-    Err("Unexpected response format.".into())
+    Err(AgentError::lens("Unexpected response format."))
 }
 
 pub async fn make_quote(
@@ -904,14 +992,25 @@ pub async fn make_quote(
     private_key: u32,
     auth_tokens: &str,
     quote_id: &str,
-) -> Result<String, Box<dyn Error + Send + Sync>> {
+) -> Result<String, AgentError> {
+    let result = post_quote(content, private_key, auth_tokens, quote_id).await;
+    lens_operation("quote", result.is_ok());
+    result
+}
+
+async fn post_quote(
+    content: &str,
+    private_key: u32,
+    auth_tokens: &str,
+    quote_id: &str,
+) -> Result<String, AgentError> {
     let client = initialize_api();
 
     let wallet = match initialize_wallet(private_key) {
         Some(wallet) => wallet,
         None => {
-            eprintln!("Wallet initialization failed. Skipping quote.");
-            return Err("Wallet initialization failed. Skipping quote.".into());
+            error!("Wallet initialization failed. Skipping quote.");
+            return Err(AgentError::config("Wallet initialization failed. Skipping quote."));
         }
     };
 
@@ -951,98 +1050,47 @@ pub async fn make_quote(
         }
     });
 
+    if dry_run() {
+        return Ok(report(private_key, "quote", query["variables"]["request"].clone()));
+    }
+
     from_filename(".env").ok();
-    let server_key: String = var("SERVER_KEY").expect("SERVER_KEY not configured in .env");
-
-    let response = client
-        .post(LENS_API)
-        .header("Authorization", format!("Bearer {}", auth_tokens))
-        .header("x-api-key", server_key)
-        .header("Content-Type", "application/json")
-        .header("Origin", "https://triplea-66ij.onrender.com")
-        // .header("Origin", "http://localhost:3000")
-        .json(&query)
-        .send()
-        .await?;
+    let server_key = server_key()?;
+
+    let response = send_once(Upstream::Lens, || {
+        client
+            .post(&network().lens_api)
+            .header("Authorization", format!("Bearer {}", auth_tokens))
+            .header("x-api-key", &server_key)
+            .header("Content-Type", "application/json")
+            .header("Origin", &network().lens_origin)
+            // .header("Origin", "http://localhost:3000")
+            .json(&query)
+    })
+    .await?;
 
     let json: Value = response.json().await?;
 
     if let Some(post_response) = json["data"]["post"].as_object() {
         if let Some(hash) = post_response.get("hash").and_then(|v| v.as_str()) {
-            println!("Quote Hash: {:?}", hash);
-            return poll(hash, auth_tokens).await;
+            info!(post_hash = %hash, "Quote submitted");
+            poll(hash, auth_tokens).await?;
+            return Ok(hash.to_string());
         }
 
         if let Some(raw) = post_response.get("raw").and_then(|v| v.as_object()) {
-            let to = raw.get("to").and_then(|v| v.as_str()).unwrap_or_default();
-            let from = raw.get("from").and_then(|v| v.as_str()).unwrap_or_default();
-            let data = raw.get("data").and_then(|v| v.as_str()).unwrap_or_default();
-
-            if to.is_empty() || from.is_empty() || data.is_empty() {
-                return Err("Invalid transaction data: missing required fields.".into());
-            }
-
-            let gas_limit = raw
-                .get("gasLimit")
-                .and_then(|v| v.as_u64())
-                .ok_or("Invalid gasLimit")?;
-            let max_fee_per_gas = raw
-                .get("maxFeePerGas")
-                .and_then(|v| v.as_str())
-                .ok_or("Invalid maxFeePerGas")?
-                .parse::<u128>()?;
-            let max_priority_fee_per_gas = raw
-                .get("maxPriorityFeePerGas")
-                .and_then(|v| v.as_str())
-                .ok_or("Invalid maxPriorityFeePerGas")?
-                .parse::<u128>()?;
-            let value = raw
-                .get("value")
-                .and_then(|v| v.as_str())
-                .ok_or("Invalid value")?
-                .parse::<u128>()?;
-            let chain_id = raw
-                .get("chainId")
-                .and_then(|v| v.as_u64())
-                .ok_or("Invalid chainId")?;
-
-            let provider = initialize_provider();
-            let current_nonce = provider
-                .get_transaction_count(wallet.address(), None)
-                .await?;
-
-            let tx = Eip1559TransactionRequest {
-                to: Some(to.parse()?),
-                from: Some(from.parse()?),
-                gas: Some(gas_limit.into()),
-                max_fee_per_gas: Some(max_fee_per_gas.into()),
-                max_priority_fee_per_gas: Some(max_priority_fee_per_gas.into()),
-                value: Some(value.into()),
-                data: Some(data.parse()?),
-                chain_id: Some(chain_id.into()),
-                nonce: Some(current_nonce.into()),
-                access_list: vec![].into(),
-            };
-
-            let typed_tx = TypedTransaction::Eip1559(tx);
-            let signed_tx = wallet.sign_transaction(&typed_tx).await?;
-            let signed_tx_bytes = typed_tx.rlp_signed(&signed_tx);
-
-            let pending_tx = provider
-                .send_raw_transaction(Bytes::from(signed_tx_bytes))
-                .await?;
-            return Ok(format!("Transaction sent: {}", pending_tx.tx_hash()));
+            return send_sponsored(raw, private_key, wallet, "quote").await;
         }
 
         if let Some(reason) = post_response.get("reason").and_then(|v| v.as_str()) {
-            return Err(format!("Transaction failed: {}", reason).into());
+            return Err(AgentError::lens_rejected(format!("Transaction failed: {}", reason)));
         }
     }
 
-    Err("Unexpected response format.".into())
+    Err(AgentError::lens("Unexpected response format."))
 }
 
-pub async fn feed_info(feed: &str) -> Result<(String, String), Box<dyn Error + Send + Sync>> {
+pub async fn feed_info(feed: &str) -> Result<FeedInfo, AgentError> {
     let client = initialize_api();
 
     let query = json!({
@@ -1053,6 +1101,14 @@ pub async fn feed_info(feed: &str) -> Result<(String, String), Box<dyn Error + S
                     title 
                     description
                 }
+                rules {
+                    required {
+                        address
+                    }
+                    anyOf {
+                        address
+                    }
+                }
             }
         }
         "#,
@@ -1066,20 +1122,25 @@ pub async fn feed_info(feed: &str) -> Result<(String, String), Box<dyn Error + S
     });
 
     from_filename(".env").ok();
-    let server_key: String = var("SERVER_KEY").expect("SERVER_KEY not configured in .env");
-
-    let res = client
-        .post(LENS_API)
-        .header("Content-Type", "application/json")
-        .header("x-api-key", server_key)
-        .header("Origin", "https://triplea-66ij.onrender.com")
-        .json(&query)
-        .send()
-        .await?;
+    let server_key = server_key()?;
+
+    let res = send_with_retry(Upstream::Lens, || {
+        client
+            .post(&network().lens_api)
+            .header("Content-Type", "application/json")
+            .header("x-api-key", &server_key)
+            .header("Origin", &network().lens_origin)
+            .json(&query)
+    })
+    .await?;
 
     if res.status().is_success() {
         let json: Value = res.json().await?;
 
+        if json["data"].is_object() && json["data"]["feed"].is_null() {
+            return Err(AgentError::lens_rejected(format!("Feed {} not found", feed)));
+        }
+
         if let Some(metadata) = json["data"]["feed"]["metadata"].as_object() {
             let mut description = String::from("");
             let mut title = String::from("");
@@ -1091,11 +1152,85 @@ pub async fn feed_info(feed: &str) -> Result<(String, String), Box<dyn Error + S
                 title = tit.to_string();
             }
 
-            return Ok((title, description));
+            let rules = ["required", "anyOf"]
+                .iter()
+                .filter_map(|group| json["data"]["feed"]["rules"][group].as_array())
+                .flatten()
+                .filter_map(|rule| rule["address"].as_str().map(|address| address.to_string()))
+                .collect();
+
+            return Ok(FeedInfo {
+                title,
+                description,
+                rules,
+            });
         } else {
-            return Err("Error: Unexpected Structure for Feed Info".into());
+            return Err(AgentError::lens("Error: Unexpected Structure for Feed Info"));
         }
     } else {
-        return Err(format!("Error: {}", res.status()).into());
+        return Err(AgentError::lens(format!("Error: {}", res.status())));
     }
 }
+
+// Looks a post up by the transaction hash it was created with. `None` means Lens has no
+// post for the hash, either because it isn't indexed yet or because it wasn't a post.
+pub async fn post_stats(hash: &str) -> Result<Option<(String, PostStats)>, AgentError> {
+    let client = initialize_api();
+
+    let query = json!({
+        "query": r#"
+        query Post($request: PostRequest!) {
+            post(request: $request) {
+                ... on Post {
+                    id
+                    stats {
+                        reactions
+                        comments
+                        quotes
+                        collects
+                    }
+                }
+            }
+        }
+        "#,
+        "variables": {
+            "request": {
+                "txHash": hash,
+            }
+        }
+    });
+
+    from_filename(".env").ok();
+    let server_key = server_key()?;
+
+    let res = send_with_retry(Upstream::Lens, || {
+        client
+            .post(&network().lens_api)
+            .header("Content-Type", "application/json")
+            .header("x-api-key", &server_key)
+            .header("Origin", &network().lens_origin)
+            .json(&query)
+    })
+    .await?;
+
+    if !res.status().is_success() {
+        return Err(AgentError::lens(format!("Error: {}", res.status())));
+    }
+
+    let json: Value = res.json().await?;
+    let post = &json["data"]["post"];
+    let Some(id) = post["id"].as_str() else {
+        return Ok(None);
+    };
+
+    let stat = |name: &str| post["stats"][name].as_u64().unwrap_or_default();
+    Ok(Some((
+        id.to_string(),
+        PostStats {
+            reactions: stat("reactions"),
+            comments: stat("comments"),
+            quotes: stat("quotes"),
+            collects: stat("collects"),
+        },
+    )))
+}
@@ -0,0 +1,297 @@
+use crate::utils::{
+    dry_run::send,
+    types::{
+        AgentError, ChatMessage, ChatRequest, FixtureLlm, LlmBackend, LlmSettings, OpenAiLlm,
+        RosterLlm, Upstream, VeniceLlm,
+    },
+};
+use async_trait::async_trait;
+use dotenv::{from_filename, var};
+use reqwest::{Client, Response};
+use serde_json::{json, Value};
+use std::{collections::HashMap, fs, path::PathBuf, sync::Mutex};
+use tracing::{error, info};
+
+static LLM_SETTINGS: Mutex<Option<HashMap<u32, LlmSettings>>> = Mutex::new(None);
+
+#[async_trait]
+pub trait LlmProvider: Send + Sync {
+    fn name(&self) -> &str;
+
+    async fn complete(&self, request: &ChatRequest) -> Result<String, AgentError>;
+}
+
+pub fn parse_backend(input: &str) -> Result<LlmBackend, AgentError> {
+    match input.trim().to_lowercase().as_str() {
+        "venice" => Ok(LlmBackend::Venice),
+        "openai" => Ok(LlmBackend::OpenAi),
+        "fixture" => Ok(LlmBackend::Fixture),
+        other => Err(AgentError::config(format!("Unknown LLM provider: {}", other))),
+    }
+}
+
+pub fn llm_from_env() -> Result<LlmSettings, AgentError> {
+    let optional = |name: &str| {
+        var(name)
+            .ok()
+            .map(|value| value.trim().to_string())
+            .filter(|value| !value.is_empty())
+    };
+
+    let settings = LlmSettings {
+        provider: match optional("AGENT_LLM_PROVIDER") {
+            Some(provider) => parse_backend(&provider)?,
+            None => LlmBackend::Venice,
+        },
+        base_url: optional("AGENT_LLM_BASE_URL"),
+        api_key_env: optional("AGENT_LLM_API_KEY_ENV"),
+        model: optional("AGENT_LLM_MODEL"),
+        fixtures_dir: optional("AGENT_LLM_FIXTURES_DIR").map(PathBuf::from),
+    };
+    validate_llm(&settings)?;
+
+    Ok(settings)
+}
+
+pub fn llm_from_entry(entry: Option<&RosterLlm>) -> Result<LlmSettings, AgentError> {
+    let mut llm = llm_from_env()?;
+
+    if let Some(entry) = entry {
+        if let Some(provider) = &entry.provider {
+            llm.provider = parse_backend(provider)?;
+        }
+        if let Some(base_url) = &entry.base_url {
+            llm.base_url = Some(base_url.clone());
+        }
+        if let Some(api_key_env) = &entry.api_key_env {
+            llm.api_key_env = Some(api_key_env.clone());
+        }
+        if let Some(model) = &entry.model {
+            llm.model = Some(model.clone());
+        }
+        if let Some(fixtures_dir) = &entry.fixtures_dir {
+            llm.fixtures_dir = Some(PathBuf::from(fixtures_dir));
+        }
+    }
+    validate_llm(&llm)?;
+
+    Ok(llm)
+}
+
+fn validate_llm(settings: &LlmSettings) -> Result<(), AgentError> {
+    if settings.provider != LlmBackend::OpenAi {
+        return Ok(());
+    }
+
+    let base_url = settings
+        .base_url
+        .as_ref()
+        .ok_or_else(|| AgentError::config("The openai LLM provider needs a base_url"))?;
+    reqwest::Url::parse(base_url)
+        .map_err(|_| AgentError::config(format!("Invalid LLM base_url {}", base_url)))?;
+
+    Ok(())
+}
+
+pub fn register_llm_settings(agent_id: u32, llm: LlmSettings) {
+    LLM_SETTINGS
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(agent_id, llm);
+}
+
+pub fn llm_settings(agent_id: u32) -> Result<LlmSettings, AgentError> {
+    let registered = LLM_SETTINGS
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|settings| settings.get(&agent_id).cloned());
+
+    match registered {
+        Some(settings) => Ok(settings),
+        None => llm_from_env(),
+    }
+}
+
+pub fn llm_provider(settings: &LlmSettings) -> Result<Box<dyn LlmProvider>, AgentError> {
+    from_filename(".env").ok();
+
+    Ok(match settings.provider {
+        LlmBackend::Venice => Box::new(VeniceLlm {
+            client: Client::new(),
+            api_key: var("VENICE_KEY")
+                .map_err(|_| AgentError::config("VENICE_KEY not configured in .env"))?,
+        }),
+        LlmBackend::OpenAi => Box::new(OpenAiLlm {
+            client: Client::new(),
+            base_url: settings
+                .base_url
+                .clone()
+                .ok_or_else(|| AgentError::config("The openai LLM provider needs a base_url"))?,
+            api_key: var(settings.api_key_env.as_deref().unwrap_or("AGENT_LLM_API_KEY")).ok(),
+        }),
+        LlmBackend::Fixture => Box::new(FixtureLlm {
+            fixtures_dir: settings.fixtures_dir.clone(),
+        }),
+    })
+}
+
+pub async fn chat(
+    agent_id: u32,
+    task: &str,
+    model: &str,
+    system_prompt: &str,
+    input_prompt: &str,
+    max_tokens: u32,
+) -> Result<String, AgentError> {
+    chat_with_examples(agent_id, task, model, system_prompt, &[], input_prompt, max_tokens).await
+}
+
+// Few-shot turns go between the system prompt and the real request.
+pub async fn chat_with_examples(
+    agent_id: u32,
+    task: &str,
+    model: &str,
+    system_prompt: &str,
+    examples: &[ChatMessage],
+    input_prompt: &str,
+    max_tokens: u32,
+) -> Result<String, AgentError> {
+    let settings = llm_settings(agent_id)?;
+    let provider = llm_provider(&settings)?;
+
+    let mut messages = vec![ChatMessage::new("system", system_prompt)];
+    messages.extend_from_slice(examples);
+    messages.push(ChatMessage::new("user", input_prompt));
+
+    let request = ChatRequest {
+        task: task.to_string(),
+        model: settings.model.unwrap_or_else(|| model.to_string()),
+        messages,
+        max_tokens,
+    };
+
+    match provider.complete(&request).await {
+        Ok(completion) => {
+            info!(agent_id, "{} call successful for {}: {}", provider.name(), task, completion);
+            Ok(completion)
+        }
+        Err(err) => {
+            error!(agent_id, "{} error ({}): {}", provider.name(), task, err);
+            Err(err)
+        }
+    }
+}
+
+pub async fn completion_text(
+    response: Response,
+    on_error: impl FnOnce(u16, String) -> AgentError,
+) -> Result<String, AgentError> {
+    let status = response.status();
+    if !status.is_success() {
+        let error_text = response
+            .text()
+            .await
+            .unwrap_or_else(|_| "Could not read error".to_string());
+        return Err(on_error(status.as_u16(), error_text));
+    }
+
+    let response_json: Value = response.json().await?;
+    Ok(response_json["choices"][0]["message"]["content"]
+        .as_str()
+        .unwrap_or("")
+        .to_string())
+}
+
+impl ChatMessage {
+    pub fn new(role: &str, content: &str) -> Self {
+        ChatMessage {
+            role: role.to_string(),
+            content: content.to_string(),
+        }
+    }
+}
+
+#[async_trait]
+impl LlmProvider for OpenAiLlm {
+    fn name(&self) -> &str {
+        "OpenAI-compatible"
+    }
+
+    async fn complete(&self, request: &ChatRequest) -> Result<String, AgentError> {
+        let url = format!("{}/chat/completions", self.base_url.trim_end_matches('/'));
+        let request_body = json!({
+            "model": request.model,
+            "messages": request.messages,
+            "max_tokens": request.max_tokens,
+        });
+
+        let response = send(
+            Upstream::Llm,
+            &format!("llm/{}", request.task),
+            &request_body.to_string(),
+            || {
+                let builder = self
+                    .client
+                    .post(&url)
+                    .header("Content-Type", "application/json")
+                    .json(&request_body);
+                match &self.api_key {
+                    Some(key) => builder.header("Authorization", format!("Bearer {}", key)),
+                    None => builder,
+                }
+            },
+        )
+        .await?;
+
+        completion_text(response, |status, error_text| {
+            AgentError::llm(
+                Some(status),
+                format!("Error in obtaining completion {} - {}", status, error_text),
+            )
+        })
+        .await
+    }
+}
+
+#[async_trait]
+impl LlmProvider for FixtureLlm {
+    fn name(&self) -> &str {
+        "Fixture"
+    }
+
+    async fn complete(&self, request: &ChatRequest) -> Result<String, AgentError> {
+        if let Some(dir) = &self.fixtures_dir {
+            if let Ok(text) = fs::read_to_string(dir.join(format!("{}.txt", request.task))) {
+                return Ok(text);
+            }
+        }
+
+        Ok(fixture_completion(&request.task))
+    }
+}
+
+// Canned answers shaped so the extract_values_* parsers accept them.
+fn fixture_completion(task: &str) -> String {
+    match task {
+        "receive_query" => "surrealism".to_string(),
+        "comment_completion" => {
+            "Every mirror in this piece is a question the frame refuses to answer.\n\nuse_image: NO"
+                .to_string()
+        }
+        "prompt" => "Image Prompt: a garden of folded mirrors melting into violet fog, \
+            double exposure, wide angle, dreamlike stillness\nModel: qwen-image"
+            .to_string(),
+        "image_details" => "Title: Folded Light Rehearsal\n\
+            Description: A room that remembers every shadow it has ever held.\n\
+            Amount: 10\n\
+            WGho: 1000000000000000000\n\
+            Bonsai: 1000000000000000000\n\
+            Mona: 1000000000000000000"
+            .to_string(),
+        "drop_details" => "Title: Rehearsal For A Quiet Flood".to_string(),
+        _ => "Fixture text: the collection folds the ordinary into something briefly strange."
+            .to_string(),
+    }
+}
@@ -0,0 +1,76 @@
+use crate::utils::types::LensTokens;
+use dotenv::var;
+use serde_json::{Map, Value};
+use std::fmt;
+use tracing_subscriber::EnvFilter;
+
+const REDACTED: &str = "<redacted>";
+const SECRET_KEYS: &[&str] = &[
+    "accesstoken",
+    "refreshtoken",
+    "idtoken",
+    "privatekey",
+    "authorization",
+    "apikey",
+    "encryptionkey",
+    "faucetkey",
+];
+
+pub fn init_logging() {
+    let filter = EnvFilter::try_from_env("AGENT_LOG")
+        .or_else(|_| EnvFilter::try_from_default_env())
+        .unwrap_or_else(|_| EnvFilter::new("info"));
+    let json = var("AGENT_LOG_FORMAT")
+        .map(|format| format.trim().eq_ignore_ascii_case("json"))
+        .unwrap_or(false);
+
+    let builder = tracing_subscriber::fmt().with_env_filter(filter).with_target(false);
+
+    let result = if json {
+        builder
+            .json()
+            .with_current_span(true)
+            .with_span_list(true)
+            .try_init()
+    } else {
+        builder.try_init()
+    };
+
+    if let Err(err) = result {
+        eprintln!("Logging already initialised: {}", err);
+    }
+}
+
+pub fn redact(value: &Value) -> Value {
+    match value {
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(key, value)| {
+                    let value = if is_secret(key) {
+                        Value::String(REDACTED.to_string())
+                    } else {
+                        redact(value)
+                    };
+                    (key.clone(), value)
+                })
+                .collect::<Map<String, Value>>(),
+        ),
+        Value::Array(values) => Value::Array(values.iter().map(redact).collect()),
+        other => other.clone(),
+    }
+}
+
+fn is_secret(key: &str) -> bool {
+    let key = key.to_lowercase().replace(['_', '-'], "");
+    SECRET_KEYS.contains(&key.as_str())
+}
+
+impl fmt::Debug for LensTokens {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("LensTokens")
+            .field("access_token", &REDACTED)
+            .field("refresh_token", &REDACTED)
+            .field("id_token", &REDACTED)
+            .finish()
+    }
+}
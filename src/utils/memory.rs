@@ -0,0 +1,244 @@
+use crate::utils::{
+    store::{open_private, state_path},
+    types::{AgentError, AgentMemory, MemoryEntry, MemoryKind, MemoryQuery},
+};
+use chrono::DateTime;
+use dotenv::var;
+use std::{
+    collections::HashSet,
+    fs,
+    io::{BufRead, BufReader, ErrorKind, Write},
+    sync::Mutex,
+};
+use tracing::{error, warn};
+
+static MEMORY_LOCK: Mutex<()> = Mutex::new(());
+
+const DEFAULT_MEMORY_LIMIT: usize = 500;
+const DEFAULT_MEMORY_RECALL: usize = 5;
+const STOP_WORDS: &[&str] = &[
+    "about", "after", "also", "been", "from", "have", "into", "just", "like", "more", "only",
+    "over", "some", "than", "that", "them", "then", "there", "their", "these", "they", "this",
+    "what", "when", "where", "which", "will", "with", "would", "your",
+];
+
+fn env_usize(name: &str, default: usize) -> usize {
+    var(name)
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(default)
+}
+
+impl AgentMemory {
+    pub fn open(agent_id: u32) -> Result<Self, AgentError> {
+
+        Ok(AgentMemory {
+            agent_id,
+            path: state_path(agent_id, "memory.jsonl")?,
+        })
+    }
+
+    pub fn append(&self, entry: &MemoryEntry) -> Result<(), AgentError> {
+        let mut line = serde_json::to_string(entry)?;
+        line.push('\n');
+
+        let _guard = MEMORY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut file = open_private(&self.path, true)?;
+        file.write_all(line.as_bytes())?;
+        file.flush()?;
+
+        Ok(())
+    }
+
+    pub fn entries(&self) -> Result<Vec<MemoryEntry>, AgentError> {
+        let file = match fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut entries = vec![];
+        for (number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match serde_json::from_str::<MemoryEntry>(&line) {
+                Ok(entry) => entries.push(entry),
+                Err(err) => warn!(
+                    "Skipping corrupt memory line {} for agent_{} in {:?}: {}",
+                    number + 1,
+                    self.agent_id,
+                    self.path,
+                    err
+                ),
+            }
+        }
+
+        Ok(entries)
+    }
+
+    // Keeps the newest `limit` entries.
+    pub fn compact(&self, limit: usize) -> Result<(), AgentError> {
+        let _guard = MEMORY_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let entries = self.entries()?;
+        if entries.len() <= limit {
+            return Ok(());
+        }
+
+        let mut contents = String::new();
+        for entry in &entries[entries.len() - limit..] {
+            contents.push_str(&serde_json::to_string(entry)?);
+            contents.push('\n');
+        }
+
+        let tmp = self.path.with_extension("jsonl.tmp");
+        let mut file = open_private(&tmp, false)?;
+        file.write_all(contents.as_bytes())?;
+        file.flush()?;
+        fs::rename(&tmp, &self.path)?;
+
+        Ok(())
+    }
+
+    pub fn recall(
+        &self,
+        query: &MemoryQuery,
+        limit: usize,
+    ) -> Result<Vec<MemoryEntry>, AgentError> {
+        let wanted = keywords(&query.text);
+        let mut scored: Vec<(usize, MemoryEntry)> = self
+            .entries()?
+            .into_iter()
+            .map(|entry| (score(&entry, query, &wanted), entry))
+            .filter(|(score, _)| *score > 0)
+            .collect();
+
+        scored.sort_by(|(a, a_entry), (b, b_entry)| b.cmp(a).then(b_entry.at.cmp(&a_entry.at)));
+        let mut recalled: Vec<MemoryEntry> =
+            scored.into_iter().take(limit).map(|(_, entry)| entry).collect();
+        recalled.sort_by_key(|entry| entry.at);
+
+        Ok(recalled)
+    }
+}
+
+fn keywords(text: &str) -> HashSet<String> {
+    text.split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= 4)
+        .map(|word| word.to_lowercase())
+        .filter(|word| !STOP_WORDS.contains(&word.as_str()))
+        .collect()
+}
+
+// Same author counts most, then the same collection, then shared keywords.
+fn score(entry: &MemoryEntry, query: &MemoryQuery, wanted: &HashSet<String>) -> usize {
+    let mut score = 0;
+
+    if let (Some(author), Some(wanted_author)) = (&entry.author, &query.author) {
+        if author.eq_ignore_ascii_case(wanted_author) {
+            score += 4;
+        }
+    }
+    if query.collection_id == Some(entry.collection_id) {
+        score += 2;
+    }
+
+    let text = format!("{} {}", entry.text, entry.replied_to.clone().unwrap_or_default());
+    score + keywords(&text).intersection(wanted).count().min(3)
+}
+
+fn shorten(text: &str) -> String {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    if text.chars().count() <= 200 {
+        return text;
+    }
+    format!("{}...", text.chars().take(200).collect::<String>())
+}
+
+fn format_memory(entries: &[MemoryEntry]) -> String {
+    if entries.is_empty() {
+        return String::new();
+    }
+
+    let mut memory = String::from(
+        "\n\nYour earlier posts and replies. Build on them where it fits and never repeat them:",
+    );
+    for entry in entries {
+        let day = DateTime::from_timestamp(entry.at, 0)
+            .map(|at| at.format("%Y-%m-%d").to_string())
+            .unwrap_or_default();
+        let author = entry.author.as_deref().unwrap_or("someone");
+        let replied_to = shorten(entry.replied_to.as_deref().unwrap_or_default());
+        let line = match entry.kind {
+            MemoryKind::Post => format!(
+                "{}: you posted about {}: {}",
+                day,
+                entry.collection_title,
+                shorten(&entry.text)
+            ),
+            MemoryKind::Comment => format!(
+                "{}: {} wrote: {} / you replied: {}",
+                day,
+                author,
+                replied_to,
+                shorten(&entry.text)
+            ),
+            MemoryKind::Feed => format!(
+                "{}: you posted in feed {} about {}: {}",
+                day,
+                entry.feed.as_deref().unwrap_or_default(),
+                entry.collection_title,
+                shorten(&entry.text)
+            ),
+            MemoryKind::Reply => format!(
+                "{}: {} replied to you: {} / you answered: {}",
+                day,
+                author,
+                replied_to,
+                shorten(&entry.text)
+            ),
+            MemoryKind::Quote => format!(
+                "{}: you quoted {} ({}) with: {}",
+                day,
+                author,
+                replied_to,
+                shorten(&entry.text)
+            ),
+        };
+        memory.push_str("\n- ");
+        memory.push_str(&line);
+    }
+
+    memory
+}
+
+pub fn remember(agent_id: u32, entry: MemoryEntry) {
+    let result = AgentMemory::open(agent_id).and_then(|memory| memory.append(&entry));
+    if let Err(err) = result {
+        error!("Error writing memory for agent_{}: {:?}", agent_id, err);
+    }
+}
+
+pub fn recall_memory(agent_id: u32, query: &MemoryQuery) -> String {
+    let limit = env_usize("AGENT_MEMORY_RECALL", DEFAULT_MEMORY_RECALL);
+    if limit == 0 {
+        return String::new();
+    }
+
+    match AgentMemory::open(agent_id).and_then(|memory| memory.recall(query, limit)) {
+        Ok(entries) => format_memory(&entries),
+        Err(err) => {
+            error!("Error reading memory for agent_{}: {:?}", agent_id, err);
+            String::new()
+        }
+    }
+}
+
+pub fn prune_memory(agent_id: u32) {
+    let limit = env_usize("AGENT_MEMORY_LIMIT", DEFAULT_MEMORY_LIMIT);
+    if let Err(err) = AgentMemory::open(agent_id).and_then(|memory| memory.compact(limit)) {
+        error!("Error compacting memory for agent_{}: {:?}", agent_id, err);
+    }
+}
@@ -0,0 +1,266 @@
+use crate::utils::types::AgentError;
+use dotenv::var;
+use ethers::types::{TransactionReceipt, U256};
+use http_body_util::Full;
+use hyper::{
+    body::{Bytes, Incoming},
+    header::CONTENT_TYPE,
+    server::conn::http1,
+    service::service_fn,
+    Request, Response, StatusCode,
+};
+use hyper_util::rt::TokioIo;
+use prometheus::{
+    register_counter_vec, register_gauge_vec, register_histogram_vec, register_int_counter,
+    register_int_counter_vec, CounterVec, Encoder, GaugeVec, HistogramVec, IntCounter,
+    IntCounterVec, TextEncoder,
+};
+use std::{convert::Infallible, net::SocketAddr, sync::LazyLock, time::Duration};
+use tokio::net::TcpListener;
+use tracing::{error, info};
+
+static ACTIVITIES_ATTEMPTED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "agent_activities_attempted_total",
+        "Activities started, per agent and activity type",
+        &["agent_id", "activity"]
+    )
+    .unwrap()
+});
+
+static ACTIVITIES_SUCCEEDED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "agent_activities_succeeded_total",
+        "Activities that finished without error, per agent and activity type",
+        &["agent_id", "activity"]
+    )
+    .unwrap()
+});
+
+static VENICE_LATENCY: LazyLock<HistogramVec> = LazyLock::new(|| {
+    register_histogram_vec!(
+        "agent_venice_request_seconds",
+        "Venice API request latency",
+        &["endpoint"],
+        vec![0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 30.0, 60.0, 120.0]
+    )
+    .unwrap()
+});
+
+static VENICE_ERRORS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "agent_venice_errors_total",
+        "Venice API failures, by HTTP status code or \"transport\"",
+        &["endpoint", "code"]
+    )
+    .unwrap()
+});
+
+static LENS_OPERATIONS: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "agent_lens_operations_total",
+        "Lens post, comment, quote and follow outcomes",
+        &["operation", "outcome"]
+    )
+    .unwrap()
+});
+
+static LENS_POLL_TIMEOUTS: LazyLock<IntCounter> = LazyLock::new(|| {
+    register_int_counter!(
+        "agent_lens_poll_timeouts_total",
+        "Lens transactions that were not indexed before polling gave up"
+    )
+    .unwrap()
+});
+
+static UPLOAD_FAILURES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "agent_upload_failures_total",
+        "Failed IPFS and Lens Storage uploads",
+        &["target"]
+    )
+    .unwrap()
+});
+
+static UPSTREAM_RETRIES: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "agent_upstream_retries_total",
+        "Retried HTTP and RPC calls, per upstream",
+        &["upstream"]
+    )
+    .unwrap()
+});
+
+static CIRCUITS_OPENED: LazyLock<IntCounterVec> = LazyLock::new(|| {
+    register_int_counter_vec!(
+        "agent_circuit_opened_total",
+        "Times an upstream circuit breaker opened",
+        &["upstream"]
+    )
+    .unwrap()
+});
+
+static GAS_SPENT: LazyLock<CounterVec> = LazyLock::new(|| {
+    register_counter_vec!(
+        "agent_gas_spent_gho_total",
+        "GHO spent on gas, per agent and transaction kind",
+        &["agent_id", "kind"]
+    )
+    .unwrap()
+});
+
+static GHO_BALANCE: LazyLock<GaugeVec> = LazyLock::new(|| {
+    register_gauge_vec!(
+        "agent_gho_balance",
+        "Native GHO balance of the agent wallet",
+        &["agent_id"]
+    )
+    .unwrap()
+});
+
+pub fn activity_attempted(agent_id: u32, activity: &str) {
+    ACTIVITIES_ATTEMPTED
+        .with_label_values(&[&agent_id.to_string(), activity])
+        .inc();
+}
+
+pub fn activity_succeeded(agent_id: u32, activity: &str) {
+    ACTIVITIES_SUCCEEDED
+        .with_label_values(&[&agent_id.to_string(), activity])
+        .inc();
+}
+
+pub fn venice_request(endpoint: &str, elapsed: Duration, status: Option<u16>) {
+    VENICE_LATENCY
+        .with_label_values(&[endpoint])
+        .observe(elapsed.as_secs_f64());
+
+    match status {
+        Some(200) => {}
+        Some(code) => VENICE_ERRORS
+            .with_label_values(&[endpoint, &code.to_string()])
+            .inc(),
+        None => VENICE_ERRORS
+            .with_label_values(&[endpoint, "transport"])
+            .inc(),
+    }
+}
+
+pub fn lens_operation(operation: &str, succeeded: bool) {
+    let outcome = if succeeded { "success" } else { "failure" };
+    LENS_OPERATIONS
+        .with_label_values(&[operation, outcome])
+        .inc();
+}
+
+pub fn lens_poll_timeout() {
+    LENS_POLL_TIMEOUTS.inc();
+}
+
+pub fn upload_failed(target: &str) {
+    UPLOAD_FAILURES.with_label_values(&[target]).inc();
+}
+
+pub fn upstream_retry(upstream: &str) {
+    UPSTREAM_RETRIES.with_label_values(&[upstream]).inc();
+}
+
+pub fn circuit_opened(upstream: &str) {
+    CIRCUITS_OPENED.with_label_values(&[upstream]).inc();
+}
+
+pub fn gas_spent(agent_id: u32, kind: &str, receipt: &TransactionReceipt) {
+    let (Some(gas_used), Some(price)) = (receipt.gas_used, receipt.effective_gas_price) else {
+        return;
+    };
+
+    GAS_SPENT
+        .with_label_values(&[&agent_id.to_string(), kind])
+        .inc_by(to_gho(gas_used.saturating_mul(price)));
+}
+
+pub fn gho_balance(agent_id: u32, balance: U256) {
+    GHO_BALANCE
+        .with_label_values(&[&agent_id.to_string()])
+        .set(to_gho(balance));
+}
+
+fn to_gho(wei: U256) -> f64 {
+    wei.to_string().parse::<f64>().unwrap_or(0.0) / 1e18
+}
+
+pub fn metrics_addr() -> Result<Option<SocketAddr>, AgentError> {
+    match var("AGENT_METRICS_ADDR") {
+        Ok(addr) if !addr.trim().is_empty() => Ok(Some(
+            addr.trim()
+                .parse()
+                .map_err(|e| AgentError::config(format!("Invalid AGENT_METRICS_ADDR: {}", e)))?,
+        )),
+        _ => Ok(None),
+    }
+}
+
+pub async fn serve_metrics(addr: SocketAddr) {
+    LazyLock::force(&ACTIVITIES_ATTEMPTED);
+    LazyLock::force(&ACTIVITIES_SUCCEEDED);
+    LazyLock::force(&VENICE_LATENCY);
+    LazyLock::force(&VENICE_ERRORS);
+    LazyLock::force(&LENS_OPERATIONS);
+    LazyLock::force(&LENS_POLL_TIMEOUTS);
+    LazyLock::force(&UPLOAD_FAILURES);
+    LazyLock::force(&UPSTREAM_RETRIES);
+    LazyLock::force(&CIRCUITS_OPENED);
+    LazyLock::force(&GAS_SPENT);
+    LazyLock::force(&GHO_BALANCE);
+
+    let listener = match TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            error!("Metrics exporter failed to bind {}: {:?}", addr, err);
+            return;
+        }
+    };
+    info!("Metrics available at http://{}/metrics", addr);
+
+    loop {
+        let (stream, _) = match listener.accept().await {
+            Ok(connection) => connection,
+            Err(err) => {
+                error!("Metrics exporter accept error: {:?}", err);
+                continue;
+            }
+        };
+
+        tokio::spawn(async move {
+            let service =
+                service_fn(|request| async move { Ok::<_, Infallible>(handle(request)) });
+
+            if let Err(err) = http1::Builder::new()
+                .serve_connection(TokioIo::new(stream), service)
+                .await
+            {
+                error!("Metrics exporter connection error: {:?}", err);
+            }
+        });
+    }
+}
+
+fn handle(request: Request<Incoming>) -> Response<Full<Bytes>> {
+    if request.uri().path() != "/metrics" {
+        let mut response = Response::new(Full::new(Bytes::from_static(b"not found")));
+        *response.status_mut() = StatusCode::NOT_FOUND;
+        return response;
+    }
+
+    let encoder = TextEncoder::new();
+    let mut buffer = vec![];
+    if let Err(err) = encoder.encode(&prometheus::gather(), &mut buffer) {
+        error!("Failed to encode metrics: {:?}", err);
+    }
+
+    let mut response = Response::new(Full::new(Bytes::from(buffer)));
+    response
+        .headers_mut()
+        .insert(CONTENT_TYPE, encoder.format_type().parse().unwrap());
+    response
+}
@@ -4,4 +4,25 @@ pub mod contracts;
 pub mod venice;
 pub mod ipfs;
 pub mod lens;
-pub mod helpers;
\ No newline at end of file
+pub mod helpers;
+pub mod schedule;
+pub mod roster;
+pub mod store;
+pub mod dry_run;
+pub mod transactions;
+pub mod nonce;
+pub mod bindings;
+pub mod subgraph;
+pub mod network;
+pub mod control;
+pub mod logging;
+pub mod metrics;
+pub mod errors;
+pub mod retry;
+pub mod llm;
+pub mod image;
+pub mod prompts;
+pub mod persona;
+pub mod memory;
+pub mod respond;
+pub mod analytics;
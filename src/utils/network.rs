@@ -0,0 +1,339 @@
+use crate::utils::{
+    constants::{
+        ACCESS_CONTROLS, AGENTS, BONSAI, COLLECTION_MANAGER, GROVE_API, INFURA_GATEWAY, IPFS_API,
+        LENS_API, LENS_CHAIN_ID, LENS_ORIGIN, LENS_RPC_URL, MARKET, MONA, TRIPLEA_GRAPH,
+        VENICE_API, WGHO,
+    },
+    lens::feed_info,
+    types::{AgentError, NetworkEntry, NetworkProfile},
+};
+use dotenv::from_filename;
+use ethers::types::Address;
+use std::{collections::HashMap, path::Path, sync::OnceLock};
+use tracing::{info, warn};
+
+static NETWORK: OnceLock<NetworkProfile> = OnceLock::new();
+
+// Loads the network profile once, at startup, so every later lookup is a plain borrow.
+pub fn init_network() -> Result<&'static NetworkProfile, AgentError> {
+    if let Some(profile) = NETWORK.get() {
+        return Ok(profile);
+    }
+
+    from_filename(".env").ok();
+    let env: HashMap<String, String> = std::env::vars().collect();
+    let profile = load_network(&env)?;
+
+    Ok(NETWORK.get_or_init(|| profile))
+}
+
+pub fn network() -> &'static NetworkProfile {
+    NETWORK
+        .get()
+        .expect("init_network runs in main before anything reads the network")
+}
+
+pub fn mainnet() -> NetworkProfile {
+    NetworkProfile {
+        name: "mainnet".to_string(),
+        chain_id: *LENS_CHAIN_ID,
+        rpc_url: LENS_RPC_URL.to_string(),
+        lens_api: LENS_API.to_string(),
+        lens_origin: LENS_ORIGIN.to_string(),
+        grove_api: GROVE_API.to_string(),
+        ipfs_api: IPFS_API.to_string(),
+        ipfs_gateway: INFURA_GATEWAY.to_string(),
+        venice_api: VENICE_API.to_string(),
+        subgraph_url: TRIPLEA_GRAPH.to_string(),
+        agents: AGENTS.to_string(),
+        access_controls: ACCESS_CONTROLS.to_string(),
+        collection_manager: COLLECTION_MANAGER.to_string(),
+        market: MARKET.to_string(),
+        wgho: WGHO.to_string(),
+        bonsai: BONSAI.to_string(),
+        mona: MONA.to_string(),
+        remix_feed: None,
+    }
+}
+
+fn load_network(env: &HashMap<String, String>) -> Result<NetworkProfile, AgentError> {
+    let name = env
+        .get("AGENT_NETWORK")
+        .map_or("mainnet", String::as_str)
+        .trim()
+        .to_lowercase();
+    let path = env
+        .get("AGENT_NETWORK_FILE")
+        .map_or("networks.toml", String::as_str);
+
+    let mut profiles: HashMap<String, NetworkEntry> = if Path::new(&path).exists() {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| {
+                AgentError::config(format!("Failed to read network file {}: {}", path, e))
+            })?;
+        toml::from_str(&contents)
+            .map_err(|e| AgentError::config(format!("Failed to parse {}: {}", path, e)))?
+    } else {
+        HashMap::new()
+    };
+
+    let mut profile = match profiles.remove(&name) {
+        Some(entry) if name == "mainnet" => apply_entry(mainnet(), entry),
+        Some(entry) => apply_entry(without_deployments(mainnet()), entry),
+        None if name == "mainnet" => mainnet(),
+        None => return Err(AgentError::config(format!("Network {} not found in {}", name, path))),
+    };
+    profile.name = name;
+
+    if let Some(url) = env.get("GRAPH_NODE_URL") {
+        profile.subgraph_url = url.clone();
+    }
+    if let Some(feed) = env.get("AGENT_REMIX_FEED") {
+        profile.remix_feed = Some(feed.clone());
+    }
+    // An empty feed turns routing off, so remixes go to the global timeline.
+    profile.remix_feed = profile
+        .remix_feed
+        .map(|feed| feed.trim().to_string())
+        .filter(|feed| !feed.is_empty());
+
+    validate_network(&profile)?;
+
+    Ok(profile)
+}
+
+// Other networks only borrow mainnet's public endpoints. The subgraph, contracts and
+// tokens must be set in their own profile, or they'd point at mainnet deployments.
+fn without_deployments(profile: NetworkProfile) -> NetworkProfile {
+    NetworkProfile {
+        subgraph_url: String::new(),
+        agents: String::new(),
+        access_controls: String::new(),
+        collection_manager: String::new(),
+        market: String::new(),
+        wgho: String::new(),
+        bonsai: String::new(),
+        mona: String::new(),
+        ..profile
+    }
+}
+
+fn apply_entry(mut profile: NetworkProfile, entry: NetworkEntry) -> NetworkProfile {
+    macro_rules! apply {
+        ($($field:ident),*) => {
+            $(if let Some(value) = entry.$field {
+                profile.$field = value;
+            })*
+        };
+    }
+
+    apply!(
+        chain_id,
+        rpc_url,
+        lens_api,
+        lens_origin,
+        grove_api,
+        ipfs_api,
+        ipfs_gateway,
+        venice_api,
+        subgraph_url,
+        agents,
+        access_controls,
+        collection_manager,
+        market,
+        wgho,
+        bonsai,
+        mona
+    );
+    if entry.remix_feed.is_some() {
+        profile.remix_feed = entry.remix_feed;
+    }
+
+    profile
+}
+
+fn validate_network(profile: &NetworkProfile) -> Result<(), AgentError> {
+    let required = [
+        ("subgraph_url", &profile.subgraph_url),
+        ("agents", &profile.agents),
+        ("access_controls", &profile.access_controls),
+        ("collection_manager", &profile.collection_manager),
+        ("market", &profile.market),
+        ("wgho", &profile.wgho),
+        ("bonsai", &profile.bonsai),
+        ("mona", &profile.mona),
+    ];
+    for (label, value) in required {
+        if value.trim().is_empty() {
+            return Err(AgentError::config(format!(
+                "Network {} must set {} in its profile",
+                profile.name, label
+            )));
+        }
+    }
+
+    for (label, address) in [
+        ("agents", &profile.agents),
+        ("access_controls", &profile.access_controls),
+        ("collection_manager", &profile.collection_manager),
+        ("market", &profile.market),
+        ("wgho", &profile.wgho),
+        ("bonsai", &profile.bonsai),
+        ("mona", &profile.mona),
+    ] {
+        address.parse::<Address>().map_err(|_| {
+            AgentError::config(format!(
+                "Invalid {} address {} in network {}",
+                label, address, profile.name
+            ))
+        })?;
+    }
+
+    if let Some(feed) = &profile.remix_feed {
+        feed.parse::<Address>().map_err(|_| {
+            AgentError::config(format!(
+                "Invalid remix_feed address {} in network {}",
+                feed, profile.name
+            ))
+        })?;
+    }
+
+    for (label, url) in [
+        ("rpc_url", &profile.rpc_url),
+        ("lens_api", &profile.lens_api),
+        ("grove_api", &profile.grove_api),
+        ("ipfs_api", &profile.ipfs_api),
+        ("ipfs_gateway", &profile.ipfs_gateway),
+        ("venice_api", &profile.venice_api),
+        ("subgraph_url", &profile.subgraph_url),
+    ] {
+        reqwest::Url::parse(url).map_err(|_| {
+            AgentError::config(format!(
+                "Invalid {} {} in network {}",
+                label, url, profile.name
+            ))
+        })?;
+    }
+
+    Ok(())
+}
+
+// Looks the remix feed up on Lens so a wrong address fails at startup instead of on the
+// first remix. If Lens can't be reached the feed is kept and checked again when posting.
+pub async fn validate_remix_feed() -> Result<(), AgentError> {
+    let profile = network();
+    let Some(feed) = &profile.remix_feed else {
+        info!("No remix feed in network {}, remixes go to the global timeline", profile.name);
+        return Ok(());
+    };
+
+    match feed_info(feed).await {
+        Ok(info) => {
+            info!("Remix feed: {} ({})", feed, info.title);
+            Ok(())
+        }
+        Err(err) if !err.is_retryable() => Err(AgentError::config(format!(
+            "Remix feed {} in network {} is not usable: {}",
+            feed, profile.name, err
+        ))),
+        Err(err) => {
+            warn!("Could not check remix feed {}: {}", feed, err);
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DEPLOYED: &str = "0x5FbDB2315678afecb367f032d93F642f64180aa3";
+
+    fn load(
+        name: &str,
+        networks: &str,
+        extra: &[(&str, &str)],
+    ) -> Result<NetworkProfile, AgentError> {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("networks.toml");
+        std::fs::write(&file, networks).unwrap();
+
+        let mut env: HashMap<String, String> = extra
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        env.insert("AGENT_NETWORK".to_string(), name.to_string());
+        env.insert("AGENT_NETWORK_FILE".to_string(), file.display().to_string());
+
+        load_network(&env)
+    }
+
+    fn deployed(name: &str, address: &str) -> String {
+        format!(
+            r#"[{name}]
+chain_id = 37111
+subgraph_url = "http://127.0.0.1:8000/subgraphs/name/triplea"
+agents = "{address}"
+access_controls = "{address}"
+collection_manager = "{address}"
+market = "{address}"
+wgho = "{address}"
+bonsai = "{address}"
+mona = "{address}"
+"#
+        )
+    }
+
+    #[test]
+    fn mainnet_is_valid_on_its_own() {
+        assert_eq!(load("mainnet", "", &[]).unwrap().agents, AGENTS);
+        assert_eq!(load("MainNet", "", &[]).unwrap().name, "mainnet");
+    }
+
+    #[test]
+    fn other_networks_never_inherit_mainnet_deployments() {
+        let networks = r#"[testnet]
+chain_id = 37111
+rpc_url = "https://rpc.testnet.lens.xyz"
+"#;
+        let err = load("testnet", networks, &[]).unwrap_err();
+        assert!(err.to_string().contains("subgraph_url"), "{}", err);
+
+        let networks =
+            deployed("testnet", DEPLOYED).replace(&format!("mona = \"{}\"\n", DEPLOYED), "");
+        let err = load("testnet", &networks, &[]).unwrap_err();
+        assert!(err.to_string().contains("mona"), "{}", err);
+
+        let err = load("devnet", &deployed("testnet", DEPLOYED), &[]).unwrap_err();
+        assert!(err.to_string().contains("not found"), "{}", err);
+    }
+
+    #[test]
+    fn complete_profiles_keep_mainnet_endpoints() {
+        let testnet = load("testnet", &deployed("testnet", DEPLOYED), &[]).unwrap();
+
+        assert_eq!(testnet.chain_id, 37111);
+        assert_eq!(testnet.venice_api, mainnet().venice_api);
+        assert_ne!(testnet.agents, mainnet().agents);
+    }
+
+    #[test]
+    fn env_overrides_the_subgraph_and_remix_feed() {
+        let local = load(
+            "local",
+            &deployed("local", DEPLOYED),
+            &[("GRAPH_NODE_URL", "http://graph:8000/"), ("AGENT_REMIX_FEED", " ")],
+        )
+        .unwrap();
+
+        assert_eq!(local.subgraph_url, "http://graph:8000/");
+        assert_eq!(local.remix_feed, None);
+    }
+
+    #[test]
+    fn rejects_bad_addresses_and_remix_feeds() {
+        assert!(load("local", &deployed("local", "0x1234"), &[]).is_err());
+        let feed = [("AGENT_REMIX_FEED", "feed")];
+        assert!(load("local", &deployed("local", DEPLOYED), &feed).is_err());
+    }
+}
@@ -0,0 +1,181 @@
+use crate::utils::types::{AgentError, NonceState, RetryHttp};
+use ethers::{
+    middleware::{Middleware, SignerMiddleware},
+    providers::Provider,
+    signers::LocalWallet,
+    types::{Address, BlockNumber, U256},
+};
+use std::{collections::HashMap, error::Error, sync::Arc, sync::Mutex};
+use tracing::info;
+
+static NONCES: Mutex<Option<HashMap<Address, NonceState>>> = Mutex::new(None);
+
+fn with_state<T>(address: Address, f: impl FnOnce(&mut NonceState) -> T) -> T {
+    let mut nonces = NONCES.lock().unwrap_or_else(|e| e.into_inner());
+    f(nonces
+        .get_or_insert_with(HashMap::new)
+        .entry(address)
+        .or_default())
+}
+
+async fn chain_nonce(
+    client: &SignerMiddleware<Arc<Provider<RetryHttp>>, LocalWallet>,
+    address: Address,
+) -> Result<U256, AgentError> {
+    Ok(client
+        .get_transaction_count(address, Some(BlockNumber::Pending.into()))
+        .await?)
+}
+
+pub async fn acquire_nonce(
+    client: &SignerMiddleware<Arc<Provider<RetryHttp>>, LocalWallet>,
+) -> Result<U256, AgentError> {
+    let address = client.address();
+
+    if with_state(address, |state| state.next.is_none()) {
+        let pending = chain_nonce(client, address).await?;
+        with_state(address, |state| {
+            if state.next.is_none() {
+                state.next = Some(pending);
+            }
+        });
+    }
+
+    Ok(take_nonce(address))
+}
+
+// Released nonces are reused first so no gap is left behind.
+fn take_nonce(address: Address) -> U256 {
+    with_state(address, |state| {
+        if let Some(nonce) = state.released.pop_first() {
+            return nonce;
+        }
+
+        let nonce = state.next.unwrap_or_default();
+        state.next = Some(nonce + 1);
+        nonce
+    })
+}
+
+pub fn release_nonce(address: Address, nonce: U256) {
+    with_state(address, |state| match state.next {
+        Some(next) if next == nonce + 1 => {
+            state.next = Some(nonce);
+            while let Some(last) = state.released.last().cloned() {
+                if last + 1 != state.next.unwrap_or_default() {
+                    break;
+                }
+                state.released.remove(&last);
+                state.next = Some(last);
+            }
+        }
+        Some(next) if nonce < next => {
+            state.released.insert(nonce);
+        }
+        _ => {}
+    });
+}
+
+pub async fn resync_nonce(
+    client: &SignerMiddleware<Arc<Provider<RetryHttp>>, LocalWallet>,
+) -> Result<(), AgentError> {
+    let address = client.address();
+    let pending = chain_nonce(client, address).await?;
+
+    with_state(address, |state| {
+        state.released.retain(|nonce| *nonce >= pending);
+        if state.next.map(|next| next < pending).unwrap_or(true) {
+            state.next = Some(pending);
+        }
+    });
+
+    info!("Resynced nonce for {:?} at {}", address, pending);
+
+    Ok(())
+}
+
+pub fn is_nonce_too_low(err: &dyn Error) -> bool {
+    let message = err.to_string().to_lowercase();
+    message.contains("nonce too low") || message.contains("nonce is too low")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Each test uses its own address, the allocator state is shared by the process.
+    fn seeded(byte: u8, next: u64) -> Address {
+        let address = Address::repeat_byte(byte);
+        with_state(address, |state| state.next = Some(U256::from(next)));
+        address
+    }
+
+    fn take(address: Address) -> u64 {
+        take_nonce(address).as_u64()
+    }
+
+    #[test]
+    fn hands_out_nonces_in_order() {
+        let address = seeded(1, 5);
+        assert_eq!((take(address), take(address), take(address)), (5, 6, 7));
+    }
+
+    #[test]
+    fn releasing_the_last_nonce_rewinds() {
+        let address = seeded(2, 5);
+        take(address);
+        take(address);
+        release_nonce(address, U256::from(6));
+
+        assert_eq!(take(address), 6);
+        assert_eq!(take(address), 7);
+    }
+
+    #[test]
+    fn released_gaps_are_reused_first() {
+        let address = seeded(3, 5);
+        take(address);
+        take(address);
+        take(address);
+        release_nonce(address, U256::from(6));
+
+        assert_eq!(take(address), 6);
+        assert_eq!(take(address), 8);
+    }
+
+    #[test]
+    fn released_runs_collapse_into_next() {
+        let address = seeded(4, 5);
+        take(address);
+        take(address);
+        take(address);
+        release_nonce(address, U256::from(6));
+        release_nonce(address, U256::from(7));
+
+        with_state(address, |state| {
+            assert_eq!(state.next, Some(U256::from(6)));
+            assert!(state.released.is_empty());
+        });
+        assert_eq!(take(address), 6);
+    }
+
+    #[test]
+    fn ignores_nonces_never_handed_out() {
+        let address = seeded(5, 5);
+        take(address);
+        release_nonce(address, U256::from(9));
+
+        assert_eq!(take(address), 6);
+        release_nonce(Address::repeat_byte(6), U256::from(1));
+        with_state(Address::repeat_byte(6), |state| assert!(state.next.is_none()));
+    }
+
+    #[test]
+    fn detects_nonce_too_low() {
+        let low = std::io::Error::other("Nonce too low: next nonce 7, tx nonce 5");
+        let other = std::io::Error::other("insufficient funds");
+
+        assert!(is_nonce_too_low(&low));
+        assert!(!is_nonce_too_low(&other));
+    }
+}
@@ -0,0 +1,152 @@
+use crate::utils::types::{ChatMessage, MessageExample, Persona, TripleAAgent};
+use dotenv::var;
+use std::{collections::HashMap, sync::Mutex};
+use tracing::info;
+
+static PERSONAS: Mutex<Option<HashMap<u32, Persona>>> = Mutex::new(None);
+
+const DEFAULT_PERSONA_TOKENS: usize = 1000;
+
+pub fn persona_budget() -> usize {
+    var("AGENT_PERSONA_TOKENS")
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .unwrap_or(DEFAULT_PERSONA_TOKENS)
+}
+
+// Rough count, about four characters per token for English text.
+pub fn estimate_tokens(text: &str) -> usize {
+    text.chars().count().div_ceil(4)
+}
+
+fn truncate_tokens(text: &str, tokens: usize) -> String {
+    let text = text.trim();
+    if estimate_tokens(text) <= tokens {
+        return text.to_string();
+    }
+
+    let cut: String = text.chars().take(tokens * 4).collect();
+    match cut.rfind(char::is_whitespace) {
+        Some(index) => cut[..index].trim_end().to_string(),
+        None => cut,
+    }
+}
+
+fn speaker_role(speaker: &str, name: &str) -> &'static str {
+    let speaker = speaker.trim();
+    if speaker.eq_ignore_ascii_case(name.trim())
+        || !(speaker.starts_with("{{user") || speaker.eq_ignore_ascii_case("user"))
+    {
+        "assistant"
+    } else {
+        "user"
+    }
+}
+
+// A group becomes user/assistant turns, ending on the agent's reply so the
+// model sees a complete exchange. Groups without a reply are dropped.
+fn example_turns(group: &[MessageExample], name: &str) -> Vec<ChatMessage> {
+    let mut turns: Vec<ChatMessage> = Vec::new();
+
+    for example in group {
+        let text = example.content.text.trim();
+        if text.is_empty() {
+            continue;
+        }
+        let role = speaker_role(&example.user, name);
+        match turns.last_mut() {
+            Some(last) if last.role == role => {
+                last.content.push('\n');
+                last.content.push_str(text);
+            }
+            _ => turns.push(ChatMessage::new(role, text)),
+        }
+    }
+
+    while turns.last().is_some_and(|turn| turn.role != "assistant") {
+        turns.pop();
+    }
+    if turns.first().is_some_and(|turn| turn.role == "assistant") {
+        turns.insert(0, ChatMessage::new("user", "..."));
+    }
+
+    turns
+}
+
+pub fn compose_persona(agent: &TripleAAgent, budget: usize) -> Persona {
+    let fixed = format!(
+        "Name: {}\nCustom Instructions: {}\nStyle: {}\nAdjectives: {}",
+        agent.name, agent.custom_instructions, agent.style, agent.adjectives
+    );
+    let mut remaining = budget.saturating_sub(estimate_tokens(&fixed));
+
+    // Bio first, then lore and knowledge, each cut down to what the budget still allows.
+    let mut optional = Vec::new();
+    for text in [&agent.bio, &agent.lore, &agent.knowledge] {
+        let kept = truncate_tokens(text, remaining);
+        remaining = remaining.saturating_sub(estimate_tokens(&kept));
+        optional.push(kept);
+    }
+
+    let instructions = format!(
+        r#"
+Name: {}
+Bio: {}
+Custom Instructions: {}
+Lore: {}
+Knowledge: {}
+Style: {}
+Adjectives: {}
+"#,
+        agent.name,
+        optional[0],
+        agent.custom_instructions,
+        optional[1],
+        optional[2],
+        agent.style,
+        agent.adjectives
+    );
+
+    let mut examples = Vec::new();
+    for group in &agent.message_examples {
+        let turns = example_turns(group, &agent.name);
+        let tokens: usize = turns.iter().map(|turn| estimate_tokens(&turn.content)).sum();
+        if turns.is_empty() || tokens > remaining {
+            continue;
+        }
+        remaining -= tokens;
+        examples.extend(turns);
+    }
+
+    Persona {
+        instructions,
+        examples,
+    }
+}
+
+pub fn register_persona(agent: &TripleAAgent) {
+    let persona = compose_persona(agent, persona_budget());
+    info!(
+        agent_id = agent.id,
+        "Persona for {}: {} instruction tokens, {} example turns",
+        agent.name,
+        estimate_tokens(&persona.instructions),
+        persona.examples.len()
+    );
+
+    PERSONAS
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(agent.id, persona);
+}
+
+pub fn persona_examples(agent_id: u32) -> Vec<ChatMessage> {
+    PERSONAS
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|personas| personas.get(&agent_id))
+        .map(|persona| persona.examples.clone())
+        .unwrap_or_default()
+}
@@ -0,0 +1,255 @@
+use crate::utils::{
+    constants::{MODELS, SAMPLE_PROMPT},
+    types::{AgentError, PromptVars},
+};
+use dotenv::var;
+use regex::{Captures, Regex};
+use std::{
+    fs,
+    io::ErrorKind,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+use tracing::info;
+
+static PLACEHOLDER: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\{\s*([a-z_]+)\s*\}\}").unwrap());
+
+const TEMPLATES: &[(&str, &str)] = &[
+    ("chat_completion.system", include_str!("../../prompts/chat_completion.system.txt")),
+    ("chat_completion.user", include_str!("../../prompts/chat_completion.user.txt")),
+    ("receive_query.system", include_str!("../../prompts/receive_query.system.txt")),
+    ("receive_query.user", include_str!("../../prompts/receive_query.user.txt")),
+    ("comment_completion.system", include_str!("../../prompts/comment_completion.system.txt")),
+    ("comment_completion.user", include_str!("../../prompts/comment_completion.user.txt")),
+    ("feed_completion.system", include_str!("../../prompts/feed_completion.system.txt")),
+    ("feed_completion.user", include_str!("../../prompts/feed_completion.user.txt")),
+    ("prompt.system", include_str!("../../prompts/prompt.system.txt")),
+    ("prompt.user", include_str!("../../prompts/prompt.user.txt")),
+    ("image_details.system", include_str!("../../prompts/image_details.system.txt")),
+    ("image_details.user", include_str!("../../prompts/image_details.user.txt")),
+    ("drop_details.system", include_str!("../../prompts/drop_details.system.txt")),
+    ("drop_details.user", include_str!("../../prompts/drop_details.user.txt")),
+];
+
+impl PromptVars {
+    pub fn value(&self, name: &str) -> Option<String> {
+        let price = |index: usize, max: bool| {
+            self.price_ranges
+                .get(index)
+                .map(|(low, high)| if max { high } else { low }.to_string())
+                .unwrap_or_default()
+        };
+
+        Some(match name {
+            "collection_title" => self.collection_title.clone(),
+            "collection_description" => self.collection_description.clone(),
+            "custom_instructions" => self.custom_instructions.clone(),
+            "collection_instructions" => self.collection_instructions.clone(),
+            "max_tokens" => self.max_tokens.to_string(),
+            "post_content" => self.post_content.clone(),
+            "feed_title" => self.feed_title.clone(),
+            "feed_description" => self.feed_description.clone(),
+            "models" => format!("{:?}", MODELS),
+            "sample_prompt" => SAMPLE_PROMPT.to_string(),
+            "wgho_min" => price(0, false),
+            "wgho_max" => price(0, true),
+            "bonsai_min" => price(1, false),
+            "bonsai_max" => price(1, true),
+            "mona_min" => price(2, false),
+            "mona_max" => price(2, true),
+            "memory" => self.memory.clone(),
+            _ => return None,
+        })
+    }
+}
+
+pub fn prompts_dir() -> PathBuf {
+    PathBuf::from(var("AGENT_PROMPTS_DIR").unwrap_or_else(|_| "prompts".to_string()))
+}
+
+fn builtin(name: &str) -> Option<&'static str> {
+    TEMPLATES
+        .iter()
+        .find(|(template, _)| *template == name)
+        .map(|(_, text)| *text)
+}
+
+// Template files end with a newline that isn't part of the prompt.
+fn strip_newline(text: &str) -> String {
+    text.strip_suffix('\n').unwrap_or(text).to_string()
+}
+
+// The agent's own file wins over the shared one, which wins over the built-in prompt.
+fn load_template_in(dir: &Path, agent_id: u32, name: &str) -> Result<String, AgentError> {
+    let file = format!("{}.txt", name);
+
+    for path in [dir.join(format!("agent_{}", agent_id)).join(&file), dir.join(&file)] {
+        match fs::read_to_string(&path) {
+            Ok(text) => return Ok(strip_newline(&text)),
+            Err(err) if err.kind() == ErrorKind::NotFound => {}
+            Err(err) => {
+                return Err(AgentError::config(format!(
+                    "Failed to read prompt {:?}: {}",
+                    path, err
+                )))
+            }
+        }
+    }
+
+    builtin(name)
+        .map(strip_newline)
+        .ok_or_else(|| AgentError::config(format!("Unknown prompt template {}", name)))
+}
+
+pub fn render(agent_id: u32, name: &str, vars: &PromptVars) -> Result<String, AgentError> {
+    render_in(&prompts_dir(), agent_id, name, vars)
+}
+
+fn render_in(
+    dir: &Path,
+    agent_id: u32,
+    name: &str,
+    vars: &PromptVars,
+) -> Result<String, AgentError> {
+    let template = load_template_in(dir, agent_id, name)?;
+    let mut unknown = None;
+
+    let rendered = PLACEHOLDER.replace_all(&template, |caps: &Captures| {
+        vars.value(&caps[1]).unwrap_or_else(|| {
+            unknown = Some(caps[1].to_string());
+            String::new()
+        })
+    });
+
+    match unknown {
+        Some(variable) => Err(AgentError::config(format!(
+            "Unknown variable {} in prompt {}",
+            variable, name
+        ))),
+        None => Ok(rendered.into_owned()),
+    }
+}
+
+fn check_template(path: &PathBuf) -> Result<(), AgentError> {
+    let name = path
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or_default();
+    if builtin(name).is_none() {
+        return Err(AgentError::config(format!("Unknown prompt template {:?}", path)));
+    }
+
+    let text = fs::read_to_string(path)
+        .map_err(|e| AgentError::config(format!("Failed to read prompt {:?}: {}", path, e)))?;
+    for caps in PLACEHOLDER.captures_iter(&text) {
+        if PromptVars::default().value(&caps[1]).is_none() {
+            return Err(AgentError::config(format!(
+                "Unknown variable {} in prompt {:?}",
+                &caps[1], path
+            )));
+        }
+    }
+
+    Ok(())
+}
+
+pub fn validate_prompts() -> Result<(), AgentError> {
+    let dir = prompts_dir();
+    let Ok(entries) = fs::read_dir(&dir) else {
+        return Ok(());
+    };
+    let mut checked = 0;
+
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let is_agent_dir = path.is_dir()
+            && path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("agent_"));
+
+        let files: Vec<PathBuf> = if is_agent_dir {
+            fs::read_dir(&path)?.flatten().map(|entry| entry.path()).collect()
+        } else {
+            vec![path]
+        };
+
+        for file in files {
+            if file.extension().and_then(|ext| ext.to_str()) == Some("txt") {
+                check_template(&file)?;
+                checked += 1;
+            }
+        }
+    }
+
+    info!("Loaded {} prompt templates from {:?}", checked, dir);
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn prompts(test: &str) -> PathBuf {
+        let dir = std::env::temp_dir()
+            .join(format!("triplea_prompts_{}_{}", std::process::id(), test));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("agent_7")).unwrap();
+        dir
+    }
+
+    #[test]
+    fn falls_back_to_builtin() {
+        let dir = prompts("builtin");
+
+        assert_eq!(
+            load_template_in(&dir, 7, "prompt.system").unwrap(),
+            strip_newline(builtin("prompt.system").unwrap())
+        );
+        assert!(load_template_in(&dir, 7, "no_such.system").is_err());
+    }
+
+    #[test]
+    fn overrides_in_order() {
+        let dir = prompts("overrides");
+        fs::write(dir.join("prompt.system.txt"), "shared\n").unwrap();
+
+        assert_eq!(load_template_in(&dir, 7, "prompt.system").unwrap(), "shared");
+
+        fs::write(dir.join("agent_7").join("prompt.system.txt"), "agent seven\n").unwrap();
+        assert_eq!(load_template_in(&dir, 7, "prompt.system").unwrap(), "agent seven");
+        assert_eq!(load_template_in(&dir, 8, "prompt.system").unwrap(), "shared");
+    }
+
+    #[test]
+    fn renders_variables() {
+        let dir = prompts("render");
+        fs::write(
+            dir.join("agent_7").join("feed_completion.user.txt"),
+            "Write for {{ feed_title }} about {{collection_title}}.{{ memory }}",
+        )
+        .unwrap();
+        let vars = PromptVars {
+            feed_title: "Dial".to_string(),
+            collection_title: "Rain".to_string(),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            render_in(&dir, 7, "feed_completion.user", &vars).unwrap(),
+            "Write for Dial about Rain."
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_variables() {
+        let dir = prompts("unknown");
+        let path = dir.join("prompt.user.txt");
+        fs::write(&path, "Hello {{ nobody }}").unwrap();
+
+        assert!(render_in(&dir, 7, "prompt.user", &PromptVars::default()).is_err());
+        assert!(check_template(&path).is_err());
+        assert!(check_template(&dir.join("made_up.user.txt")).is_err());
+    }
+}
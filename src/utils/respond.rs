@@ -0,0 +1,105 @@
+use crate::utils::{
+    constants::REPLY_RETENTION_HOURS,
+    types::{AgentError, RespondSettings, RespondWorker, RosterRespond, RosterRespondWorker},
+};
+use dotenv::var;
+use ethers::types::U256;
+use std::{
+    collections::{BTreeMap, HashMap},
+    str::FromStr,
+};
+
+fn parse_env<T: FromStr>(name: &str, default: T) -> Result<T, AgentError> {
+    match var(name).ok().map(|value| value.trim().to_string()) {
+        Some(value) if !value.is_empty() => value
+            .parse()
+            .map_err(|_| AgentError::config(format!("Invalid {}: {}", name, value))),
+        _ => Ok(default),
+    }
+}
+
+fn lookback(hours: u64) -> Result<u64, AgentError> {
+    if hours > REPLY_RETENTION_HOURS {
+        return Err(AgentError::config(format!(
+            "Respond lookback of {} hours is longer than the {} hours answered notifications are kept",
+            hours, REPLY_RETENTION_HOURS
+        )));
+    }
+
+    Ok(hours * 3600)
+}
+
+fn collection_workers(
+    base: &RespondSettings,
+    entries: &HashMap<String, RosterRespondWorker>,
+) -> Result<BTreeMap<String, RespondWorker>, AgentError> {
+    let mut workers = BTreeMap::new();
+
+    for (collection_id, entry) in entries {
+        let id = U256::from_dec_str(collection_id.trim()).map_err(|_| {
+            AgentError::config(format!("Invalid respond collection id {}", collection_id))
+        })?;
+        workers.insert(
+            id.to_string(),
+            RespondWorker {
+                frequency: entry.frequency.unwrap_or(base.frequency),
+                max_replies: entry.max_replies.unwrap_or(base.max_replies),
+            },
+        );
+    }
+
+    Ok(workers)
+}
+
+pub fn respond_from_env() -> Result<RespondSettings, AgentError> {
+    let mut settings = RespondSettings {
+        frequency: parse_env("AGENT_RESPOND_FREQUENCY", 0)?,
+        max_replies: parse_env("AGENT_RESPOND_MAX_REPLIES", 3)?,
+        daily_limit: parse_env("AGENT_RESPOND_DAILY_LIMIT", 20)?,
+        lookback: lookback(parse_env("AGENT_RESPOND_LOOKBACK_HOURS", 48)?)?,
+        collections: BTreeMap::new(),
+    };
+
+    if let Ok(collections) = var("AGENT_RESPOND_COLLECTIONS") {
+        let entries: HashMap<String, RosterRespondWorker> = serde_json::from_str(&collections)
+            .map_err(|e| AgentError::config(format!("Invalid AGENT_RESPOND_COLLECTIONS: {}", e)))?;
+        settings.collections = collection_workers(&settings, &entries)?;
+    }
+
+    Ok(settings)
+}
+
+pub fn respond_from_entry(entry: Option<&RosterRespond>) -> Result<RespondSettings, AgentError> {
+    let mut respond = respond_from_env()?;
+
+    if let Some(entry) = entry {
+        if let Some(frequency) = entry.frequency {
+            respond.frequency = frequency;
+        }
+        if let Some(max_replies) = entry.max_replies {
+            respond.max_replies = max_replies;
+        }
+        if let Some(daily_limit) = entry.daily_limit {
+            respond.daily_limit = daily_limit;
+        }
+        if let Some(lookback_hours) = entry.lookback_hours {
+            respond.lookback = lookback(lookback_hours)?;
+        }
+        if !entry.collections.is_empty() {
+            respond.collections = collection_workers(&respond, &entry.collections)?;
+        }
+    }
+
+    Ok(respond)
+}
+
+pub fn respond_worker(settings: &RespondSettings, collection_id: U256) -> RespondWorker {
+    settings
+        .collections
+        .get(&collection_id.to_string())
+        .copied()
+        .unwrap_or(RespondWorker {
+            frequency: settings.frequency,
+            max_replies: settings.max_replies,
+        })
+}
@@ -0,0 +1,501 @@
+use crate::utils::{
+    metrics::{circuit_opened, upstream_retry},
+    types::{AgentError, Breaker, RetryHttp, RetryHttpError, RetryPolicy, Upstream},
+};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use dotenv::var;
+use ethers::providers::{Http, HttpClientError, JsonRpcClient, JsonRpcError, ProviderError, RpcError};
+use rand::{thread_rng, Rng};
+use reqwest::{header::RETRY_AFTER, RequestBuilder, Response, StatusCode};
+use serde::{de::DeserializeOwned, Serialize};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt::{self, Debug},
+    sync::{LazyLock, Mutex},
+    time::{Duration, Instant},
+};
+use tracing::{debug, warn};
+
+const UPSTREAMS: [Upstream; 8] = [
+    Upstream::Venice,
+    Upstream::Lens,
+    Upstream::Grove,
+    Upstream::Ipfs,
+    Upstream::Subgraph,
+    Upstream::Rpc,
+    Upstream::Llm,
+    Upstream::Image,
+];
+
+static POLICIES: LazyLock<HashMap<Upstream, RetryPolicy>> = LazyLock::new(|| {
+    UPSTREAMS
+        .iter()
+        .map(|upstream| (*upstream, load_policy(*upstream)))
+        .collect()
+});
+static BREAKERS: Mutex<Option<HashMap<Upstream, Breaker>>> = Mutex::new(None);
+
+impl Upstream {
+    pub fn as_str(&self) -> &str {
+        match self {
+            Upstream::Venice => "venice",
+            Upstream::Lens => "lens",
+            Upstream::Grove => "grove",
+            Upstream::Ipfs => "ipfs",
+            Upstream::Subgraph => "subgraph",
+            Upstream::Rpc => "rpc",
+            Upstream::Llm => "llm",
+            Upstream::Image => "image",
+        }
+    }
+}
+
+impl fmt::Display for Upstream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+fn default_policy(upstream: Upstream) -> RetryPolicy {
+    let (attempts, base_ms, max_secs, breaker_threshold, cooldown_secs) = match upstream {
+        Upstream::Venice => (4, 1000, 60, 5, 300),
+        Upstream::Lens => (4, 500, 10, 5, 60),
+        Upstream::Grove => (4, 500, 10, 5, 60),
+        Upstream::Ipfs => (4, 1000, 15, 5, 120),
+        Upstream::Subgraph => (4, 500, 10, 5, 60),
+        Upstream::Rpc => (5, 250, 8, 8, 30),
+        Upstream::Llm => (3, 1000, 30, 5, 120),
+        Upstream::Image => (3, 1000, 30, 5, 120),
+    };
+
+    RetryPolicy {
+        attempts,
+        base_delay: Duration::from_millis(base_ms),
+        max_delay: Duration::from_secs(max_secs),
+        breaker_threshold,
+        breaker_cooldown: Duration::from_secs(cooldown_secs),
+    }
+}
+
+fn load_policy(upstream: Upstream) -> RetryPolicy {
+    let mut policy = default_policy(upstream);
+    let name = upstream.as_str().to_uppercase();
+    let read = |key: String| var(&key).ok().and_then(|value| value.trim().parse::<u64>().ok());
+
+    if let Some(attempts) = read(format!("AGENT_RETRY_{}_ATTEMPTS", name)) {
+        policy.attempts = attempts.max(1) as u32;
+    }
+    if let Some(secs) = read(format!("AGENT_RETRY_{}_MAX_DELAY", name)) {
+        policy.max_delay = Duration::from_secs(secs);
+    }
+    if let Some(threshold) = read(format!("AGENT_BREAKER_{}_THRESHOLD", name)) {
+        policy.breaker_threshold = threshold.max(1) as u32;
+    }
+    if let Some(secs) = read(format!("AGENT_BREAKER_{}_COOLDOWN", name)) {
+        policy.breaker_cooldown = Duration::from_secs(secs);
+    }
+
+    policy
+}
+
+pub fn policy(upstream: Upstream) -> RetryPolicy {
+    POLICIES
+        .get(&upstream)
+        .cloned()
+        .unwrap_or_else(|| default_policy(upstream))
+}
+
+fn with_breaker<T>(upstream: Upstream, f: impl FnOnce(&mut Breaker) -> T) -> T {
+    let mut breakers = BREAKERS.lock().unwrap_or_else(|e| e.into_inner());
+    f(breakers
+        .get_or_insert_with(HashMap::new)
+        .entry(upstream)
+        .or_default())
+}
+
+pub fn check_breaker(upstream: Upstream) -> Result<(), AgentError> {
+    let now = Instant::now();
+    match with_breaker(upstream, |breaker| breaker.open_until) {
+        Some(until) if until > now => Err(AgentError::Unavailable {
+            upstream,
+            retry_in: until - now,
+        }),
+        _ => Ok(()),
+    }
+}
+
+fn record_success(upstream: Upstream) {
+    with_breaker(upstream, |breaker| {
+        breaker.failures = 0;
+        breaker.open_until = None;
+    });
+}
+
+fn record_failure(upstream: Upstream) {
+    let policy = policy(upstream);
+    let opened = with_breaker(upstream, |breaker| {
+        breaker.failures += 1;
+        if breaker.failures >= policy.breaker_threshold {
+            breaker.open_until = Some(Instant::now() + policy.breaker_cooldown);
+            true
+        } else {
+            false
+        }
+    });
+
+    if opened {
+        circuit_opened(upstream.as_str());
+        warn!(
+            upstream = upstream.as_str(),
+            "Circuit open for {:?} after repeated {} failures",
+            policy.breaker_cooldown,
+            upstream
+        );
+    }
+}
+
+fn backoff(policy: &RetryPolicy, attempt: u32) -> Duration {
+    let exponential = policy
+        .base_delay
+        .saturating_mul(2u32.saturating_pow(attempt.saturating_sub(1)))
+        .min(policy.max_delay);
+
+    exponential.mul_f64(thread_rng().gen_range(0.5..=1.0))
+}
+
+fn retry_after(response: &Response) -> Option<Duration> {
+    parse_retry_after(response.headers().get(RETRY_AFTER)?.to_str().ok()?)
+}
+
+fn parse_retry_after(value: &str) -> Option<Duration> {
+    let value = value.trim();
+
+    if let Ok(secs) = value.parse::<u64>() {
+        return Some(Duration::from_secs(secs));
+    }
+
+    DateTime::parse_from_rfc2822(value)
+        .ok()
+        .and_then(|at| (at.with_timezone(&Utc) - Utc::now()).to_std().ok())
+}
+
+fn is_transient_status(status: StatusCode) -> bool {
+    status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error()
+}
+
+fn is_transient(err: &reqwest::Error) -> bool {
+    err.is_timeout()
+        || err.is_connect()
+        || err.status().is_some_and(is_transient_status)
+}
+
+pub async fn send_with_retry(
+    upstream: Upstream,
+    build: impl Fn() -> RequestBuilder,
+) -> Result<Response, AgentError> {
+    let attempts = policy(upstream).attempts;
+    send_attempts(upstream, attempts, build).await
+}
+
+// For requests that are not safe to repeat, such as Lens mutations that create a post
+// or follow. A timeout may still have reached Lens, so resending could post twice.
+pub async fn send_once(
+    upstream: Upstream,
+    build: impl Fn() -> RequestBuilder,
+) -> Result<Response, AgentError> {
+    send_attempts(upstream, 1, build).await
+}
+
+async fn send_attempts(
+    upstream: Upstream,
+    attempts: u32,
+    build: impl Fn() -> RequestBuilder,
+) -> Result<Response, AgentError> {
+    check_breaker(upstream)?;
+    let policy = policy(upstream);
+    let mut attempt = 1;
+
+    loop {
+        let wait = match build().send().await {
+            Ok(response) if !is_transient_status(response.status()) => {
+                record_success(upstream);
+                return Ok(response);
+            }
+            Ok(response) => {
+                let wait = retry_after(&response).unwrap_or_else(|| backoff(&policy, attempt));
+                if attempt >= attempts || wait > policy.max_delay {
+                    record_failure(upstream);
+                    return Ok(response);
+                }
+                debug!(
+                    upstream = upstream.as_str(),
+                    attempt,
+                    "{} returned {}, retrying in {:?}",
+                    upstream,
+                    response.status(),
+                    wait
+                );
+                wait
+            }
+            Err(err) if is_transient(&err) && attempt < attempts => {
+                let wait = backoff(&policy, attempt);
+                debug!(
+                    upstream = upstream.as_str(),
+                    attempt,
+                    "{} request failed ({}), retrying in {:?}",
+                    upstream,
+                    err,
+                    wait
+                );
+                wait
+            }
+            Err(err) => {
+                if is_transient(&err) {
+                    record_failure(upstream);
+                }
+                return Err(err.into());
+            }
+        };
+
+        upstream_retry(upstream.as_str());
+        tokio::time::sleep(wait).await;
+        attempt += 1;
+    }
+}
+
+impl RetryHttp {
+    pub fn new(inner: Http) -> Self {
+        RetryHttp { inner }
+    }
+}
+
+fn is_transient_rpc(err: &HttpClientError) -> bool {
+    match err {
+        HttpClientError::ReqwestError(err) => {
+            err.is_timeout()
+                || err.is_connect()
+                || err
+                    .status()
+                    .is_some_and(|status| status.as_u16() == 429 || status.is_server_error())
+        }
+        HttpClientError::JsonRpcError(JsonRpcError { code, message, .. }) => {
+            let message = message.to_lowercase();
+            *code == -32005
+                || *code == 429
+                || message.contains("rate limit")
+                || message.contains("timeout")
+                || message.contains("header not found")
+        }
+        HttpClientError::SerdeJson { .. } => true,
+    }
+}
+
+#[async_trait]
+impl JsonRpcClient for RetryHttp {
+    type Error = RetryHttpError;
+
+    async fn request<T, R>(&self, method: &str, params: T) -> Result<R, RetryHttpError>
+    where
+        T: Debug + Serialize + Send + Sync,
+        R: DeserializeOwned + Send,
+    {
+        if let Err(AgentError::Unavailable { retry_in, .. }) = check_breaker(Upstream::Rpc) {
+            return Err(RetryHttpError::Unavailable(retry_in));
+        }
+
+        let policy = policy(Upstream::Rpc);
+        // Resending a raw transaction that already reached the mempool fails with
+        // "already known" or "nonce too low", so those are left to send_transaction.
+        let attempts = match method {
+            "eth_sendRawTransaction" | "eth_sendTransaction" => 1,
+            _ => policy.attempts,
+        };
+        let mut attempt = 1;
+
+        loop {
+            match self.inner.request(method, &params).await {
+                Ok(result) => {
+                    record_success(Upstream::Rpc);
+                    return Ok(result);
+                }
+                Err(err) if is_transient_rpc(&err) && attempt < attempts => {
+                    let wait = backoff(&policy, attempt);
+                    debug!(
+                        upstream = "rpc",
+                        attempt, "{} failed ({}), retrying in {:?}", method, err, wait
+                    );
+                    upstream_retry(Upstream::Rpc.as_str());
+                    tokio::time::sleep(wait).await;
+                    attempt += 1;
+                }
+                Err(err) => {
+                    if is_transient_rpc(&err) {
+                        record_failure(Upstream::Rpc);
+                    } else {
+                        record_success(Upstream::Rpc);
+                    }
+                    return Err(RetryHttpError::Http(err));
+                }
+            }
+        }
+    }
+}
+
+impl fmt::Display for RetryHttpError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RetryHttpError::Http(err) => write!(f, "{}", err),
+            RetryHttpError::Unavailable(retry_in) => {
+                write!(f, "RPC circuit open, retry in {:?}", retry_in)
+            }
+        }
+    }
+}
+
+impl Error for RetryHttpError {
+    fn source(&self) -> Option<&(dyn Error + 'static)> {
+        match self {
+            RetryHttpError::Http(err) => Some(err),
+            RetryHttpError::Unavailable(_) => None,
+        }
+    }
+}
+
+impl RpcError for RetryHttpError {
+    fn as_error_response(&self) -> Option<&JsonRpcError> {
+        match self {
+            RetryHttpError::Http(err) => err.as_error_response(),
+            RetryHttpError::Unavailable(_) => None,
+        }
+    }
+
+    fn as_serde_error(&self) -> Option<&serde_json::Error> {
+        match self {
+            RetryHttpError::Http(err) => err.as_serde_error(),
+            RetryHttpError::Unavailable(_) => None,
+        }
+    }
+}
+
+impl From<RetryHttpError> for ProviderError {
+    fn from(err: RetryHttpError) -> Self {
+        match err {
+            RetryHttpError::Http(err) => err.into(),
+            err => ProviderError::JsonRpcClientError(Box::new(err)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::{
+        atomic::{AtomicU32, Ordering},
+        Arc,
+    };
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::TcpListener,
+    };
+
+    // Answers every request with a 503 and counts them, so retries can be observed.
+    async fn unavailable_server() -> (String, Arc<AtomicU32>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let requests = Arc::new(AtomicU32::new(0));
+        let counter = requests.clone();
+
+        tokio::spawn(async move {
+            while let Ok((mut stream, _)) = listener.accept().await {
+                counter.fetch_add(1, Ordering::SeqCst);
+                let mut buffer = [0u8; 1024];
+                let _ = stream.read(&mut buffer).await;
+                let _ = stream
+                    .write_all(
+                        b"HTTP/1.1 503 Service Unavailable\r\nRetry-After: 0\r\n\
+                          Content-Length: 0\r\nConnection: close\r\n\r\n",
+                    )
+                    .await;
+            }
+        });
+
+        (url, requests)
+    }
+
+    #[test]
+    fn backoff_doubles_with_jitter_up_to_the_max_delay() {
+        let policy = default_policy(Upstream::Venice);
+
+        for _ in 0..50 {
+            let first = backoff(&policy, 1);
+            assert!(first >= policy.base_delay / 2 && first <= policy.base_delay);
+
+            let third = backoff(&policy, 3);
+            assert!(third >= policy.base_delay * 2 && third <= policy.base_delay * 4);
+
+            let late = backoff(&policy, 30);
+            assert!(late >= policy.max_delay / 2 && late <= policy.max_delay);
+        }
+    }
+
+    #[test]
+    fn retry_after_reads_seconds_and_dates() {
+        assert_eq!(parse_retry_after(" 7 "), Some(Duration::from_secs(7)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:00 GMT"), None);
+        assert_eq!(parse_retry_after("soon"), None);
+
+        let later = (Utc::now() + chrono::Duration::seconds(90)).to_rfc2822();
+        let wait = parse_retry_after(&later).unwrap();
+        assert!(wait > Duration::from_secs(80) && wait <= Duration::from_secs(90));
+    }
+
+    #[test]
+    fn breaker_opens_half_opens_and_closes() {
+        // Grove has no other test traffic, the breakers are shared by the process.
+        let upstream = Upstream::Grove;
+        let threshold = policy(upstream).breaker_threshold;
+
+        for _ in 1..threshold {
+            record_failure(upstream);
+        }
+        assert!(check_breaker(upstream).is_ok());
+
+        record_failure(upstream);
+        assert!(matches!(
+            check_breaker(upstream),
+            Err(AgentError::Unavailable { .. })
+        ));
+
+        // Once the cooldown is over a single request goes through, and another failure
+        // opens the breaker again straight away.
+        with_breaker(upstream, |breaker| breaker.open_until = Some(Instant::now()));
+        assert!(check_breaker(upstream).is_ok());
+        record_failure(upstream);
+        assert!(check_breaker(upstream).is_err());
+
+        with_breaker(upstream, |breaker| breaker.open_until = Some(Instant::now()));
+        record_success(upstream);
+        assert!(check_breaker(upstream).is_ok());
+        assert_eq!(with_breaker(upstream, |breaker| breaker.failures), 0);
+    }
+
+    #[tokio::test]
+    async fn send_once_never_repeats_a_request() {
+        let (url, requests) = unavailable_server().await;
+        let client = reqwest::Client::new();
+        let upstream = Upstream::Subgraph;
+
+        let response = send_once(upstream, || client.get(&url)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        requests.store(0, Ordering::SeqCst);
+        let response = send_with_retry(upstream, || client.get(&url)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(requests.load(Ordering::SeqCst), policy(upstream).attempts);
+
+        record_success(upstream);
+    }
+}
@@ -0,0 +1,285 @@
+use crate::utils::{
+    helpers::handle_agents,
+    lens::handle_lens_account,
+    image::{image_from_entry, image_from_env},
+    llm::{llm_from_entry, llm_from_env},
+    respond::{respond_from_entry, respond_from_env},
+    schedule::{
+        daily_schedule, parse_catch_up, parse_weekdays, parse_windows, schedule_from_env,
+        validate_schedule,
+    },
+    transactions::{gas_from_entry, gas_from_env},
+    types::{
+        AgentError, AgentManager, AgentSchedule, MessageExample, RosterEntry, RosterFile,
+        RosterSchedule, Text, TripleAAgent,
+    },
+};
+use chrono::Utc;
+use dotenv::var;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use serde_json::Value;
+use std::path::Path;
+use tracing::warn;
+
+pub async fn load_roster() -> Result<Vec<AgentManager>, AgentError> {
+    let runtime = var("AGENT_RUNTIME").unwrap_or_else(|_| "single".to_string());
+
+    let managers = match runtime.trim().to_lowercase().as_str() {
+        "single" => {
+            let agent = agent_from_env()?;
+            match AgentManager::new(&agent) {
+                Some(manager) => vec![manager],
+                None => {
+                    return Err(AgentError::config(format!(
+                        "Failed to create agent manager for agent_{}",
+                        agent.id
+                    )))
+                }
+            }
+        }
+        "subgraph" => {
+            let mut managers: Vec<AgentManager> = handle_agents().await?.into_values().collect();
+            managers.sort_by_key(|manager| manager.agent.id);
+            managers
+        }
+        "file" => {
+            let path = var("AGENT_ROSTER")
+                .map_err(|_| AgentError::config("AGENT_ROSTER not set for file runtime"))?;
+            let agents = agents_from_file(&path).await?;
+            let mut managers = vec![];
+
+            for agent in agents {
+                match AgentManager::new(&agent) {
+                    Some(manager) => managers.push(manager),
+                    None => warn!("Agent Not Added at id {}", agent.id),
+                }
+            }
+
+            managers
+        }
+        other => return Err(AgentError::config(format!("Unknown AGENT_RUNTIME: {}", other))),
+    };
+
+    if managers.is_empty() {
+        return Err(AgentError::config(format!("No agents loaded for runtime {}", runtime)));
+    }
+
+    Ok(managers)
+}
+
+fn required(name: &str) -> Result<String, AgentError> {
+    var(name).map_err(|_| AgentError::config(format!("{} not set", name)))
+}
+
+pub fn agent_from_env() -> Result<TripleAAgent, AgentError> {
+    let agent_id: u32 = required("AGENT_ID")?
+        .parse()
+        .map_err(|_| AgentError::config("AGENT_ID must be a number"))?;
+
+    let agent_name = required("AGENT_NAME")?;
+    let agent_bio = required("AGENT_BIO")?;
+    let agent_lore = required("AGENT_LORE")?;
+    let agent_adjectives = required("AGENT_ADJECTIVES")?;
+    let agent_style = required("AGENT_STYLE")?;
+    let agent_knowledge = required("AGENT_KNOWLEDGE")?;
+    let agent_model = var("AGENT_MODEL").unwrap_or_else(|_| "llama-3.3-70b".to_string());
+    let agent_cover = required("AGENT_COVER")?;
+    let agent_custom_instructions = var("AGENT_CUSTOM_INSTRUCTIONS")
+        .unwrap_or_else(|_| "Be creative and engaging".to_string());
+    let agent_wallet = required("AGENT_WALLET")?;
+    let agent_account_address = required("AGENT_ACCOUNT_ADDRESS")?;
+    let agent_private_key = required("AGENT_PRIVATE_KEY")?;
+
+    let agent_clock: u32 = var("AGENT_CLOCK")
+        .unwrap_or_else(|_| "7200".to_string())
+        .parse()
+        .map_err(|_| AgentError::config("AGENT_CLOCK must be a number"))?;
+
+    let agent_schedule = schedule_from_env(agent_clock)?;
+
+    let agent_feeds_str = var("AGENT_FEEDS").unwrap_or_else(|_| "[]".to_string());
+    let agent_feeds: Vec<String> = serde_json::from_str(&agent_feeds_str)
+        .unwrap_or_else(|_| Vec::new());
+    let agent_feed_frequency = feed_frequency_from_env()?;
+
+    let message_examples_str = var("AGENT_MESSAGE_EXAMPLES").unwrap_or_else(|_| "[]".to_string());
+    let message_examples_array: Vec<Vec<Value>> = serde_json::from_str(&message_examples_str)
+        .unwrap_or_else(|_| Vec::new());
+
+    std::env::set_var(format!("ID_{}", agent_id), &agent_private_key);
+
+    Ok(TripleAAgent {
+        id: agent_id,
+        name: agent_name,
+        bio: agent_bio,
+        lore: agent_lore,
+        adjectives: agent_adjectives,
+        style: agent_style,
+        knowledge: agent_knowledge,
+        message_examples: parse_message_examples(&message_examples_array),
+        model: agent_model,
+        cover: agent_cover,
+        custom_instructions: agent_custom_instructions,
+        wallet: agent_wallet,
+        clock: agent_clock,
+        last_active_time: Utc::now().timestamp() as u32,
+        account_address: agent_account_address,
+        feeds: agent_feeds,
+        feed_frequency: agent_feed_frequency,
+        schedule: agent_schedule,
+        gas: gas_from_env(),
+        llm: llm_from_env()?,
+        image: image_from_env()?,
+        respond: respond_from_env()?,
+    })
+}
+
+pub async fn agents_from_file(path: &str) -> Result<Vec<TripleAAgent>, AgentError> {
+    let contents = std::fs::read_to_string(path)
+        .map_err(|e| AgentError::config(format!("Failed to read roster {}: {}", path, e)))?;
+
+    let roster: RosterFile = match Path::new(path).extension().and_then(|e| e.to_str()) {
+        Some("toml") => toml::from_str(&contents)?,
+        _ => serde_json::from_str(&contents)?,
+    };
+
+    let mut agents: Vec<TripleAAgent> = vec![];
+
+    for entry in roster.agents {
+        if agents.iter().any(|agent| agent.id == entry.id) {
+            return Err(AgentError::config(format!("Duplicate agent id {} in roster", entry.id)));
+        }
+
+        let clocks: Vec<u32> = agents.iter().map(|agent| agent.clock).collect();
+        agents.push(agent_from_entry(entry, &clocks).await?);
+    }
+
+    Ok(agents)
+}
+
+async fn agent_from_entry(
+    entry: RosterEntry,
+    clocks: &[u32],
+) -> Result<TripleAAgent, AgentError> {
+    if let Some(private_key) = &entry.private_key {
+        std::env::set_var(format!("ID_{}", entry.id), private_key);
+    }
+
+    let clock = entry.clock.unwrap_or_else(|| assign_clock(clocks));
+
+    let schedule = match &entry.schedule {
+        Some(schedule) => schedule_from_entry(schedule, clock)?,
+        None => daily_schedule(clock),
+    };
+
+    let account_address = match entry.account_address {
+        Some(address) => address,
+        None => handle_lens_account(&entry.wallet, false)
+            .await
+            .unwrap_or_default(),
+    };
+
+    Ok(TripleAAgent {
+        id: entry.id,
+        name: entry.name,
+        bio: entry.bio,
+        lore: entry.lore,
+        adjectives: entry.adjectives,
+        style: entry.style,
+        knowledge: entry.knowledge,
+        message_examples: parse_message_examples(&entry.message_examples),
+        model: entry.model.unwrap_or_else(|| "llama-3.3-70b".to_string()),
+        cover: entry.cover,
+        custom_instructions: entry
+            .custom_instructions
+            .unwrap_or_else(|| "Be creative and engaging".to_string()),
+        wallet: entry.wallet,
+        clock,
+        last_active_time: Utc::now().timestamp() as u32,
+        account_address,
+        feeds: entry.feeds,
+        feed_frequency: match entry.feed_frequency {
+            Some(frequency) => frequency,
+            None => feed_frequency_from_env()?,
+        },
+        schedule,
+        gas: gas_from_entry(entry.gas.as_ref()),
+        llm: llm_from_entry(entry.llm.as_ref())?,
+        image: image_from_entry(entry.image.as_ref())?,
+        respond: respond_from_entry(entry.respond.as_ref())?,
+    })
+}
+
+fn schedule_from_entry(
+    entry: &RosterSchedule,
+    clock: u32,
+) -> Result<AgentSchedule, AgentError> {
+    let mut schedule = daily_schedule(clock);
+
+    schedule.cron = entry.cron.clone();
+    if !entry.windows.is_empty() {
+        schedule.windows = parse_windows(&entry.windows.join(","))?;
+    } else if !schedule.cron.is_empty() {
+        schedule.windows = vec![];
+    }
+    schedule.weekdays = parse_weekdays(&entry.weekdays.join(","))?;
+    if let Some(timezone) = &entry.timezone {
+        schedule.timezone = timezone.clone();
+    }
+    if let Some(catch_up) = &entry.catch_up {
+        schedule.catch_up = parse_catch_up(catch_up)?;
+    }
+    if let Some(grace) = entry.grace {
+        schedule.grace = grace;
+    }
+    if let Some(span) = entry.span {
+        schedule.span = span;
+    }
+
+    validate_schedule(&schedule)?;
+
+    Ok(schedule)
+}
+
+pub fn assign_clock(clocks: &[u32]) -> u32 {
+    let mut rng = StdRng::from_entropy();
+
+    loop {
+        let random_hour = rng.gen_range(0..5);
+        let random_minute = rng.gen_range(0..60);
+        let random_second = rng.gen_range(0..60);
+        let clock = random_hour * 3600 + random_minute * 60 + random_second;
+
+        if !clocks
+            .iter()
+            .any(|agent_clock| ((clock as i32) - (*agent_clock as i32)).abs() < 60)
+        {
+            return clock;
+        }
+    }
+}
+
+pub fn feed_frequency_from_env() -> Result<u32, AgentError> {
+    var("AGENT_FEED_FREQUENCY")
+        .unwrap_or_else(|_| "1".to_string())
+        .trim()
+        .parse()
+        .map_err(|_| AgentError::config("AGENT_FEED_FREQUENCY must be a number"))
+}
+
+pub fn parse_message_examples(groups: &[Vec<Value>]) -> Vec<Vec<MessageExample>> {
+    groups
+        .iter()
+        .map(|group| {
+            group
+                .iter()
+                .map(|msg| MessageExample {
+                    user: msg["user"].as_str().unwrap_or("").to_string(),
+                    content: Text {
+                        text: msg["content"]["text"].as_str().unwrap_or("").to_string(),
+                    },
+                })
+                .collect()
+        })
+        .collect()
+}
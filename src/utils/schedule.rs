@@ -0,0 +1,375 @@
+use crate::utils::types::{AgentError, AgentSchedule, CatchUpPolicy};
+use chrono::{DateTime, Datelike, Duration, LocalResult, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+use cron::Schedule;
+use dotenv::var;
+use std::str::FromStr;
+
+const LOOKAHEAD_DAYS: i64 = 8;
+
+pub fn daily_schedule(clock: u32) -> AgentSchedule {
+    AgentSchedule {
+        cron: vec![],
+        windows: vec![clock % 86400],
+        weekdays: vec![],
+        timezone: "UTC".to_string(),
+        catch_up: CatchUpPolicy::Skip,
+        grace: 500,
+        span: 7200,
+    }
+}
+
+pub fn schedule_from_env(clock: u32) -> Result<AgentSchedule, AgentError> {
+    let mut schedule = daily_schedule(clock);
+
+    if let Ok(cron) = var("AGENT_SCHEDULE") {
+        schedule.cron = cron
+            .split(';')
+            .map(|expr| expr.trim().to_string())
+            .filter(|expr| !expr.is_empty())
+            .collect();
+    }
+
+    if let Ok(windows) = var("AGENT_WINDOWS") {
+        schedule.windows = parse_windows(&windows)?;
+    } else if !schedule.cron.is_empty() {
+        schedule.windows = vec![];
+    }
+
+    if let Ok(weekdays) = var("AGENT_WEEKDAYS") {
+        schedule.weekdays = parse_weekdays(&weekdays)?;
+    }
+
+    if let Ok(timezone) = var("AGENT_TIMEZONE") {
+        schedule.timezone = timezone.trim().to_string();
+    }
+
+    if let Ok(catch_up) = var("AGENT_CATCH_UP") {
+        schedule.catch_up = parse_catch_up(&catch_up)?;
+    }
+
+    if let Ok(grace) = var("AGENT_CATCH_UP_GRACE") {
+        schedule.grace = grace.trim().parse()?;
+    }
+
+    if let Ok(span) = var("AGENT_ACTIVITY_SPAN") {
+        schedule.span = span.trim().parse()?;
+    }
+
+    validate_schedule(&schedule)?;
+
+    Ok(schedule)
+}
+
+pub fn parse_windows(input: &str) -> Result<Vec<u32>, AgentError> {
+    let mut windows = vec![];
+
+    for window in input.split(',').map(|w| w.trim()).filter(|w| !w.is_empty()) {
+        let seconds = if window.contains(':') {
+            let time = NaiveTime::parse_from_str(window, "%H:%M:%S")
+                .or_else(|_| NaiveTime::parse_from_str(window, "%H:%M"))
+                .map_err(|_| AgentError::config(format!("Invalid window time: {}", window)))?;
+            time.signed_duration_since(NaiveTime::MIN).num_seconds() as u32
+        } else {
+            window
+                .parse::<u32>()
+                .map_err(|_| AgentError::config(format!("Invalid window seconds: {}", window)))?
+        };
+
+        if seconds >= 86400 {
+            return Err(AgentError::config(format!("Window out of range: {}", window)));
+        }
+
+        windows.push(seconds);
+    }
+
+    windows.sort_unstable();
+    windows.dedup();
+
+    Ok(windows)
+}
+
+pub fn parse_weekdays(input: &str) -> Result<Vec<u32>, AgentError> {
+    let mut weekdays = vec![];
+
+    for day in input.split(',').map(|d| d.trim()).filter(|d| !d.is_empty()) {
+        let weekday = chrono::Weekday::from_str(day)
+            .map_err(|_| AgentError::config(format!("Invalid weekday: {}", day)))?;
+        weekdays.push(weekday.num_days_from_monday());
+    }
+
+    weekdays.sort_unstable();
+    weekdays.dedup();
+
+    Ok(weekdays)
+}
+
+pub fn parse_catch_up(input: &str) -> Result<CatchUpPolicy, AgentError> {
+    match input.trim().to_lowercase().as_str() {
+        "skip" => Ok(CatchUpPolicy::Skip),
+        "once" => Ok(CatchUpPolicy::Once),
+        "all" => Ok(CatchUpPolicy::All),
+        other => Err(AgentError::config(format!("Invalid catch up policy: {}", other))),
+    }
+}
+
+pub fn validate_schedule(schedule: &AgentSchedule) -> Result<(), AgentError> {
+    parse_timezone(&schedule.timezone)?;
+
+    for expr in &schedule.cron {
+        Schedule::from_str(expr)
+            .map_err(|e| AgentError::config(format!("Invalid cron `{}`: {}", expr, e)))?;
+    }
+
+    if schedule.cron.is_empty() && schedule.windows.is_empty() {
+        return Err(AgentError::config("Schedule needs at least one cron expression or window"));
+    }
+
+    Ok(())
+}
+
+fn parse_timezone(timezone: &str) -> Result<Tz, AgentError> {
+    Tz::from_str(timezone)
+        .map_err(|_| AgentError::config(format!("Invalid timezone: {}", timezone)))
+}
+
+fn weekday_allowed(schedule: &AgentSchedule, time: &DateTime<Tz>) -> bool {
+    schedule.weekdays.is_empty()
+        || schedule
+            .weekdays
+            .contains(&time.weekday().num_days_from_monday())
+}
+
+pub fn next_fire(schedule: &AgentSchedule, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let tz = parse_timezone(&schedule.timezone).ok()?;
+    let local_after = after.with_timezone(&tz);
+    let horizon = local_after + Duration::days(LOOKAHEAD_DAYS);
+    let mut candidates = vec![];
+
+    for expr in &schedule.cron {
+        if let Ok(cron) = Schedule::from_str(expr) {
+            if let Some(time) = cron
+                .after(&local_after)
+                .take_while(|time| *time <= horizon)
+                .find(|time| weekday_allowed(schedule, time))
+            {
+                candidates.push(time.with_timezone(&Utc));
+            }
+        }
+    }
+
+    let start_date = local_after.date_naive();
+    'days: for offset in 0..=LOOKAHEAD_DAYS {
+        let date = start_date + Duration::days(offset);
+
+        for window in &schedule.windows {
+            let naive = date.and_time(NaiveTime::MIN) + Duration::seconds(*window as i64);
+            let time = match tz.from_local_datetime(&naive) {
+                LocalResult::Single(time) => time,
+                LocalResult::Ambiguous(earliest, _) => earliest,
+                LocalResult::None => continue,
+            };
+
+            if time > local_after && weekday_allowed(schedule, &time) {
+                candidates.push(time.with_timezone(&Utc));
+                break 'days;
+            }
+        }
+    }
+
+    candidates.into_iter().min()
+}
+
+pub fn fires_between(
+    schedule: &AgentSchedule,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Vec<DateTime<Utc>> {
+    let mut fires = vec![];
+    let mut cursor = from;
+
+    while let Some(next) = next_fire(schedule, cursor) {
+        if next > to {
+            break;
+        }
+        fires.push(next);
+        cursor = next;
+    }
+
+    fires
+}
+
+pub fn due_runs(schedule: &AgentSchedule, fires: &[DateTime<Utc>], now: DateTime<Utc>) -> usize {
+    let on_time = fires
+        .iter()
+        .filter(|fire| (now - **fire).num_seconds() <= schedule.grace as i64)
+        .count();
+    let missed = fires.len() - on_time;
+
+    match schedule.catch_up {
+        CatchUpPolicy::Skip => on_time.min(1),
+        CatchUpPolicy::Once => (on_time + missed).min(1),
+        CatchUpPolicy::All => on_time + missed,
+    }
+}
+
+pub fn describe_schedule(schedule: &AgentSchedule) -> String {
+    let windows = schedule
+        .windows
+        .iter()
+        .map(|w| format!("{:02}:{:02}:{:02}", w / 3600, (w % 3600) / 60, w % 60))
+        .collect::<Vec<_>>();
+
+    format!(
+        "cron: {:?}, windows: {:?}, weekdays: {:?}, timezone: {}, catch up: {:?}",
+        schedule.cron, windows, schedule.weekdays, schedule.timezone, schedule.catch_up
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(time: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(time).unwrap().with_timezone(&Utc)
+    }
+
+    fn windows(windows: &str, timezone: &str) -> AgentSchedule {
+        AgentSchedule {
+            windows: parse_windows(windows).unwrap(),
+            timezone: timezone.to_string(),
+            ..daily_schedule(0)
+        }
+    }
+
+    #[test]
+    fn parses_windows() {
+        assert_eq!(parse_windows("14:30, 02:00,02:00:00,3600").unwrap(), vec![3600, 7200, 52200]);
+        assert!(parse_windows("25:00").is_err());
+        assert!(parse_windows("86400").is_err());
+        assert!(parse_windows("noon").is_err());
+    }
+
+    #[test]
+    fn parses_weekdays_and_catch_up() {
+        assert_eq!(parse_weekdays("Fri, mon,Wed,Mon").unwrap(), vec![0, 2, 4]);
+        assert!(parse_weekdays("Funday").is_err());
+        assert_eq!(parse_catch_up(" ALL ").unwrap(), CatchUpPolicy::All);
+        assert!(parse_catch_up("sometimes").is_err());
+    }
+
+    #[test]
+    fn validates_schedules() {
+        assert!(validate_schedule(&daily_schedule(0)).is_ok());
+        assert!(validate_schedule(&windows("", "UTC")).is_err());
+        assert!(validate_schedule(&windows("09:00", "Mars/Olympus")).is_err());
+
+        let mut schedule = windows("09:00", "UTC");
+        schedule.cron = vec!["not a cron".to_string()];
+        assert!(validate_schedule(&schedule).is_err());
+    }
+
+    #[test]
+    fn next_window_fire() {
+        let schedule = windows("09:00,21:00", "UTC");
+
+        assert_eq!(
+            next_fire(&schedule, at("2026-01-05T08:00:00Z")),
+            Some(at("2026-01-05T09:00:00Z"))
+        );
+        assert_eq!(
+            next_fire(&schedule, at("2026-01-05T09:00:00Z")),
+            Some(at("2026-01-05T21:00:00Z"))
+        );
+        assert_eq!(
+            next_fire(&schedule, at("2026-01-05T22:00:00Z")),
+            Some(at("2026-01-06T09:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn windows_follow_timezone_and_weekdays() {
+        let mut schedule = windows("09:00", "Europe/Berlin");
+        assert_eq!(
+            next_fire(&schedule, at("2026-01-05T00:00:00Z")),
+            Some(at("2026-01-05T08:00:00Z"))
+        );
+
+        // Mondays only, asked on a Tuesday.
+        schedule.weekdays = parse_weekdays("Mon").unwrap();
+        assert_eq!(
+            next_fire(&schedule, at("2026-01-06T00:00:00Z")),
+            Some(at("2026-01-12T08:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn windows_across_dst() {
+        // 02:30 doesn't exist on the day clocks spring forward, so that day is skipped.
+        let schedule = windows("02:30", "America/New_York");
+        assert_eq!(
+            next_fire(&schedule, at("2026-03-08T05:00:00Z")),
+            Some(at("2026-03-09T06:30:00Z"))
+        );
+
+        // 01:30 happens twice when clocks fall back, the first one fires.
+        let schedule = windows("01:30", "America/New_York");
+        assert_eq!(
+            next_fire(&schedule, at("2026-11-01T04:00:00Z")),
+            Some(at("2026-11-01T05:30:00Z"))
+        );
+    }
+
+    #[test]
+    fn cron_and_windows_pick_the_earliest() {
+        let mut schedule = windows("18:00", "UTC");
+        schedule.cron = vec!["0 0 12 * * *".to_string()];
+
+        assert_eq!(
+            next_fire(&schedule, at("2026-01-05T00:00:00Z")),
+            Some(at("2026-01-05T12:00:00Z"))
+        );
+        assert_eq!(
+            next_fire(&schedule, at("2026-01-05T12:00:00Z")),
+            Some(at("2026-01-05T18:00:00Z"))
+        );
+    }
+
+    #[test]
+    fn lists_fires_between() {
+        let schedule = windows("09:00,21:00", "UTC");
+        let fires = fires_between(&schedule, at("2026-01-05T00:00:00Z"), at("2026-01-06T21:00:00Z"));
+
+        assert_eq!(
+            fires,
+            vec![
+                at("2026-01-05T09:00:00Z"),
+                at("2026-01-05T21:00:00Z"),
+                at("2026-01-06T09:00:00Z"),
+                at("2026-01-06T21:00:00Z"),
+            ]
+        );
+        assert!(fires_between(&schedule, at("2026-01-05T10:00:00Z"), at("2026-01-05T20:00:00Z"))
+            .is_empty());
+    }
+
+    #[test]
+    fn catch_up_policies() {
+        let now = at("2026-01-05T12:00:00Z");
+        let on_time = at("2026-01-05T11:58:20Z");
+        let missed = vec![at("2026-01-05T09:00:00Z"), at("2026-01-05T10:00:00Z")];
+        let all = [missed.clone(), vec![on_time]].concat();
+
+        let mut schedule = daily_schedule(0);
+        assert_eq!(due_runs(&schedule, &all, now), 1);
+        assert_eq!(due_runs(&schedule, &missed, now), 0);
+
+        schedule.catch_up = CatchUpPolicy::Once;
+        assert_eq!(due_runs(&schedule, &missed, now), 1);
+        assert_eq!(due_runs(&schedule, &all, now), 1);
+
+        schedule.catch_up = CatchUpPolicy::All;
+        assert_eq!(due_runs(&schedule, &all, now), 3);
+        assert_eq!(due_runs(&schedule, &[], now), 0);
+    }
+}
@@ -0,0 +1,396 @@
+use crate::utils::{
+    constants::REPLY_RETENTION_HOURS,
+    dry_run::dry_run,
+    types::{
+        ActivityType, AgentActivity, AgentError, AgentStore, PlannedRun, SavedTokens, StoreRecord,
+        StoreState, TaskOutcome,
+    },
+};
+use chrono::{DateTime, Utc};
+use chrono_tz::Tz;
+use dotenv::var;
+use ethers::types::U256;
+use serde::{Deserialize, Deserializer};
+use serde_json::Value;
+use std::{
+    collections::HashSet,
+    fs::{self, OpenOptions},
+    io::{BufRead, BufReader, Write},
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::Mutex,
+};
+use tracing::{error, warn};
+
+static WRITE_LOCK: Mutex<()> = Mutex::new(());
+
+// Days follow the agent's schedule timezone, so a plan made late in the local evening
+// still belongs to that day after UTC midnight.
+pub fn today(timezone: &str) -> String {
+    day_of(Utc::now().timestamp(), timezone)
+}
+
+pub fn day_of(at: i64, timezone: &str) -> String {
+    let tz = Tz::from_str(timezone).unwrap_or(Tz::UTC);
+    DateTime::from_timestamp(at, 0)
+        .map(|at| at.with_timezone(&tz).format("%Y-%m-%d").to_string())
+        .unwrap_or_default()
+}
+
+// Every per-agent state file lives in AGENT_STATE_DIR, with dry runs kept apart so
+// they never touch the real journal, memory or analytics.
+pub fn state_path(agent_id: u32, extension: &str) -> Result<PathBuf, AgentError> {
+    let dir = PathBuf::from(var("AGENT_STATE_DIR").unwrap_or_else(|_| "state".to_string()));
+    fs::create_dir_all(&dir)?;
+
+    let file = if dry_run() {
+        format!("agent_{}.dry_run.{}", agent_id, extension)
+    } else {
+        format!("agent_{}.{}", agent_id, extension)
+    };
+
+    Ok(dir.join(file))
+}
+
+impl AgentStore {
+    pub fn open(agent_id: u32, timezone: &str) -> Result<Self, AgentError> {
+        let store = AgentStore {
+            agent_id,
+            path: state_path(agent_id, "jsonl")?,
+            timezone: timezone.to_string(),
+        };
+        store.compact()?;
+
+        Ok(store)
+    }
+
+    pub fn append(&self, record: &StoreRecord) -> Result<(), AgentError> {
+        let mut line = serde_json::to_string(record)?;
+        line.push('\n');
+
+        let _guard = WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let mut file = open_private(&self.path, true)?;
+        file.write_all(line.as_bytes())?;
+        file.flush()?;
+
+        Ok(())
+    }
+
+    pub fn day(&self) -> String {
+        today(&self.timezone)
+    }
+
+    pub fn record(&self, record: StoreRecord) {
+        if let Err(err) = self.append(&record) {
+            error!(
+                "Error writing state for agent_{} to {:?}: {:?}",
+                self.agent_id, self.path, err
+            );
+        }
+    }
+
+    pub fn save_tokens(&self, tokens: &SavedTokens) {
+        self.record(StoreRecord::Tokens {
+            tokens: tokens.clone(),
+        });
+    }
+
+    pub fn save_active(&self, last_active_time: u32) {
+        self.record(StoreRecord::Active {
+            at: Utc::now().timestamp(),
+            last_active_time,
+        });
+    }
+
+    pub fn save_plan(&self, run: i64, activity: &AgentActivity, tasks: &[ActivityType]) {
+        self.record(StoreRecord::Planned {
+            run,
+            day: self.day(),
+            activity: Box::new(activity.clone()),
+            tasks: tasks.to_vec(),
+        });
+    }
+
+    pub fn save_completed(
+        &self,
+        run: i64,
+        collection_id: U256,
+        index: usize,
+        task: &ActivityType,
+        result: &Result<Vec<String>, AgentError>,
+    ) {
+        self.record(StoreRecord::Completed {
+            run,
+            day: self.day(),
+            collection_id,
+            index,
+            task: task.clone(),
+            outcome: TaskOutcome::of(result),
+            error: result.as_ref().err().map(|err| err.to_string()),
+            hashes: result.as_ref().cloned().unwrap_or_default(),
+            at: Utc::now().timestamp(),
+        });
+    }
+
+    pub fn save_cancelled(&self, run: i64, collection_id: U256, index: usize, task: &ActivityType) {
+        self.record(StoreRecord::Cancelled {
+            run,
+            day: self.day(),
+            collection_id,
+            index,
+            task: task.clone(),
+            at: Utc::now().timestamp(),
+        });
+    }
+
+    pub fn save_skipped(&self, run: i64, collection_id: U256, index: usize, task: &ActivityType) {
+        self.record(StoreRecord::Skipped {
+            run,
+            day: self.day(),
+            collection_id,
+            index,
+            task: task.clone(),
+            at: Utc::now().timestamp(),
+        });
+    }
+
+    // Unlike the other records this one has to land: a reply that isn't recorded would be
+    // posted again by the next Respond task.
+    pub fn save_replied(&self, post_id: &str) -> Result<(), AgentError> {
+        self.append(&StoreRecord::Replied {
+            post_id: post_id.to_string(),
+            day: self.day(),
+            at: Utc::now().timestamp(),
+        })
+    }
+
+    pub fn records(&self) -> Result<Vec<StoreRecord>, AgentError> {
+        let file = match fs::File::open(&self.path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(err) => return Err(err.into()),
+        };
+
+        let mut records = vec![];
+        for (number, line) in BufReader::new(file).lines().enumerate() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            match serde_json::from_str::<StoreRecord>(&line) {
+                Ok(record) => records.push(record),
+                Err(err) => warn!(
+                    "Skipping corrupt state line {} in {:?}: {}",
+                    number + 1,
+                    self.path,
+                    err
+                ),
+            }
+        }
+
+        Ok(records)
+    }
+
+    pub fn load(&self) -> Result<StoreState, AgentError> {
+        let mut state = StoreState::default();
+        let today = self.day();
+
+        for record in self.records()? {
+            match record {
+                StoreRecord::Tokens { tokens } => state.tokens = Some(tokens),
+                StoreRecord::Active {
+                    last_active_time, ..
+                } => state.last_active_time = Some(last_active_time),
+                StoreRecord::Planned {
+                    run,
+                    day,
+                    activity,
+                    tasks,
+                } => {
+                    state.plans.retain(|plan| {
+                        !(plan.run == run && plan.activity.collection_id == activity.collection_id)
+                    });
+                    state.plans.push(PlannedRun {
+                        run,
+                        day,
+                        activity: *activity,
+                        tasks,
+                        completed: HashSet::new(),
+                    });
+                }
+                StoreRecord::Completed {
+                    outcome: TaskOutcome::Retryable,
+                    ..
+                } => {}
+                StoreRecord::Completed {
+                    run,
+                    collection_id,
+                    index,
+                    ..
+                }
+                | StoreRecord::Cancelled {
+                    run,
+                    collection_id,
+                    index,
+                    ..
+                }
+                | StoreRecord::Skipped {
+                    run,
+                    collection_id,
+                    index,
+                    ..
+                } => {
+                    if let Some(plan) = state.plans.iter_mut().find(|plan| {
+                        plan.run == run && plan.activity.collection_id == collection_id
+                    }) {
+                        plan.completed.insert(index);
+                    }
+                }
+                StoreRecord::Replied { post_id, day, .. } => {
+                    if day == today {
+                        state.replied_today += 1;
+                    }
+                    state.replied.insert(post_id);
+                }
+            }
+        }
+
+        Ok(state)
+    }
+
+    pub fn pending_plans(&self) -> Vec<PlannedRun> {
+        let day = self.day();
+
+        match self.load() {
+            Ok(state) => state
+                .plans
+                .into_iter()
+                .filter(|plan| plan.day == day && plan.completed.len() < plan.tasks.len())
+                .collect(),
+            Err(err) => {
+                error!("Error loading state for agent_{}: {:?}", self.agent_id, err);
+                vec![]
+            }
+        }
+    }
+
+    fn compact(&self) -> Result<(), AgentError> {
+        let records = self.records()?;
+        if records.is_empty() {
+            return Ok(());
+        }
+
+        let day = self.day();
+        let retention = Utc::now().timestamp() - (REPLY_RETENTION_HOURS * 3600) as i64;
+        let mut tokens = None;
+        let mut active = None;
+        let mut kept = vec![];
+
+        for record in records {
+            match &record {
+                StoreRecord::Tokens { .. } => tokens = Some(record),
+                StoreRecord::Active { .. } => active = Some(record),
+                StoreRecord::Planned { day: plan_day, .. }
+                | StoreRecord::Completed { day: plan_day, .. }
+                | StoreRecord::Cancelled { day: plan_day, .. }
+                | StoreRecord::Skipped { day: plan_day, .. } => {
+                    if *plan_day == day {
+                        kept.push(record);
+                    }
+                }
+                StoreRecord::Replied { at, .. } => {
+                    if *at >= retention {
+                        kept.push(record);
+                    }
+                }
+            }
+        }
+
+        let mut contents = String::new();
+        for record in tokens.iter().chain(active.iter()).chain(kept.iter()) {
+            contents.push_str(&serde_json::to_string(record)?);
+            contents.push('\n');
+        }
+
+        let _guard = WRITE_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let tmp = self.path.with_extension("jsonl.tmp");
+        let mut file = open_private(&tmp, false)?;
+        file.write_all(contents.as_bytes())?;
+        file.flush()?;
+        fs::rename(&tmp, &self.path)?;
+
+        Ok(())
+    }
+}
+
+pub fn open_private(path: &Path, append: bool) -> std::io::Result<fs::File> {
+    let mut options = OpenOptions::new();
+    options.create(true);
+    if append {
+        options.append(true);
+    } else {
+        options.write(true).truncate(true);
+    }
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+
+    options.open(path)
+}
+
+pub fn task_outcome<'de, D: Deserializer<'de>>(deserializer: D) -> Result<TaskOutcome, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::Bool(true) => Ok(TaskOutcome::Succeeded),
+        Value::Bool(false) => Ok(TaskOutcome::Permanent),
+        other => serde_json::from_value(other).map_err(serde::de::Error::custom),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn days_follow_the_schedule_timezone() {
+        // 2026-01-06 03:30 UTC is still the evening of the 5th in New York.
+        let at = 1767670200;
+
+        assert_eq!(day_of(at, "UTC"), "2026-01-06");
+        assert_eq!(day_of(at, "America/New_York"), "2026-01-05");
+        assert_eq!(day_of(at, "Asia/Tokyo"), "2026-01-06");
+        assert_eq!(day_of(at, "not a zone"), "2026-01-06");
+    }
+
+    #[test]
+    fn replies_count_today_and_expire_after_retention() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = AgentStore {
+            agent_id: 0,
+            path: dir.path().join("agent_0.jsonl"),
+            timezone: "UTC".to_string(),
+        };
+        let expired = Utc::now().timestamp() - (REPLY_RETENTION_HOURS * 3600) as i64 - 60;
+
+        store
+            .append(&StoreRecord::Replied {
+                post_id: "old".to_string(),
+                day: day_of(expired, "UTC"),
+                at: expired,
+            })
+            .unwrap();
+        store.save_replied("new").unwrap();
+
+        let state = store.load().unwrap();
+        assert_eq!(state.replied_today, 1);
+        assert!(state.replied.contains("old") && state.replied.contains("new"));
+
+        store.compact().unwrap();
+        let state = store.load().unwrap();
+        assert_eq!(state.replied_today, 1);
+        assert!(!state.replied.contains("old") && state.replied.contains("new"));
+    }
+}
@@ -0,0 +1,319 @@
+use crate::utils::{
+    network::network,
+    dry_run::send,
+    types::{
+        AgentError, GraphQLResponse, PriceCollection, SubgraphAgent, SubgraphAgentWorkers,
+        SubgraphClient, SubgraphError, SubgraphRemix, SubgraphTokenDetails, Upstream,
+    },
+};
+use dotenv::{from_filename, var};
+use ethers::types::U256;
+use reqwest::Client;
+use serde::{de::DeserializeOwned, Deserialize, Deserializer};
+use serde_json::{json, Map, Value};
+use std::{
+    collections::HashMap,
+    error::Error,
+    fmt,
+    sync::{Mutex, Once},
+    time::Duration,
+};
+
+static INIT: Once = Once::new();
+static CLIENT: Mutex<Option<SubgraphClient>> = Mutex::new(None);
+
+pub fn subgraph() -> SubgraphClient {
+    INIT.call_once(|| {
+        from_filename(".env").ok();
+        let client = SubgraphClient {
+            client: Client::new(),
+            url: network().subgraph_url.clone(),
+            timeout: Duration::from_secs(
+                var("AGENT_SUBGRAPH_TIMEOUT_SECS")
+                    .ok()
+                    .and_then(|value| value.trim().parse().ok())
+                    .unwrap_or(60),
+            ),
+            page_size: var("AGENT_SUBGRAPH_PAGE_SIZE")
+                .ok()
+                .and_then(|value| value.trim().parse().ok())
+                .filter(|size| *size > 0 && *size <= 1000)
+                .unwrap_or(100),
+        };
+
+        *CLIENT.lock().unwrap() = Some(client);
+    });
+
+    CLIENT.lock().unwrap().clone().unwrap()
+}
+
+impl fmt::Display for SubgraphError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Subgraph {} failed: {}", self.field, self.messages.join("; "))
+    }
+}
+
+impl Error for SubgraphError {}
+
+impl SubgraphClient {
+    pub async fn query<T: DeserializeOwned>(
+        &self,
+        field: &str,
+        query: &str,
+        variables: Value,
+    ) -> Result<T, AgentError> {
+        let body = json!({
+            "query": query,
+            "variables": variables,
+        });
+        let response = send(
+            Upstream::Subgraph,
+            &format!("subgraph/{}", field),
+            &body.to_string(),
+            || self.client.post(&self.url).timeout(self.timeout).json(&body),
+        )
+        .await?;
+
+        let status = response.status();
+        if !status.is_success() {
+            return Err(AgentError::Subgraph(SubgraphError {
+                field: field.to_string(),
+                messages: vec![format!("HTTP {}: {}", status, response.text().await?)],
+            }));
+        }
+
+        let parsed: GraphQLResponse<T> = response.json().await?;
+
+        if !parsed.errors.is_empty() {
+            return Err(AgentError::Subgraph(SubgraphError {
+                field: field.to_string(),
+                messages: parsed.errors.into_iter().map(|err| err.message).collect(),
+            }));
+        }
+
+        parsed.data.ok_or_else(|| {
+            AgentError::Subgraph(SubgraphError {
+                field: field.to_string(),
+                messages: vec!["response had no data".to_string()],
+            })
+        })
+    }
+
+    pub async fn query_all<T: DeserializeOwned>(
+        &self,
+        field: &str,
+        query: &str,
+        variables: Value,
+    ) -> Result<Vec<T>, AgentError> {
+        let mut items = vec![];
+        let mut skip = 0;
+
+        loop {
+            let mut page_variables = match &variables {
+                Value::Object(map) => map.clone(),
+                _ => Map::new(),
+            };
+            page_variables.insert("first".to_string(), json!(self.page_size));
+            page_variables.insert("skip".to_string(), json!(skip));
+
+            let mut data: HashMap<String, Vec<T>> = self
+                .query(field, query, Value::Object(page_variables))
+                .await?;
+            let page = data.remove(field).unwrap_or_default();
+            let count = page.len();
+            items.extend(page);
+
+            if count < self.page_size as usize {
+                break;
+            }
+            skip += self.page_size;
+        }
+
+        Ok(items)
+    }
+}
+
+pub fn decimal<'de, D: Deserializer<'de>>(deserializer: D) -> Result<U256, D::Error> {
+    match Value::deserialize(deserializer)? {
+        Value::String(value) => U256::from_dec_str(&value).map_err(serde::de::Error::custom),
+        Value::Number(value) => value
+            .as_u64()
+            .map(U256::from)
+            .ok_or_else(|| serde::de::Error::custom(format!("Invalid number {}", value))),
+        Value::Null => Ok(U256::zero()),
+        other => Err(serde::de::Error::custom(format!("Invalid decimal {}", other))),
+    }
+}
+
+pub async fn agents() -> Result<Vec<SubgraphAgent>, AgentError> {
+    subgraph()
+        .query_all(
+            "agentCreateds",
+            r#"
+            query($first: Int!, $skip: Int!) {
+                agentCreateds(first: $first, skip: $skip, orderBy: id) {
+                    wallets
+                    SkyhuntersAgentManager_id
+                    uri
+                    metadata {
+                        title
+                        bio
+                        lore
+                        adjectives
+                        style
+                        knowledge
+                        messageExamples
+                        model
+                        cover
+                        customInstructions
+                        feeds
+                    }
+                }
+            }
+            "#,
+            json!({}),
+        )
+        .await
+}
+
+pub async fn agent_workers(
+    agent_id: u32,
+) -> Result<Option<SubgraphAgentWorkers>, AgentError> {
+    let mut data: HashMap<String, Vec<SubgraphAgentWorkers>> = subgraph()
+        .query(
+            "agentCreateds",
+            r#"
+            query($SkyhuntersAgentManager_id: Int!) {
+                agentCreateds(where: { SkyhuntersAgentManager_id: $SkyhuntersAgentManager_id }, first: 1) {
+                    balances {
+                        rentBalance
+                        bonusBalance
+                        collectionId
+                    }
+                    workers {
+                        publish
+                        remix
+                        lead
+                        mint
+                        leadFrequency
+                        publishFrequency
+                        mintFrequency
+                        remixFrequency
+                        instructions
+                        collectionId
+                        collection {
+                            artist
+                            uri
+                            metadata {
+                                description
+                                title
+                                image
+                            }
+                            prices {
+                                price
+                                token
+                            }
+                        }
+                    }
+                }
+            }
+            "#,
+            json!({ "SkyhuntersAgentManager_id": agent_id }),
+        )
+        .await?;
+
+    Ok(data
+        .remove("agentCreateds")
+        .and_then(|agents| agents.into_iter().next()))
+}
+
+pub async fn token_details() -> Result<Vec<SubgraphTokenDetails>, AgentError> {
+    subgraph()
+        .query_all(
+            "tokenDetailsSets",
+            r#"
+            query($first: Int!, $skip: Int!) {
+                tokenDetailsSets(first: $first, skip: $skip) {
+                    threshold
+                    token
+                    base
+                }
+            }
+            "#,
+            json!({}),
+        )
+        .await
+}
+
+pub async fn agent_remix(
+    agent_id: u32,
+    remix_id: U256,
+) -> Result<Option<SubgraphRemix>, AgentError> {
+    let mut data: HashMap<String, Vec<SubgraphRemix>> = subgraph()
+        .query(
+            "agentRemixes",
+            r#"
+            query($TripleAAgents_id: Int!, $remixId: BigInt!) {
+                agentRemixes(first: 1, where: { TripleAAgents_id: $TripleAAgents_id, remixId: $remixId }) {
+                    dropId
+                }
+            }
+            "#,
+            json!({
+                "TripleAAgents_id": agent_id,
+                "remixId": remix_id.to_string(),
+            }),
+        )
+        .await?;
+
+    Ok(data
+        .remove("agentRemixes")
+        .and_then(|remixes| remixes.into_iter().next()))
+}
+
+pub async fn collection_prices(
+    token: &str,
+    artist: &str,
+    max_price: U256,
+) -> Result<Vec<PriceCollection>, AgentError> {
+    subgraph()
+        .query_all(
+            "collectionPrices",
+            r#"
+            query($token: String!, $artist: String!, $soldOut: Boolean!, $maxPrice: BigInt!, $first: Int!, $skip: Int!) {
+                collectionPrices(where: { token: $token, artist: $artist, soldOut: $soldOut, price_lte: $maxPrice }, first: $first, skip: $skip) {
+                    collectionId
+                    amount
+                    amountSold
+                }
+            }
+            "#,
+            json!({
+                "token": token,
+                "artist": artist,
+                "soldOut": false,
+                "maxPrice": max_price.to_string(),
+            }),
+        )
+        .await
+}
+
+pub async fn collection_sales(collection_ids: &[U256]) -> Result<Vec<PriceCollection>, AgentError> {
+    let collection_ids: Vec<String> = collection_ids.iter().map(|id| id.to_string()).collect();
+
+    subgraph()
+        .query_all(
+            "collectionPrices",
+            r#"
+            query($collectionIds: [BigInt!]!, $first: Int!, $skip: Int!) {
+                collectionPrices(where: { collectionId_in: $collectionIds }, first: $first, skip: $skip) {
+                    collectionId
+                    amount
+                    amountSold
+                }
+            }
+            "#,
+            json!({ "collectionIds": collection_ids }),
+        )
+        .await
+}
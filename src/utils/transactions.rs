@@ -0,0 +1,467 @@
+use crate::utils::{
+    network::network,
+    dry_run::{dry_run, report_transaction},
+    metrics::gas_spent,
+    nonce::{acquire_nonce, is_nonce_too_low, release_nonce, resync_nonce},
+    types::{AgentError, GasHints, GasSettings, RetryHttp, RosterGas, SentTransaction},
+};
+use dotenv::var;
+use ethers::{
+    middleware::{Middleware, SignerMiddleware},
+    providers::{PendingTransaction, Provider},
+    signers::{LocalWallet, Signer},
+    types::{
+        transaction::eip2718::TypedTransaction, Address, BlockNumber, Bytes,
+        Eip1559TransactionRequest, TransactionReceipt, H256, U256, U64,
+    },
+};
+use std::{collections::HashMap, sync::{Arc, Mutex}, time::Duration};
+use tracing::{error, info, warn};
+
+const GWEI: u64 = 1_000_000_000;
+const MIN_BUMP_PERCENT: u64 = 10;
+const MAX_NONCE_RESYNCS: u32 = 3;
+
+static GAS_SETTINGS: Mutex<Option<HashMap<u32, GasSettings>>> = Mutex::new(None);
+
+pub fn gas_from_env() -> GasSettings {
+    let number = |name: &str, default: u64| -> u64 {
+        var(name)
+            .ok()
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(default)
+    };
+
+    GasSettings {
+        multiplier: var("AGENT_GAS_MULTIPLIER")
+            .ok()
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(1.2),
+        max_fee: U256::from(number("AGENT_MAX_FEE_GWEI", 525)) * GWEI,
+        max_priority_fee: U256::from(number("AGENT_MAX_PRIORITY_FEE_GWEI", 25)) * GWEI,
+        fallback_gas: U256::from(number("AGENT_FALLBACK_GAS_LIMIT", 300_000)),
+        history_blocks: number("AGENT_FEE_HISTORY_BLOCKS", 10),
+        reward_percentile: var("AGENT_FEE_PERCENTILE")
+            .ok()
+            .and_then(|value| value.trim().parse().ok())
+            .unwrap_or(50.0),
+        stuck_after: number("AGENT_TX_STUCK_SECS", 120),
+        bump_percent: number("AGENT_TX_BUMP_PERCENT", 15).max(MIN_BUMP_PERCENT),
+        max_replacements: number("AGENT_TX_MAX_REPLACEMENTS", 3) as u32,
+    }
+}
+
+pub fn gas_from_entry(entry: Option<&RosterGas>) -> GasSettings {
+    let mut gas = gas_from_env();
+
+    if let Some(entry) = entry {
+        if let Some(max_fee) = entry.max_fee_gwei {
+            gas.max_fee = U256::from(max_fee) * GWEI;
+        }
+        if let Some(max_priority_fee) = entry.max_priority_fee_gwei {
+            gas.max_priority_fee = U256::from(max_priority_fee) * GWEI;
+        }
+        if let Some(multiplier) = entry.multiplier {
+            gas.multiplier = multiplier;
+        }
+    }
+
+    gas
+}
+
+pub fn register_gas_settings(agent_id: u32, gas: GasSettings) {
+    GAS_SETTINGS
+        .lock()
+        .unwrap()
+        .get_or_insert_with(HashMap::new)
+        .insert(agent_id, gas);
+}
+
+pub fn gas_settings(agent_id: u32) -> GasSettings {
+    GAS_SETTINGS
+        .lock()
+        .unwrap()
+        .as_ref()
+        .and_then(|settings| settings.get(&agent_id).cloned())
+        .unwrap_or_else(gas_from_env)
+}
+
+pub fn signer_client(
+    provider: Arc<Provider<RetryHttp>>,
+    wallet: LocalWallet,
+) -> SignerMiddleware<Arc<Provider<RetryHttp>>, LocalWallet> {
+    let chain_id = network().chain_id;
+    SignerMiddleware::new(provider, wallet.with_chain_id(chain_id))
+}
+
+pub async fn send_transaction(
+    client: &SignerMiddleware<Arc<Provider<RetryHttp>>, LocalWallet>,
+    agent_id: u32,
+    method: &str,
+    to: Address,
+    data: Option<Bytes>,
+    value: Option<U256>,
+) -> Result<SentTransaction, AgentError> {
+    send_hinted_transaction(client, agent_id, method, to, data, value, &GasHints::default()).await
+}
+
+// Transactions prepared by someone else, like Lens sponsored requests, come with their own
+// gas limit and fees. The limit is a floor for our estimate and the fees replace ours,
+// both still held to the agent's caps.
+pub async fn send_hinted_transaction(
+    client: &SignerMiddleware<Arc<Provider<RetryHttp>>, LocalWallet>,
+    agent_id: u32,
+    method: &str,
+    to: Address,
+    data: Option<Bytes>,
+    value: Option<U256>,
+    hints: &GasHints,
+) -> Result<SentTransaction, AgentError> {
+    let settings = gas_settings(agent_id);
+    let chain_id = network().chain_id;
+
+    let mut req = Eip1559TransactionRequest {
+        from: Some(client.address()),
+        to: Some(to.into()),
+        value,
+        data,
+        chain_id: Some(chain_id.into()),
+        ..Default::default()
+    };
+
+    if dry_run() {
+        let hash = report_transaction(agent_id, method, &format!("{:?}", to), &req);
+        return Ok(SentTransaction {
+            hash,
+            receipt: None,
+        });
+    }
+
+    let estimated = estimate_gas(client, &req, &settings).await;
+    req.gas = Some(hints.gas_limit.map_or(estimated, |limit| estimated.max(limit)));
+    let (max_fee, max_priority_fee) =
+        hinted_fees(estimate_fees(client, &settings).await, hints, &settings);
+    req.max_fee_per_gas = Some(max_fee);
+    req.max_priority_fee_per_gas = Some(max_priority_fee);
+    req.nonce = Some(acquire_nonce(client).await?);
+
+    info!(
+        "Sending {} for agent_{}: gas {:?}, max fee {:?}, tip {:?}, nonce {:?}",
+        method, agent_id, req.gas, req.max_fee_per_gas, req.max_priority_fee_per_gas, req.nonce
+    );
+
+    let mut hashes: Vec<H256> = vec![];
+    let mut replacements = 0;
+    let mut resyncs = 0;
+
+    loop {
+        let pending = match client
+            .send_transaction(TypedTransaction::Eip1559(req.clone()), None)
+            .await
+        {
+            Ok(pending) => pending,
+            Err(err) => {
+                if let Some(receipt) = find_receipt(client, &hashes).await {
+                    return confirmed(agent_id, method, receipt);
+                }
+
+                if is_nonce_too_low(&err) {
+                    if let Some(hash) = hashes.last() {
+                        return wait_for(client, agent_id, *hash, method, req.nonce).await;
+                    }
+
+                    if resyncs < MAX_NONCE_RESYNCS {
+                        resyncs += 1;
+                        resync_nonce(client).await?;
+                        req.nonce = Some(acquire_nonce(client).await?);
+                        info!("Retrying {} with nonce {:?}", method, req.nonce);
+                        continue;
+                    }
+                }
+
+                if hashes.is_empty() {
+                    if let Some(nonce) = req.nonce {
+                        release_nonce(client.address(), nonce);
+                    }
+                }
+
+                error!("Error sending the transaction for {}: {:?}", method, err);
+                return Err(err.into());
+            }
+        };
+
+        let hash = pending.tx_hash();
+        hashes.push(hash);
+
+        match tokio::time::timeout(
+            Duration::from_secs(settings.stuck_after),
+            pending.confirmations(1),
+        )
+        .await
+        {
+            Ok(Ok(Some(receipt))) => return confirmed(agent_id, method, receipt),
+            Ok(Ok(None)) => {
+                if let Some(receipt) = find_receipt(client, &hashes).await {
+                    return confirmed(agent_id, method, receipt);
+                }
+                return dropped(client, hash, method, req.nonce);
+            }
+            Ok(Err(err)) => {
+                error!("Error with transaction confirmation: {:?}", err);
+                return Err(err.into());
+            }
+            Err(_) => {
+                if let Some(receipt) = find_receipt(client, &hashes).await {
+                    return confirmed(agent_id, method, receipt);
+                }
+
+                if replacements >= settings.max_replacements || !bump_fees(&mut req, &settings) {
+                    info!(
+                        "Transaction {:?} for {} still pending, waiting without replacing",
+                        hash, method
+                    );
+                    return wait_for(client, agent_id, hash, method, req.nonce).await;
+                }
+
+                replacements += 1;
+                info!(
+                    "Transaction {:?} for {} stuck after {}s, replacing with max fee {:?} and tip {:?}",
+                    hash, method, settings.stuck_after, req.max_fee_per_gas, req.max_priority_fee_per_gas
+                );
+            }
+        }
+    }
+}
+
+async fn wait_for(
+    client: &SignerMiddleware<Arc<Provider<RetryHttp>>, LocalWallet>,
+    agent_id: u32,
+    hash: H256,
+    method: &str,
+    nonce: Option<U256>,
+) -> Result<SentTransaction, AgentError> {
+    match PendingTransaction::new(hash, client.provider())
+        .confirmations(1)
+        .await?
+    {
+        Some(receipt) => confirmed(agent_id, method, receipt),
+        None => dropped(client, hash, method, nonce),
+    }
+}
+
+fn dropped(
+    client: &SignerMiddleware<Arc<Provider<RetryHttp>>, LocalWallet>,
+    hash: H256,
+    method: &str,
+    nonce: Option<U256>,
+) -> Result<SentTransaction, AgentError> {
+    if let Some(nonce) = nonce {
+        release_nonce(client.address(), nonce);
+    }
+
+    Err(AgentError::chain(format!("Transaction {:?} for {} was dropped", hash, method)))
+}
+
+async fn estimate_gas(
+    client: &SignerMiddleware<Arc<Provider<RetryHttp>>, LocalWallet>,
+    req: &Eip1559TransactionRequest,
+    settings: &GasSettings,
+) -> U256 {
+    match client
+        .estimate_gas(&TypedTransaction::Eip1559(req.clone()), None)
+        .await
+    {
+        Ok(estimate) => {
+            let scaled = (estimate.as_u128() as f64 * settings.multiplier.max(1.0)).ceil();
+            U256::from(scaled as u128)
+        }
+        Err(err) => {
+            error!(
+                "Gas estimation failed, using fallback limit {}: {:?}",
+                settings.fallback_gas, err
+            );
+            settings.fallback_gas
+        }
+    }
+}
+
+async fn estimate_fees(
+    client: &SignerMiddleware<Arc<Provider<RetryHttp>>, LocalWallet>,
+    settings: &GasSettings,
+) -> (U256, U256) {
+    let history = client
+        .fee_history(
+            settings.history_blocks.max(1),
+            BlockNumber::Latest,
+            &[settings.reward_percentile],
+        )
+        .await;
+
+    match history {
+        Ok(history) => {
+            let base_fee: U256 = history.base_fee_per_gas.last().cloned().unwrap_or_default();
+
+            let mut rewards: Vec<U256> = history
+                .reward
+                .iter()
+                .filter_map(|reward| reward.first().cloned())
+                .collect();
+            rewards.sort();
+            let tip = rewards
+                .get(rewards.len() / 2)
+                .cloned()
+                .unwrap_or_default()
+                .min(settings.max_priority_fee);
+
+            let max_fee = (base_fee * U256::from(2) + tip).min(settings.max_fee);
+            if max_fee < base_fee {
+                warn!(
+                    "Fee cap {} is below the current base fee {}, transaction may stay pending",
+                    settings.max_fee, base_fee
+                );
+            }
+
+            (max_fee, tip.min(max_fee))
+        }
+        Err(err) => {
+            warn!("Fee history unavailable, using fee caps: {:?}", err);
+            (settings.max_fee, settings.max_priority_fee.min(settings.max_fee))
+        }
+    }
+}
+
+fn hinted_fees(estimated: (U256, U256), hints: &GasHints, settings: &GasSettings) -> (U256, U256) {
+    let max_fee = hints.max_fee.map_or(estimated.0, |fee| fee.min(settings.max_fee));
+    let max_priority_fee = hints
+        .max_priority_fee
+        .map_or(estimated.1, |tip| tip.min(settings.max_priority_fee))
+        .min(max_fee);
+
+    (max_fee, max_priority_fee)
+}
+
+fn bump_fees(req: &mut Eip1559TransactionRequest, settings: &GasSettings) -> bool {
+    let current_fee = req.max_fee_per_gas.unwrap_or_default();
+    let current_tip = req.max_priority_fee_per_gas.unwrap_or_default();
+
+    let bump = |value: U256, percent: u64| value * (100 + percent) / 100;
+    let min_fee = bump(current_fee, MIN_BUMP_PERCENT);
+    let min_tip = bump(current_tip, MIN_BUMP_PERCENT);
+
+    let next_fee = bump(current_fee, settings.bump_percent).min(settings.max_fee);
+    let next_tip = bump(current_tip, settings.bump_percent)
+        .min(settings.max_priority_fee)
+        .min(next_fee);
+
+    if next_fee < min_fee || next_tip < min_tip {
+        info!("Fee caps reached, can't bump replacement transaction");
+        return false;
+    }
+
+    req.max_fee_per_gas = Some(next_fee);
+    req.max_priority_fee_per_gas = Some(next_tip);
+    true
+}
+
+async fn find_receipt(
+    client: &SignerMiddleware<Arc<Provider<RetryHttp>>, LocalWallet>,
+    hashes: &[H256],
+) -> Option<TransactionReceipt> {
+    for hash in hashes {
+        if let Ok(Some(receipt)) = client.get_transaction_receipt(*hash).await {
+            return Some(receipt);
+        }
+    }
+
+    None
+}
+
+fn confirmed(
+    agent_id: u32,
+    method: &str,
+    receipt: TransactionReceipt,
+) -> Result<SentTransaction, AgentError> {
+    gas_spent(agent_id, method, &receipt);
+    info!(
+        tx_hash = ?receipt.transaction_hash,
+        "{} confirmed (status {:?})",
+        method, receipt.status
+    );
+
+    Ok(SentTransaction {
+        hash: format!("{:?}", receipt.transaction_hash),
+        receipt: Some(receipt),
+    })
+}
+
+pub fn succeeded(receipt: &TransactionReceipt) -> bool {
+    receipt.status == Some(U64::from(1))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gwei(value: u64) -> U256 {
+        U256::from(value) * GWEI
+    }
+
+    fn settings() -> GasSettings {
+        GasSettings {
+            max_fee: gwei(100),
+            max_priority_fee: gwei(10),
+            ..gas_from_env()
+        }
+    }
+
+    #[test]
+    fn lens_fees_replace_estimates_within_caps() {
+        let estimated = (gwei(20), gwei(2));
+
+        assert_eq!(hinted_fees(estimated, &GasHints::default(), &settings()), estimated);
+
+        let hints = GasHints {
+            gas_limit: None,
+            max_fee: Some(gwei(50)),
+            max_priority_fee: Some(gwei(5)),
+        };
+        assert_eq!(hinted_fees(estimated, &hints, &settings()), (gwei(50), gwei(5)));
+
+        let hints = GasHints {
+            gas_limit: None,
+            max_fee: Some(gwei(500)),
+            max_priority_fee: Some(gwei(50)),
+        };
+        assert_eq!(hinted_fees(estimated, &hints, &settings()), (gwei(100), gwei(10)));
+    }
+
+    #[test]
+    fn tip_never_exceeds_max_fee() {
+        let hints = GasHints {
+            gas_limit: None,
+            max_fee: Some(gwei(3)),
+            max_priority_fee: Some(gwei(8)),
+        };
+
+        assert_eq!(hinted_fees((gwei(20), gwei(2)), &hints, &settings()), (gwei(3), gwei(3)));
+    }
+
+    #[test]
+    fn bumps_stop_at_caps() {
+        let mut req = Eip1559TransactionRequest {
+            max_fee_per_gas: Some(gwei(50)),
+            max_priority_fee_per_gas: Some(gwei(5)),
+            ..Default::default()
+        };
+        let settings = GasSettings {
+            bump_percent: 15,
+            ..settings()
+        };
+
+        assert!(bump_fees(&mut req, &settings));
+        assert_eq!(req.max_fee_per_gas, Some(gwei(50) * 115 / 100));
+
+        req.max_fee_per_gas = Some(gwei(100));
+        assert!(!bump_fees(&mut req, &settings));
+    }
+}
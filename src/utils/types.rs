@@ -1,14 +1,20 @@
+use crate::utils::bindings::{
+    SignerClient, TripleAAccessControls, TripleAAgents, TripleACollectionManager, TripleAMarket,
+};
+use crate::utils::{store::task_outcome, subgraph::decimal};
 use ethers::{
-    abi::{InvalidOutputType, Token, Tokenizable, TokenizableItem},
-    contract::ContractInstance,
-    core::k256::ecdsa::SigningKey,
-    middleware::SignerMiddleware,
-    providers::{Http, Provider},
-    signers::Wallet,
-    types::{Address, U256},
+    providers::{Http, HttpClientError},
+    types::{Address, TransactionReceipt, U256},
 };
+use reqwest::Client;
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+use tokio::sync::Notify;
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct Text {
@@ -39,6 +45,193 @@ pub struct TripleAAgent {
     pub last_active_time: u32,
     pub account_address: String,
     pub feeds: Vec<String>,
+    pub feed_frequency: u32,
+    pub schedule: AgentSchedule,
+    pub gas: GasSettings,
+    pub llm: LlmSettings,
+    pub image: ImageSettings,
+    pub respond: RespondSettings,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RespondSettings {
+    pub frequency: u32,
+    pub max_replies: u32,
+    pub daily_limit: u32,
+    pub lookback: u64,
+    pub collections: BTreeMap<String, RespondWorker>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub struct RespondWorker {
+    pub frequency: u32,
+    pub max_replies: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct LlmSettings {
+    pub provider: LlmBackend,
+    pub base_url: Option<String>,
+    pub api_key_env: Option<String>,
+    pub model: Option<String>,
+    pub fixtures_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum LlmBackend {
+    Venice,
+    OpenAi,
+    Fixture,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ImageSettings {
+    pub provider: ImageBackend,
+    pub base_url: Option<String>,
+    pub model: Option<String>,
+    pub fixture: Option<PathBuf>,
+    pub img2img: bool,
+    pub strength: Option<f32>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum ImageBackend {
+    Venice,
+    Local,
+    Fixture,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct GasHints {
+    pub gas_limit: Option<U256>,
+    pub max_fee: Option<U256>,
+    pub max_priority_fee: Option<U256>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct GasSettings {
+    pub multiplier: f64,
+    pub max_fee: U256,
+    pub max_priority_fee: U256,
+    pub fallback_gas: U256,
+    pub history_blocks: u64,
+    pub reward_percentile: f64,
+    pub stuck_after: u64,
+    pub bump_percent: u64,
+    pub max_replacements: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct AgentSchedule {
+    pub cron: Vec<String>,
+    pub windows: Vec<u32>,
+    pub weekdays: Vec<u32>,
+    pub timezone: String,
+    pub catch_up: CatchUpPolicy,
+    pub grace: u32,
+    pub span: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RosterFile {
+    pub agents: Vec<RosterEntry>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RosterEntry {
+    pub id: u32,
+    pub name: String,
+    #[serde(default)]
+    pub bio: String,
+    #[serde(default)]
+    pub lore: String,
+    #[serde(default)]
+    pub style: String,
+    #[serde(default)]
+    pub knowledge: String,
+    #[serde(default)]
+    pub adjectives: String,
+    #[serde(default)]
+    pub message_examples: Vec<Vec<serde_json::Value>>,
+    pub model: Option<String>,
+    #[serde(default)]
+    pub cover: String,
+    pub custom_instructions: Option<String>,
+    pub wallet: String,
+    pub account_address: Option<String>,
+    pub private_key: Option<String>,
+    pub clock: Option<u32>,
+    #[serde(default)]
+    pub feeds: Vec<String>,
+    pub feed_frequency: Option<u32>,
+    pub schedule: Option<RosterSchedule>,
+    pub gas: Option<RosterGas>,
+    pub llm: Option<RosterLlm>,
+    pub image: Option<RosterImage>,
+    pub respond: Option<RosterRespond>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RosterRespond {
+    pub frequency: Option<u32>,
+    pub max_replies: Option<u32>,
+    pub daily_limit: Option<u32>,
+    pub lookback_hours: Option<u64>,
+    #[serde(default)]
+    pub collections: HashMap<String, RosterRespondWorker>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct RosterRespondWorker {
+    pub frequency: Option<u32>,
+    pub max_replies: Option<u32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RosterImage {
+    pub provider: Option<String>,
+    pub base_url: Option<String>,
+    pub model: Option<String>,
+    pub fixture: Option<String>,
+    pub img2img: Option<bool>,
+    pub strength: Option<f32>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RosterLlm {
+    pub provider: Option<String>,
+    pub base_url: Option<String>,
+    pub api_key_env: Option<String>,
+    pub model: Option<String>,
+    pub fixtures_dir: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RosterGas {
+    pub max_fee_gwei: Option<u64>,
+    pub max_priority_fee_gwei: Option<u64>,
+    pub multiplier: Option<f64>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct RosterSchedule {
+    #[serde(default)]
+    pub cron: Vec<String>,
+    #[serde(default)]
+    pub windows: Vec<String>,
+    #[serde(default)]
+    pub weekdays: Vec<String>,
+    pub timezone: Option<String>,
+    pub catch_up: Option<String>,
+    pub grace: Option<u32>,
+    pub span: Option<u32>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum CatchUpPolicy {
+    Skip,
+    Once,
+    All,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
@@ -52,37 +245,103 @@ pub struct TripleAWorker {
     pub remix_frequency: U256,
     pub mint_frequency: U256,
     pub instructions: String,
+    #[serde(default)]
+    pub respond: bool,
+    #[serde(default)]
+    pub respond_frequency: U256,
+    #[serde(default)]
+    pub feed: bool,
+    #[serde(default)]
+    pub feed_frequency: U256,
 }
 
 #[derive(Debug, Clone)]
 pub struct AgentManager {
     pub agent: TripleAAgent,
     pub current_queue: Vec<AgentActivity>,
-    pub agents_contract: Arc<
-        ContractInstance<
-            Arc<SignerMiddleware<Arc<Provider<Http>>, Wallet<SigningKey>>>,
-            SignerMiddleware<Arc<Provider<Http>>, Wallet<SigningKey>>,
-        >,
-    >,
-    pub market_contract: Arc<
-        ContractInstance<
-            Arc<SignerMiddleware<Arc<Provider<Http>>, Wallet<SigningKey>>>,
-            SignerMiddleware<Arc<Provider<Http>>, Wallet<SigningKey>>,
-        >,
-    >,
-    pub access_controls_contract: Arc<
-        ContractInstance<
-            Arc<SignerMiddleware<Arc<Provider<Http>>, Wallet<SigningKey>>>,
-            SignerMiddleware<Arc<Provider<Http>>, Wallet<SigningKey>>,
-        >,
-    >,
-    pub collection_manager_contract: Arc<
-        ContractInstance<
-            Arc<SignerMiddleware<Arc<Provider<Http>>, Wallet<SigningKey>>>,
-            SignerMiddleware<Arc<Provider<Http>>, Wallet<SigningKey>>,
-        >,
-    >,
+    pub agents_contract: Arc<TripleAAgents<SignerClient>>,
+    pub market_contract: Arc<TripleAMarket<SignerClient>>,
+    pub access_controls_contract: Arc<TripleAAccessControls<SignerClient>>,
+    pub collection_manager_contract: Arc<TripleACollectionManager<SignerClient>>,
     pub tokens: Option<SavedTokens>,
+    pub store: AgentStore,
+}
+
+#[derive(Debug, Clone)]
+pub struct AgentStore {
+    pub agent_id: u32,
+    pub path: PathBuf,
+    pub timezone: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind")]
+pub enum StoreRecord {
+    Tokens {
+        tokens: SavedTokens,
+    },
+    Active {
+        at: i64,
+        last_active_time: u32,
+    },
+    Planned {
+        run: i64,
+        day: String,
+        activity: Box<AgentActivity>,
+        tasks: Vec<ActivityType>,
+    },
+    Completed {
+        run: i64,
+        day: String,
+        collection_id: U256,
+        index: usize,
+        task: ActivityType,
+        #[serde(alias = "success", deserialize_with = "task_outcome")]
+        outcome: TaskOutcome,
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        error: Option<String>,
+        hashes: Vec<String>,
+        at: i64,
+    },
+    Cancelled {
+        run: i64,
+        day: String,
+        collection_id: U256,
+        index: usize,
+        task: ActivityType,
+        at: i64,
+    },
+    Skipped {
+        run: i64,
+        day: String,
+        collection_id: U256,
+        index: usize,
+        task: ActivityType,
+        at: i64,
+    },
+    Replied {
+        post_id: String,
+        day: String,
+        at: i64,
+    },
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct StoreState {
+    pub tokens: Option<SavedTokens>,
+    pub last_active_time: Option<u32>,
+    pub plans: Vec<PlannedRun>,
+    pub replied: HashSet<String>,
+    pub replied_today: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct PlannedRun {
+    pub run: i64,
+    pub day: String,
+    pub activity: AgentActivity,
+    pub tasks: Vec<ActivityType>,
+    pub completed: HashSet<usize>,
 }
 
 #[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
@@ -124,7 +383,7 @@ pub struct IPFSResponse {
     Size: String,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Clone)]
 pub struct LensTokens {
     pub access_token: String,
     pub refresh_token: String,
@@ -156,139 +415,531 @@ pub struct Content {
     pub image: Option<Image>,
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SavedTokens {
     pub tokens: LensTokens,
     pub expiry: i64,
 }
-#[derive(Debug, Clone)]
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Hash)]
 pub enum ActivityType {
     Publish,
     Lead,
     Remix,
     Mint,
+    Respond,
+    Feed,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
 pub struct PriceCollection {
+    #[serde(deserialize_with = "decimal")]
     pub collectionId: U256,
+    #[serde(deserialize_with = "decimal")]
     pub amountSold: U256,
+    #[serde(deserialize_with = "decimal")]
     pub amount: U256,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub struct CollectionInput {
-    pub tokens: Vec<Address>,
-    pub prices: Vec<U256>,
-    pub agentIds: Vec<U256>,
-    pub metadata: String,
-    pub forArtist: Address,
-    pub collectionType: u8,
-    pub amount: U256,
-    pub fulfillerId: U256,
-    pub remixId: U256,
-    pub remixable: bool,
+#[derive(Debug, Clone, PartialEq)]
+pub enum FixtureMode {
+    Off,
+    Replay,
+    Record,
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
-pub struct CollectionWorker {
-    pub instructions: String,
-    pub publishFrequency: U256,
-    pub remixFrequency: U256,
-    pub leadFrequency: U256,
-    pub mintFrequency: U256,
+#[derive(Debug, Clone)]
+pub struct DryRunSettings {
+    pub enabled: bool,
+    pub report: PathBuf,
+    pub fixtures: FixtureMode,
+    pub fixtures_dir: PathBuf,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RentQuote {
+    pub token: Address,
+    pub rent: U256,
+    pub balance: U256,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RentPlanEntry {
+    pub collection_id: U256,
+    pub token: Address,
+    pub rent: U256,
+    pub balance: U256,
+}
+
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct RentPlan {
+    pub funded: Vec<RentPlanEntry>,
+    pub exempt: Vec<U256>,
+    pub unfunded: Vec<U256>,
+    pub quotes: Vec<(U256, Vec<RentQuote>)>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SentTransaction {
+    pub hash: String,
+    pub receipt: Option<TransactionReceipt>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct NonceState {
+    pub next: Option<U256>,
+    pub released: BTreeSet<U256>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SubgraphClient {
+    pub client: Client,
+    pub url: String,
+    pub timeout: Duration,
+    pub page_size: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GraphQLResponse<T> {
+    pub data: Option<T>,
+    #[serde(default)]
+    pub errors: Vec<GraphQLError>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GraphQLError {
+    pub message: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct SubgraphError {
+    pub field: String,
+    pub messages: Vec<String>,
+}
+
+#[derive(Debug)]
+pub enum AgentError {
+    Venice { status: Option<u16>, message: String },
+    Llm { status: Option<u16>, message: String },
+    Image { status: Option<u16>, message: String },
+    Lens { message: String, retryable: bool },
+    Ipfs(String),
+    Subgraph(SubgraphError),
+    Chain { message: String, retryable: bool },
+    Http(reqwest::Error),
+    Io(std::io::Error),
+    Config(String),
+    Parse(String),
+    Unavailable { upstream: Upstream, retry_in: Duration },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Upstream {
+    Venice,
+    Lens,
+    Grove,
+    Ipfs,
+    Subgraph,
+    Rpc,
+    Llm,
+    Image,
+}
+
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    pub attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+    pub breaker_threshold: u32,
+    pub breaker_cooldown: Duration,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Breaker {
+    pub failures: u32,
+    pub open_until: Option<Instant>,
+}
+
+#[derive(Debug)]
+pub struct RetryHttp {
+    pub inner: Http,
+}
+
+#[derive(Debug)]
+pub enum RetryHttpError {
+    Http(HttpClientError),
+    Unavailable(Duration),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskOutcome {
+    Succeeded,
+    Retryable,
+    Permanent,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubgraphAgent {
+    #[serde(default)]
+    pub wallets: Vec<String>,
+    #[serde(rename = "SkyhuntersAgentManager_id", deserialize_with = "decimal")]
+    pub id: U256,
+    pub uri: Option<String>,
+    pub metadata: Option<SubgraphAgentMetadata>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubgraphAgentMetadata {
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub bio: String,
+    #[serde(default)]
+    pub lore: String,
+    #[serde(default)]
+    pub adjectives: String,
+    #[serde(default)]
+    pub style: String,
+    #[serde(default)]
+    pub knowledge: String,
+    #[serde(default, alias = "message_examples")]
+    pub message_examples: Vec<Vec<serde_json::Value>>,
+    pub model: Option<String>,
+    #[serde(default)]
+    pub cover: String,
+    #[serde(default)]
+    pub custom_instructions: String,
+    #[serde(default)]
+    pub feeds: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubgraphAgentWorkers {
+    #[serde(default)]
+    pub balances: Vec<SubgraphBalance>,
+    #[serde(default)]
+    pub workers: Vec<SubgraphWorker>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubgraphBalance {
+    #[serde(deserialize_with = "decimal")]
+    pub rent_balance: U256,
+    #[serde(deserialize_with = "decimal")]
+    pub bonus_balance: U256,
+    #[serde(deserialize_with = "decimal")]
+    pub collection_id: U256,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubgraphWorker {
     pub publish: bool,
     pub remix: bool,
     pub lead: bool,
     pub mint: bool,
+    #[serde(deserialize_with = "decimal")]
+    pub lead_frequency: U256,
+    #[serde(deserialize_with = "decimal")]
+    pub publish_frequency: U256,
+    #[serde(deserialize_with = "decimal")]
+    pub mint_frequency: U256,
+    #[serde(deserialize_with = "decimal")]
+    pub remix_frequency: U256,
+    #[serde(default)]
+    pub instructions: String,
+    #[serde(deserialize_with = "decimal")]
+    pub collection_id: U256,
+    pub collection: Option<SubgraphCollection>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubgraphCollection {
+    #[serde(default)]
+    pub artist: String,
+    pub uri: Option<String>,
+    pub metadata: Option<SubgraphCollectionMetadata>,
+    #[serde(default)]
+    pub prices: Vec<SubgraphPrice>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SubgraphCollectionMetadata {
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub title: String,
+    #[serde(default)]
+    pub image: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubgraphPrice {
+    #[serde(deserialize_with = "decimal")]
+    pub price: U256,
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct SubgraphTokenDetails {
+    #[serde(deserialize_with = "decimal")]
+    pub threshold: U256,
+    pub token: String,
+    #[serde(deserialize_with = "decimal")]
+    pub base: U256,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SubgraphRemix {
+    #[serde(deserialize_with = "decimal")]
+    pub drop_id: U256,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct NetworkProfile {
+    pub name: String,
+    pub chain_id: u64,
+    pub rpc_url: String,
+    pub lens_api: String,
+    pub lens_origin: String,
+    pub grove_api: String,
+    pub ipfs_api: String,
+    pub ipfs_gateway: String,
+    pub venice_api: String,
+    pub subgraph_url: String,
+    pub agents: String,
+    pub access_controls: String,
+    pub collection_manager: String,
+    pub market: String,
+    pub wgho: String,
+    pub bonsai: String,
+    pub mona: String,
+    pub remix_feed: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct NetworkEntry {
+    pub chain_id: Option<u64>,
+    pub rpc_url: Option<String>,
+    pub lens_api: Option<String>,
+    pub lens_origin: Option<String>,
+    pub grove_api: Option<String>,
+    pub ipfs_api: Option<String>,
+    pub ipfs_gateway: Option<String>,
+    pub venice_api: Option<String>,
+    pub subgraph_url: Option<String>,
+    pub agents: Option<String>,
+    pub access_controls: Option<String>,
+    pub collection_manager: Option<String>,
+    pub market: Option<String>,
+    pub wgho: Option<String>,
+    pub bonsai: Option<String>,
+    pub mona: Option<String>,
+    pub remix_feed: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct AgentControl {
+    pub agent: TripleAAgent,
+    pub store: AgentStore,
+    pub queue: Vec<AgentActivity>,
+    pub last_activity: Option<i64>,
+    pub paused: bool,
+    pub generation: u64,
+    pub trigger: Arc<Notify>,
+    pub resumed: Arc<Notify>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ControlSettings {
+    pub addr: std::net::SocketAddr,
+    pub token: String,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct ChatMessage {
+    pub role: String,
+    pub content: String,
 }
 
-impl Tokenizable for CollectionInput {
-    fn from_token(token: Token) -> Result<Self, InvalidOutputType> {
-        match token {
-            Token::Tuple(tokens) if tokens.len() == 10 => Ok(Self {
-                tokens: tokens[0]
-                    .clone()
-                    .into_array()
-                    .unwrap()
-                    .into_iter()
-                    .map(|t| t.into_address().unwrap())
-                    .collect(),
-                prices: tokens[1]
-                    .clone()
-                    .into_array()
-                    .unwrap()
-                    .into_iter()
-                    .map(|t| t.into_uint().unwrap())
-                    .collect(),
-                agentIds: tokens[2]
-                    .clone()
-                    .into_array()
-                    .unwrap()
-                    .into_iter()
-                    .map(|t| t.into_uint().unwrap())
-                    .collect(),
-                metadata: tokens[3].clone().into_string().unwrap(),
-                forArtist: tokens[4].clone().into_address().unwrap(),
-                collectionType: tokens[5].clone().into_int().unwrap().as_u32() as u8,
-                amount: tokens[6].clone().into_uint().unwrap(),
-                fulfillerId: tokens[7].clone().into_uint().unwrap(),
-                remixId: tokens[8].clone().into_uint().unwrap(),
-                remixable: tokens[9].clone().into_bool().unwrap(),
-            }),
-            _ => Err(InvalidOutputType(String::from("conversion error"))),
-        }
-    }
-
-    fn into_token(self) -> Token {
-        Token::Tuple(vec![
-            Token::Array(self.tokens.into_iter().map(Token::Address).collect()),
-            Token::Array(self.prices.into_iter().map(Token::Uint).collect()),
-            Token::Array(self.agentIds.into_iter().map(Token::Uint).collect()),
-            Token::String(self.metadata),
-            Token::Address(self.forArtist),
-            Token::Uint(U256::from(self.collectionType)),
-            Token::Uint(self.amount),
-            Token::Uint(self.fulfillerId),
-            Token::Uint(self.remixId),
-            Token::Bool(self.remixable),
-        ])
-    }
-}
-
-impl Tokenizable for CollectionWorker {
-    fn from_token(token: Token) -> Result<Self, InvalidOutputType> {
-        match token {
-            Token::Tuple(tokens) if tokens.len() == 9 => Ok(Self {
-                instructions: tokens[0].clone().into_string().unwrap(),
-                publishFrequency: tokens[1].clone().into_uint().unwrap(),
-                remixFrequency: tokens[2].clone().into_uint().unwrap(),
-                leadFrequency: tokens[3].clone().into_uint().unwrap(),
-                mintFrequency: tokens[4].clone().into_uint().unwrap(),
-                publish: tokens[5].clone().into_bool().unwrap(),
-                remix: tokens[6].clone().into_bool().unwrap(),
-                lead: tokens[7].clone().into_bool().unwrap(),
-                mint: tokens[8].clone().into_bool().unwrap(),
-            }),
-            _ => Err(InvalidOutputType(String::from("conversion error"))),
-        }
-    }
-
-    fn into_token(self) -> Token {
-        Token::Tuple(vec![
-            Token::String(self.instructions),
-            Token::Uint(self.publishFrequency),
-            Token::Uint(self.remixFrequency),
-            Token::Uint(self.leadFrequency),
-            Token::Uint(self.mintFrequency),
-            Token::Bool(self.publish),
-            Token::Bool(self.remix),
-            Token::Bool(self.lead),
-            Token::Bool(self.mint),
-        ])
-    }
-}
-
-impl TokenizableItem for CollectionWorker {}
-
-impl TokenizableItem for CollectionInput {}
+#[derive(Debug, Clone, Default)]
+pub struct Persona {
+    pub instructions: String,
+    pub examples: Vec<ChatMessage>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ChatRequest {
+    pub task: String,
+    pub model: String,
+    pub messages: Vec<ChatMessage>,
+    pub max_tokens: u32,
+}
+
+pub struct VeniceLlm {
+    pub client: Client,
+    pub api_key: String,
+}
+
+pub struct OpenAiLlm {
+    pub client: Client,
+    pub base_url: String,
+    pub api_key: Option<String>,
+}
+
+pub struct FixtureLlm {
+    pub fixtures_dir: Option<PathBuf>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImageRequest {
+    pub task: String,
+    pub model: String,
+    pub prompt: String,
+    pub negative_prompt: Option<String>,
+    pub width: u32,
+    pub height: u32,
+    pub seed: Option<u64>,
+    pub style_preset: Option<String>,
+    pub source: Option<Vec<u8>>,
+    pub strength: Option<f32>,
+}
+
+#[derive(Debug, Clone)]
+pub struct GeneratedImage {
+    pub bytes: Vec<u8>,
+    pub mime_type: String,
+}
+
+pub struct VeniceImage {
+    pub client: Client,
+    pub api_key: String,
+}
+
+pub struct LocalImage {
+    pub client: Client,
+    pub base_url: String,
+    pub checkpoint: Option<String>,
+}
+
+pub struct FixtureImage {
+    pub fixture: Option<PathBuf>,
+}
+
+// Keys that a running task is working on, such as a feed or a post being replied to.
+#[derive(Debug, Default)]
+pub struct Claims(pub Mutex<BTreeSet<String>>);
+
+#[derive(Debug, Clone)]
+pub struct AgentMemory {
+    pub agent_id: u32,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+pub enum MemoryKind {
+    Post,
+    Comment,
+    Quote,
+    Reply,
+    Feed,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MemoryEntry {
+    pub at: i64,
+    pub kind: MemoryKind,
+    pub collection_id: U256,
+    pub collection_title: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub replied_to: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub feed: Option<String>,
+    pub text: String,
+    pub hash: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct FeedInfo {
+    pub title: String,
+    pub description: String,
+    pub rules: Vec<String>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct MemoryQuery {
+    pub author: Option<String>,
+    pub collection_id: Option<U256>,
+    pub text: String,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PromptVars {
+    pub collection_title: String,
+    pub collection_description: String,
+    pub custom_instructions: String,
+    pub collection_instructions: String,
+    pub max_tokens: u32,
+    pub post_content: String,
+    pub feed_title: String,
+    pub feed_description: String,
+    pub price_ranges: Vec<(U256, U256)>,
+    pub memory: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct AgentAnalytics {
+    pub agent_id: u32,
+    pub path: PathBuf,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct PostStats {
+    pub reactions: u64,
+    pub comments: u64,
+    pub quotes: u64,
+    pub collects: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AnalyticsPost {
+    pub at: i64,
+    pub task: ActivityType,
+    pub collection_id: U256,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub post_id: Option<String>,
+    #[serde(default)]
+    pub stats: PostStats,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub checked: Option<i64>,
+    #[serde(default)]
+    pub misses: u32,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SalesSample {
+    pub at: i64,
+    pub sold: U256,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct AnalyticsTable {
+    #[serde(default)]
+    pub posts: BTreeMap<String, AnalyticsPost>,
+    #[serde(default)]
+    pub sales: BTreeMap<String, Vec<SalesSample>>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct AnalyticsWeights {
+    pub activities: HashMap<ActivityType, f64>,
+    pub collections: HashMap<U256, f64>,
+}